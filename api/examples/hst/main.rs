@@ -13,7 +13,7 @@ fn main() -> anyhow::Result<()> {
     let hst = Hst::new();
     hst.show_pupil(Option::<&str>::None)?;
     let mut field: Field<Hst> = FieldBuilder::new(hst)
-        .pixel_scale(PixelScale::NyquistAt(2, "V".to_string()))
+        .pixel_scale(PixelScale::nyquist_at(2, "V"))
         .field_of_view(21)
         .photometry("K")
         .build();
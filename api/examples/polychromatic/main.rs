@@ -7,8 +7,8 @@ use eyepiece::{
 fn main() -> anyhow::Result<()> {
     let hst = Hst::new();
     let mut field: PolychromaticField<Hst> = FieldBuilder::new(hst)
-        .pixel_scale(PixelScale::NyquistFractionAt(2, "V".to_string()))
-        .field_of_view(FieldOfView::PixelScaleAt(61, "K".to_string()))
+        .pixel_scale(PixelScale::nyquist_fraction_at(2, "V"))
+        .field_of_view(FieldOfView::pixel_scale_at(61, "K"))
         .polychromatic(PhotometricBands::default().into_iter().collect())
         .flux(1f64)
         .build();
@@ -1,6 +1,6 @@
 use eyepiece::{
     Builder, Field, FieldBuilder, Gmt, Hst, Jwst, MagnitudeDistribution, PixelScale, SaveOptions,
-    Star, StarDistribution,
+    SkyBackground, Star, StarDistribution,
 };
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use skyangle::SkyAngle;
@@ -60,6 +60,7 @@ fn main() -> anyhow::Result<()> {
         .photometry(field_band)
         .objects((&coordinates, &magnitudes))
         .exposure(exposure)
+        .sky_background(SkyBackground::default())
         .photon_noise()
         .build();
     println!("{gmt_field}");
@@ -5,30 +5,110 @@ use num_traits::Zero;
 use serde::Serialize;
 use skyangle::SkyAngle;
 
-use crate::{optust, Star, ZpDft};
+use crate::{
+    optust, Error, GroundLayerAdaptiveOptics, LaserTomographyAdaptiveOptics,
+    MultiConjugateAdaptiveOptics, Photometry, Result, Star, TurbulenceProfile, ZpDft,
+};
 
 const DELTA_0: f64 = 2.5e-2;
+/// Default frame rate of the AO control loop [Hz]
+const FRAME_RATE: f64 = 1e3;
+/// Integral gain of the AO control loop
+const LOOP_GAIN: f64 = 0.5;
 
-#[derive(Debug)]
-struct TurbulenceProfile {
-    height: Vec<f64>,
-    weight: Vec<f64>,
+/// Number of samples across the pupil footprints used to compute the coverage of the guide stars
+const N_SAMPLE: usize = 32;
+
+/// Checks that the pupil sampling `d` is large enough for the AO transfer function
+pub(crate) fn check_pupil_sampling(d: f64) -> Result<()> {
+    if d < DELTA_0 {
+        Err(Error::PupilSampling(d))
+    } else {
+        Ok(())
+    }
 }
-impl TurbulenceProfile {
-    pub fn new() -> Self {
-        Self {
-            height: vec![25., 275., 425., 1250., 4000., 8000., 13000.],
-            weight: vec![0.1257, 0.0874, 0.0666, 0.3498, 0.2273, 0.0681, 0.0751],
+
+/// Returns the fraction of the pupil footprint of the star at the altitude `height` in meters
+/// that is covered by the footprints of the guide stars, for a pupil of the given diameter in meters
+///
+/// The footprints of guide stars at the finite altitude `cone_height` in meters are shrunk by the cone effect
+pub(crate) fn footprint_coverage(
+    star: &Star,
+    guide_stars: &[Star],
+    height: f64,
+    diameter: f64,
+    cone_height: Option<f64>,
+) -> f64 {
+    let (x_star, y_star) = star.coordinates;
+    let center = (height * x_star.to_radians(), height * y_star.to_radians());
+    let radius = 0.5 * diameter;
+    let cone_radius = cone_height.map_or(radius, |cone_height| {
+        radius * (1. - height / cone_height).max(0f64)
+    });
+    let footprints: Vec<_> = guide_stars
+        .iter()
+        .map(|guide_star| {
+            let (x, y) = guide_star.coordinates;
+            (height * x.to_radians(), height * y.to_radians())
+        })
+        .collect();
+    let step = diameter / N_SAMPLE as f64;
+    let (mut n, mut n_covered) = (0usize, 0usize);
+    for i in 0..N_SAMPLE {
+        let y = (i as f64 + 0.5) * step - radius;
+        for j in 0..N_SAMPLE {
+            let x = (j as f64 + 0.5) * step - radius;
+            if x.hypot(y) > radius {
+                continue;
+            }
+            n += 1;
+            let (x, y) = (x + center.0, y + center.1);
+            if footprints
+                .iter()
+                .any(|(xc, yc)| (x - xc).hypot(y - yc) <= cone_radius)
+            {
+                n_covered += 1;
+            }
         }
     }
+    n_covered as f64 / n as f64
 }
-impl IntoIterator for TurbulenceProfile {
-    type Item = (f64, f64);
-
-    type IntoIter = std::iter::Zip<std::vec::IntoIter<f64>, std::vec::IntoIter<f64>>;
 
-    fn into_iter(self) -> Self::IntoIter {
-        self.height.into_iter().zip(self.weight.into_iter())
+/// Sampling of the telescope pupil by the actuators of a deformable mirror
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum Actuators {
+    /// actuator pitch [m]
+    Pitch(f64),
+    /// number of actuators across the pupil
+    Count(usize),
+}
+impl Actuators {
+    /// Returns the actuator pitch in meters for a pupil of the given diameter in meters
+    pub fn pitch(&self, diameter: f64) -> f64 {
+        match self {
+            Actuators::Pitch(pitch) => *pitch,
+            Actuators::Count(count) => diameter / *count as f64,
+        }
+    }
+    /// Checks that the actuator pitch or count is positive
+    pub(crate) fn check(self) -> Result<Self> {
+        match self {
+            Actuators::Pitch(pitch) if pitch <= 0f64 => Err(Error::AdaptiveOptics(format!(
+                "the actuator pitch must be positive, found {pitch}"
+            ))),
+            Actuators::Count(0) => Err(Error::AdaptiveOptics(String::from(
+                "the number of actuators must be positive",
+            ))),
+            _ => Ok(self),
+        }
+    }
+}
+impl Display for Actuators {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Actuators::Pitch(pitch) => write!(f, "{:.3}m actuator pitch", pitch),
+            Actuators::Count(count) => write!(f, "{} actuators across the pupil", count),
+        }
     }
 }
 
@@ -36,6 +116,7 @@ impl IntoIterator for TurbulenceProfile {
 struct TransferFunction {
     fft: ZpDft,
     d: f64,
+    diameter: f64,
     n_otf: usize,
     kappa: usize,
     fitting_cutoff: f64,
@@ -45,6 +126,7 @@ impl Clone for TransferFunction {
         Self {
             fft: ZpDft::forward(self.fft.len()),
             d: self.d.clone(),
+            diameter: self.diameter,
             n_otf: self.n_otf.clone(),
             kappa: self.kappa.clone(),
             fitting_cutoff: self.fitting_cutoff.clone(),
@@ -52,9 +134,9 @@ impl Clone for TransferFunction {
     }
 }
 impl TransferFunction {
-    pub fn new(n_otf: usize, d: f64) -> Self {
-        if d < DELTA_0 {
-            panic!("Pupil sampling is too small, must be greater or equal to 2.5cm")
+    pub fn new(n_otf: usize, d: f64, diameter: f64) -> Self {
+        if let Err(e) = check_pupil_sampling(d) {
+            panic!("{e}")
         }
         let kappa = (d / DELTA_0).ceil() as usize;
         let n = usize::max(kappa * n_otf, 4096);
@@ -62,17 +144,23 @@ impl TransferFunction {
             fft: ZpDft::forward(n),
             n_otf,
             d,
+            diameter,
             kappa,
             fitting_cutoff: 0f64,
         }
     }
+    /// Sets the spatial frequency below which the turbulence is corrected
+    ///
+    /// The variance of the uncorrected phase is set to `-ln(S)` according to
+    /// the extended Maréchal approximation `S=exp(-var)`, that holds for partial corrections
+    /// as long as the phase is corrected at the scale of the Fried parameter
     pub fn fitting_cutoff_frequency(
         &mut self,
         strehl_ratio: f64,
         fried_parameter: f64,
         outer_scale: f64,
     ) -> &mut Self {
-        let var_lim = 2f64 * (1f64 - strehl_ratio.sqrt());
+        let var_lim = -strehl_ratio.ln();
         let n = 4096 * 2;
         let delta = 1e-2_f64;
         for i in 0..n {
@@ -97,53 +185,297 @@ impl TransferFunction {
 }
 #[derive(Debug, Clone, Serialize)]
 pub struct AdaptiveOpticsCorrection {
-    strehl_ratio: f64,
+    strehl_ratio: Option<f64>,
+    actuators: Option<Actuators>,
     guide_star: Option<Star>,
     laser_guide_star_radius: Option<SkyAngle<f64>>,
+    ground_layer: Option<GroundLayerAdaptiveOptics>,
+    laser_tomography: Option<LaserTomographyAdaptiveOptics>,
+    multi_conjugate: Option<MultiConjugateAdaptiveOptics>,
+    control_loop: Option<(f64, f64)>,
+    wavefront_sensor: Option<(Photometry, f64)>,
     #[serde(skip)]
     transfer_function: Option<TransferFunction>,
 }
 impl Display for AdaptiveOpticsCorrection {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "adaptive optics correction")?;
-        writeln!(f, " . Strehl ratio: {:.3}", self.strehl_ratio)?;
+        if let Some(strehl_ratio) = self.strehl_ratio {
+            writeln!(f, " . Strehl ratio: {:.3}", strehl_ratio)?;
+        }
+        if let Some(actuators) = self.actuators {
+            writeln!(f, " . {}", actuators)?;
+        }
+        if let Some(glao) = self.ground_layer.as_ref() {
+            write!(f, " . {}", glao)?;
+        }
+        if let Some(ltao) = self.laser_tomography.as_ref() {
+            write!(f, " . {}", ltao)?;
+        }
+        if let Some(mcao) = self.multi_conjugate.as_ref() {
+            write!(f, " . {}", mcao)?;
+        }
         if let Some(guide_star) = self.guide_star {
             writeln!(f, " . guide {}", guide_star)?;
         }
         if let Some(lgs_radius) = self.laser_guide_star_radius {
             writeln!(f, " . LGS radius: {}arsec", lgs_radius)?;
         }
+        if let Some((frame_rate, delay)) = self.control_loop {
+            writeln!(
+                f,
+                " . control loop: {:.0}Hz, {:.3}ms delay",
+                frame_rate,
+                delay * 1e3
+            )?;
+        }
+        if let Some((band, throughput)) = self.wavefront_sensor.as_ref() {
+            writeln!(
+                f,
+                " . wavefront sensor: {} band, {:.3} throughput",
+                band, throughput
+            )?;
+        }
         Ok(())
     }
 }
 impl AdaptiveOpticsCorrection {
+    /// Returns the AO Strehl ratio
+    ///
+    /// Returns `None` for the systems defined by their actuator pitch
+    pub fn strehl_ratio(&self) -> Option<f64> {
+        self.strehl_ratio
+    }
+    /// Returns the actuator sampling of the NGAO and LTAO systems defined by their actuators
+    pub fn actuators(&self) -> Option<Actuators> {
+        self.actuators
+    }
     pub fn ngao(strehl_ratio: f64, guide_star: Option<Star>) -> Self {
         Self {
-            strehl_ratio,
+            strehl_ratio: Some(strehl_ratio),
+            actuators: None,
             guide_star,
             laser_guide_star_radius: None,
+            ground_layer: None,
+            laser_tomography: None,
+            multi_conjugate: None,
+            control_loop: None,
+            wavefront_sensor: None,
             transfer_function: None,
         }
     }
     pub fn ltao(strehl_ratio: f64, laser_guide_star_radius: SkyAngle<f64>) -> Self {
         Self {
-            strehl_ratio,
+            strehl_ratio: Some(strehl_ratio),
+            actuators: None,
             guide_star: None,
             laser_guide_star_radius: Some(laser_guide_star_radius),
+            ground_layer: None,
+            laser_tomography: None,
+            multi_conjugate: None,
+            control_loop: None,
+            wavefront_sensor: None,
+            transfer_function: None,
+        }
+    }
+    /// Creates a NGAO correction with the fitting error set by the deformable mirror actuators
+    pub fn ngao_actuators(actuators: Actuators, guide_star: Option<Star>) -> Self {
+        Self {
+            strehl_ratio: None,
+            actuators: Some(actuators),
+            ..Self::ngao(1f64, guide_star)
+        }
+    }
+    /// Creates a LTAO correction with the fitting error set by the deformable mirror actuators
+    pub fn ltao_actuators(actuators: Actuators, laser_guide_star_radius: SkyAngle<f64>) -> Self {
+        Self {
+            strehl_ratio: None,
+            actuators: Some(actuators),
+            ..Self::ltao(1f64, laser_guide_star_radius)
+        }
+    }
+    /// Returns the GLAO configuration
+    pub fn ground_layer(&self) -> Option<&GroundLayerAdaptiveOptics> {
+        self.ground_layer.as_ref()
+    }
+    pub fn glao(ground_layer: GroundLayerAdaptiveOptics) -> Self {
+        Self {
+            strehl_ratio: None,
+            actuators: None,
+            guide_star: None,
+            laser_guide_star_radius: None,
+            ground_layer: Some(ground_layer),
+            laser_tomography: None,
+            multi_conjugate: None,
+            control_loop: None,
+            wavefront_sensor: None,
+            transfer_function: None,
+        }
+    }
+    /// Returns the LTAO configuration
+    pub fn laser_tomography(&self) -> Option<&LaserTomographyAdaptiveOptics> {
+        self.laser_tomography.as_ref()
+    }
+    pub fn tomography(laser_tomography: LaserTomographyAdaptiveOptics) -> Self {
+        Self {
+            strehl_ratio: None,
+            actuators: None,
+            guide_star: None,
+            laser_guide_star_radius: None,
+            ground_layer: None,
+            laser_tomography: Some(laser_tomography),
+            multi_conjugate: None,
+            control_loop: None,
+            wavefront_sensor: None,
+            transfer_function: None,
+        }
+    }
+    /// Returns the MCAO configuration
+    pub fn multi_conjugate(&self) -> Option<&MultiConjugateAdaptiveOptics> {
+        self.multi_conjugate.as_ref()
+    }
+    pub fn mcao(multi_conjugate: MultiConjugateAdaptiveOptics) -> Self {
+        Self {
+            strehl_ratio: None,
+            actuators: None,
+            guide_star: None,
+            laser_guide_star_radius: None,
+            ground_layer: None,
+            laser_tomography: None,
+            multi_conjugate: Some(multi_conjugate),
+            control_loop: None,
+            wavefront_sensor: None,
             transfer_function: None,
         }
     }
+    /// Sets the frame rate in Hz and the delay in seconds of the control loop
+    pub fn control_loop(self, frame_rate: f64, delay: f64) -> Self {
+        Self {
+            control_loop: Some((frame_rate, delay)),
+            ..self
+        }
+    }
+    /// Sets the photometric band and the throughput of the wavefront sensor
+    pub fn wavefront_sensor<P: Into<Photometry>>(self, band: P, throughput: f64) -> Self {
+        Self {
+            wavefront_sensor: Some((band.into(), throughput)),
+            ..self
+        }
+    }
+    /// Returns the control loop lag in seconds
+    ///
+    /// The lag is the loop delay plus half the wavefront sensor integration time
+    fn lag(&self) -> Option<f64> {
+        self.control_loop
+            .map(|(frame_rate, delay)| delay + 0.5 / frame_rate)
+    }
+    /// Returns the wavefront sensor noise phase variance in radians squared at the given wavelength
+    ///
+    /// The noise is the photon noise of a Shack-Hartmann wavefront sensor with subapertures
+    /// of size `subaperture` (Rousset, Adaptive Optics in Astronomy, 1999) filtered by an integrator.
+    /// The guide star of a NGAO system defaults to the star itself and the noise of the GLAO and MCAO systems
+    /// is averaged over the asterism.
+    /// There is no noise without wavefront sensor or for the laser guide stars of LTAO systems.
+    fn noise_variance(
+        &self,
+        star: &Star,
+        subaperture: f64,
+        fried_parameter: f64,
+        wavelength: f64,
+    ) -> f64 {
+        if self.laser_guide_star_radius.is_some() || self.laser_tomography.is_some() {
+            return 0f64;
+        }
+        self.wavefront_sensor
+            .as_ref()
+            .map(|(band, throughput)| {
+                let frame_rate = self.control_loop.map_or(FRAME_RATE, |(f, _)| f);
+                // Fried parameter at the wavefront sensor wavelength
+                let r0 = fried_parameter * (band.wavelength / wavelength).powf(1.2);
+                let spot = (subaperture / r0).max(1f64).powi(2);
+                let photon_noise = |magnitude: f64| {
+                    let n_photon =
+                        band.n_photon(magnitude) * throughput * subaperture.powi(2) / frame_rate;
+                    std::f64::consts::PI.powi(2) * spot / (2. * n_photon)
+                };
+                let asterism = self
+                    .ground_layer
+                    .as_ref()
+                    .map(|glao| glao.asterism())
+                    .or_else(|| self.multi_conjugate.as_ref().map(|mcao| mcao.asterism()));
+                let variance = match asterism {
+                    Some(asterism) => {
+                        let n = asterism.len() as f64;
+                        asterism
+                            .iter()
+                            .map(|guide_star| photon_noise(guide_star.magnitude))
+                            .sum::<f64>()
+                            / (n * n)
+                    }
+                    None => photon_noise(self.guide_star.as_ref().unwrap_or(star).magnitude),
+                };
+                variance * (band.wavelength / wavelength).powi(2) * LOOP_GAIN / (2. - LOOP_GAIN)
+            })
+            .unwrap_or_default()
+    }
+    /// Returns the variance per axis in radians squared per meter squared of the phase gradient
+    /// at the given wavelength due to the noise of the tip-tilt measurement of a LTAO system
+    ///
+    /// The image of the tip-tilt star is assumed to be diffraction limited by the telescope of the given diameter
+    fn tip_tilt_noise(&self, diameter: f64, wavelength: f64) -> f64 {
+        self.laser_tomography
+            .as_ref()
+            .zip(self.wavefront_sensor.as_ref())
+            .map(|(ltao, (band, throughput))| {
+                let frame_rate = self.control_loop.map_or(FRAME_RATE, |(f, _)| f);
+                let n_photon = band.n_photon(ltao.tip_tilt_star().magnitude)
+                    * throughput
+                    * std::f64::consts::FRAC_PI_4
+                    * diameter.powi(2)
+                    / frame_rate;
+                // centroid variance [rd^2]
+                let variance = (band.wavelength / diameter).powi(2) / (8. * n_photon);
+                variance * (2. * std::f64::consts::PI / wavelength).powi(2) * LOOP_GAIN
+                    / (2. - LOOP_GAIN)
+            })
+            .unwrap_or_default()
+    }
     pub fn init_transfer_function(
         &mut self,
         n_otf: usize,
         d: f64,
+        diameter: f64,
         fried_parameter: f64,
         outer_scale: f64,
     ) -> &mut Self {
-        self.transfer_function = Some(TransferFunction::new(n_otf, d));
-        self.transfer_function
-            .as_mut()
-            .map(|tf| tf.fitting_cutoff_frequency(self.strehl_ratio, fried_parameter, outer_scale));
+        let mut transfer_function = TransferFunction::new(n_otf, d, diameter);
+        let actuator_pitch = self
+            .ground_layer
+            .as_ref()
+            .map(|glao| glao.actuator_pitch())
+            .or_else(|| {
+                self.laser_tomography
+                    .as_ref()
+                    .map(|ltao| ltao.actuator_pitch())
+            })
+            .or_else(|| {
+                self.multi_conjugate
+                    .as_ref()
+                    .map(|mcao| mcao.actuator_pitch())
+            })
+            .or_else(|| self.actuators.map(|actuators| actuators.pitch(diameter)));
+        match (self.strehl_ratio, actuator_pitch) {
+            (Some(strehl_ratio), _) => {
+                transfer_function.fitting_cutoff_frequency(
+                    strehl_ratio,
+                    fried_parameter,
+                    outer_scale,
+                );
+            }
+            (None, Some(actuator_pitch)) => transfer_function.fitting_cutoff = 0.5 / actuator_pitch,
+            (None, None) => (),
+        }
+        self.transfer_function = Some(transfer_function);
         self
     }
     /*     pub fn variance_check(&mut self, fried_parameter: f64, outer_scale: f64) {
@@ -197,11 +529,23 @@ impl AdaptiveOpticsCorrection {
         &mut self,
         fried_parameter: f64,
         outer_scale: f64,
+        turbulence: &TurbulenceProfile,
         star: &Star,
+        wavelength: f64,
     ) -> Vec<Complex<f64>> {
+        // white noise PSD over the corrected spatial frequencies
+        let noise = match self.transfer_function.as_ref() {
+            Some(TransferFunction { fitting_cutoff, .. }) if *fitting_cutoff > 0f64 => {
+                self.noise_variance(star, 0.5 / fitting_cutoff, fried_parameter, wavelength)
+                    / (std::f64::consts::PI * fitting_cutoff.powi(2))
+            }
+            _ => 0f64,
+        };
+        let lag = self.lag();
         let TransferFunction {
             fft,
             d,
+            diameter,
             n_otf,
             kappa,
             fitting_cutoff,
@@ -209,8 +553,28 @@ impl AdaptiveOpticsCorrection {
         let d = *d;
         let kappa = *kappa;
         let fitting_cutoff = *fitting_cutoff;
+        let diameter = *diameter;
         let n = fft.len();
         let df = (d / kappa as f64).recip() / (n - 1) as f64;
+        // LTAO high order residual and tip-tilt star offset
+        let laser_tomography = self.laser_tomography.as_ref().map(|ltao| {
+            let (x_star, y_star) = star.coordinates;
+            let (x_tt, y_tt) = ltao.tip_tilt_star().coordinates;
+            (
+                ltao.residual(turbulence, star, diameter),
+                (
+                    x_tt.to_radians() - x_star.to_radians(),
+                    y_tt.to_radians() - y_star.to_radians(),
+                ),
+            )
+        });
+        // MCAO correction of the turbulence layers
+        let multi_conjugate = self
+            .multi_conjugate
+            .as_ref()
+            .map(|mcao| mcao.layers(turbulence, star, diameter));
+        // covariance of the tip-tilt anisoplanatism phase gradients
+        let mut tip_tilt = [0f64; 3];
         let mut psd: Vec<Complex<f64>> = vec![Complex::zero(); n * n];
         for i in 0..n {
             let q = i as i32 - n as i32 / 2;
@@ -232,9 +596,72 @@ impl AdaptiveOpticsCorrection {
                 let f = x.hypot(y);
                 let buffer = optust::phase::spectrum(f, fried_parameter, outer_scale);
 
+                // the layers are moved by their wind during the loop lag
+                let servo_lag = lag
+                    .map(|lag| {
+                        turbulence
+                            .layers()
+                            .iter()
+                            .filter_map(|layer| layer.wind.map(|wind| (layer.weight, wind)))
+                            .map(|(w, (speed, direction))| {
+                                let (s, c) = direction.sin_cos();
+                                let red = 2. * std::f64::consts::PI * lag * speed * (x * c + y * s);
+                                2. * w * (1. - red.cos())
+                            })
+                            .sum::<f64>()
+                            * buffer
+                    })
+                    .unwrap_or_default();
+
+                let kk = ii * n + jj;
+                if let Some(glao) = self.ground_layer.as_ref() {
+                    psd[kk].re = if f < fitting_cutoff {
+                        glao.residual(turbulence, star, x, y) * buffer + servo_lag + noise
+                    } else {
+                        buffer
+                    };
+                    continue;
+                }
+                if let Some(layers) = multi_conjugate.as_ref() {
+                    psd[kk].re = MultiConjugateAdaptiveOptics::residual(layers, star, x, y)
+                        * buffer
+                        + if f < fitting_cutoff {
+                            servo_lag + noise
+                        } else {
+                            0f64
+                        };
+                    continue;
+                }
+                if let Some((residual, (delta_x, delta_y))) = laser_tomography {
+                    psd[kk].re = if f < fitting_cutoff {
+                        residual * buffer + servo_lag
+                    } else {
+                        buffer
+                    };
+                    // the pupil averaged tilt is negligible beyond 10/D
+                    if f * diameter < 10f64 {
+                        let filter = optust::jinc(std::f64::consts::PI * f * diameter).powi(2);
+                        let anisoplanatism = turbulence
+                            .iter()
+                            .map(|(h, w)| {
+                                let red =
+                                    2. * std::f64::consts::PI * h * (x * delta_x + y * delta_y);
+                                2. * w * (1. - red.cos())
+                            })
+                            .sum::<f64>()
+                            * filter
+                            * buffer;
+                        tip_tilt[0] += x * x * anisoplanatism;
+                        tip_tilt[1] += x * y * anisoplanatism;
+                        tip_tilt[2] += y * y * anisoplanatism;
+                    }
+                    continue;
+                }
+
                 let (x_star, y_star) = star.coordinates;
                 let r_star = x_star.to_radians().hypot(y_star.to_radians());
 
+                // the star and guide star phase difference is filtered by |1-exp(i red)|^2 = 2(1-cos(red))
                 let anisoplanatism = match self.laser_guide_star_radius {
                     Some(radius) => {
                         if r_star > radius.to_radians() {
@@ -244,12 +671,12 @@ impl AdaptiveOpticsCorrection {
                                 (SkyAngle::Radian(radius * c), SkyAngle::Radian(radius * s));
                             let delta_x = x_star - x_gs;
                             let delta_y = y_star - y_gs;
-                            TurbulenceProfile::new()
-                                .into_iter()
+                            turbulence
+                                .iter()
                                 .map(|(h, w)| {
                                     let red =
                                         2. * std::f64::consts::PI * h * (x * delta_x + y * delta_y);
-                                    w * (1. - red.cos())
+                                    2. * w * (1. - red.cos())
                                 })
                                 .sum::<f64>()
                                 * buffer
@@ -261,27 +688,28 @@ impl AdaptiveOpticsCorrection {
                         let (x_gs, y_gs) = self.guide_star.unwrap_or_default().coordinates;
                         let delta_x = x_star - x_gs;
                         let delta_y = y_star - y_gs;
-                        TurbulenceProfile::new()
-                            .into_iter()
+                        turbulence
+                            .iter()
                             .map(|(h, w)| {
                                 let red =
                                     2. * std::f64::consts::PI * h * (x * delta_x + y * delta_y);
-                                w * (1. - red.cos())
+                                2. * w * (1. - red.cos())
                             })
                             .sum::<f64>()
                             * buffer
                     }
                 };
 
-                let kk = ii * n + jj;
                 psd[kk].re = if f < fitting_cutoff {
-                    anisoplanatism
+                    anisoplanatism + servo_lag + noise
                 } else {
                     buffer + anisoplanatism
                 };
             }
         }
         let covariance = fft.zero_padding(psd).process().buffer();
+        // the DFT is normalized by 1/n
+        let scale = n as f64 * df * df;
 
         let n_otf = *n_otf;
         let mut cov: Vec<Complex<f64>> = vec![Complex::zero(); n_otf * n_otf];
@@ -291,7 +719,7 @@ impl AdaptiveOpticsCorrection {
                 let jj = j * kappa;
                 let k = i * n_otf + j;
                 let kk = ii * n + jj;
-                cov[k] = covariance[kk] * df * df;
+                cov[k] = covariance[kk] * scale;
             }
         }
         for i in 1..n_otf / 2 + 1 {
@@ -300,7 +728,7 @@ impl AdaptiveOpticsCorrection {
                 let jj = n - j * kappa;
                 let k = (n_otf - i) * n_otf + n_otf - j;
                 let kk = ii * n + jj;
-                cov[k] = covariance[kk] * df * df;
+                cov[k] = covariance[kk] * scale;
             }
         }
         for i in 1..n_otf / 2 + 1 {
@@ -309,7 +737,7 @@ impl AdaptiveOpticsCorrection {
                 let jj = j * kappa;
                 let k = (n_otf - i) * n_otf + j;
                 let kk = ii * n + jj;
-                cov[k] = covariance[kk] * df * df;
+                cov[k] = covariance[kk] * scale;
             }
         }
         for i in 0..(n_otf + 1) / 2 {
@@ -318,11 +746,34 @@ impl AdaptiveOpticsCorrection {
                 let jj = n - j * kappa;
                 let k = i * n_otf + n_otf - j;
                 let kk = ii * n + jj;
-                cov[k] = covariance[kk] * df * df;
+                cov[k] = covariance[kk] * scale;
             }
         }
         let var = cov[0];
-        cov.into_iter().map(|cov| (cov - var).exp()).collect()
+        if self.laser_tomography.is_none() {
+            return cov.into_iter().map(|cov| (cov - var).exp()).collect();
+        }
+        // tip-tilt jitter
+        let [g_xx, g_xy, g_yy] = tip_tilt.map(|t| t * (2. * std::f64::consts::PI * df).powi(2));
+        let g_noise = self.tip_tilt_noise(diameter, wavelength);
+        let signed = |i: usize| {
+            if i < n_otf.div_ceil(2) {
+                i as f64 * d
+            } else {
+                (i as f64 - n_otf as f64) * d
+            }
+        };
+        cov.into_iter()
+            .enumerate()
+            .map(|(k, cov)| {
+                let (u_x, u_y) = (signed(k / n_otf), signed(k % n_otf));
+                let jitter = g_xx * u_x * u_x
+                    + 2. * g_xy * u_x * u_y
+                    + g_yy * u_y * u_y
+                    + g_noise * (u_x * u_x + u_y * u_y);
+                (cov - var - 0.5 * jitter).exp()
+            })
+            .collect()
     }
 }
 
@@ -361,3 +812,73 @@ pub mod tests {
     } */
 }
  */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        field::tests::{hst_field, peak_intensity, strehl_ratio},
+        AdaptiveOptics, DiffractionLimited, SeeingBuilder,
+    };
+
+    #[test]
+    fn servo_lag_and_noise() {
+        let strehl = |seeing: SeeingBuilder, magnitude: f64| {
+            strehl_ratio::<AdaptiveOptics>(
+                hst_field()
+                    .objects(Star::default().magnitude(magnitude))
+                    .seeing_limited(seeing),
+            )
+        };
+        let seeing = || {
+            SeeingBuilder::new(16e-2)
+                .turbulence_profile(TurbulenceProfile::default().bufton_wind(0.))
+                .ngao(0.75, None)
+        };
+        let reference = strehl(seeing(), 0.);
+        // a slow control loop lags behind the turbulence
+        let servo_lag = strehl(seeing().control_loop(100., 5e-3), 0.);
+        assert!(servo_lag < reference, "{servo_lag} vs {reference}");
+        // the control loop may be set before the correction
+        let seeing_first = SeeingBuilder::new(16e-2)
+            .turbulence_profile(TurbulenceProfile::default().bufton_wind(0.))
+            .control_loop(100., 5e-3)
+            .ngao(0.75, None);
+        assert_eq!(strehl(seeing_first, 0.), servo_lag);
+        // a faint guide star degrades the correction
+        let bright = strehl(seeing().wavefront_sensor("R", 0.5), 0.);
+        let faint = strehl(seeing().wavefront_sensor("R", 0.5), 16.);
+        assert!((bright / reference - 1.).abs() < 1e-2);
+        assert!(faint < bright, "{faint} vs {bright}");
+    }
+
+    #[test]
+    fn actuators() {
+        let diffraction_limited = peak_intensity::<DiffractionLimited>(hst_field());
+        let strehl = |seeing: SeeingBuilder| {
+            peak_intensity::<AdaptiveOptics>(hst_field().seeing_limited(seeing))
+                / diffraction_limited
+        };
+        let seeing = SeeingBuilder::new(16e-2);
+        // fitting error of the spatial frequencies above 1/(2p): 0.275(p/r0)^(5/3)
+        let r0 = seeing.fried_parameter("V");
+        let fitting = |pitch: f64| (-0.275 * (pitch / r0).powf(5. / 3.)).exp();
+        let coarse = strehl(seeing.clone().ngao_actuators(Actuators::Pitch(0.6), None));
+        assert!((coarse - fitting(0.6)).abs() < 2e-2, "{coarse}");
+        let fine = strehl(seeing.clone().ngao_actuators(Actuators::Pitch(0.2), None));
+        assert!((fine - fitting(0.2)).abs() < 2e-2, "{fine}");
+        // 12 actuators across the 2.4m HST pupil
+        let count = strehl(seeing.clone().ngao_actuators(Actuators::Count(12), None));
+        assert!((count / fine - 1.).abs() < 1e-2, "{count} vs {fine}");
+        assert!(seeing
+            .clone()
+            .try_ngao_actuators(Actuators::Count(0), None)
+            .is_err());
+        // partial correction
+        for strehl_ratio in [0.2, 0.5] {
+            let partial = strehl(seeing.clone().ngao(strehl_ratio, None));
+            assert!((partial - strehl_ratio).abs() < 2e-2, "{partial}");
+        }
+        assert!(seeing.try_ngao(1., None).is_err());
+    }
+}
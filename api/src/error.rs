@@ -0,0 +1,42 @@
+use std::path::PathBuf;
+
+/// Eyepiece error
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("expected the photometric band: V, R, I, J, H, K, u, g, r, i, z, G, BP, RP, Y, Z, L or M, found {0:?}")]
+    PhotometricBand(String),
+    #[error("invalid filter transmission: {0}")]
+    FilterTransmission(String),
+    #[error("the field has no photometric band")]
+    NoPhotometry,
+    #[error("Strehl ratio must be in ]0,1[, found {0}")]
+    StrehlRatio(f64),
+    #[error("pupil sampling ({0}m) is too small, must be greater or equal to 2.5cm")]
+    PupilSampling(f64),
+    #[error("invalid pupil mask: {0}")]
+    PupilMask(String),
+    #[error("invalid turbulence profile: {0}")]
+    TurbulenceProfile(String),
+    #[error("the Noll index starts at 1, found {0}")]
+    NollIndex(usize),
+    #[error("GMT segment # must be in the range [1,7], found {0}")]
+    GmtSegment(usize),
+    #[error("invalid phase screen: {0}")]
+    PhaseScreen(String),
+    #[error("invalid sky background: {0}")]
+    SkyBackground(String),
+    #[error("invalid detector: {0}")]
+    Detector(String),
+    #[error("invalid adaptive optics configuration: {0}")]
+    AdaptiveOptics(String),
+    #[error("unsupported file extension for {0:?}, expected one of {1}")]
+    Extension(PathBuf, String),
+    #[error("failed to read or write image")]
+    Image(#[from] image::ImageError),
+    #[error("failed to read or write file")]
+    Io(#[from] std::io::Error),
+    #[error("failed to serialize data into pickle")]
+    Pickle(#[from] serde_pickle::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
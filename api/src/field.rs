@@ -15,11 +15,23 @@ pub use observing_mode::{Intensity, Observing};
 use serde::Serialize;
 mod intensity;
 pub use intensity::FieldImage;
+mod detector;
+mod header;
+pub use detector::Detector;
 mod serpkl;
+mod short_exposure;
+pub use header::FitsKeywords;
 
 /// [FieldBuilder] to [Field] interface
 pub trait Builder<F> {
     fn build(self) -> F;
+    /// Creates a new field, returning an [error](crate::Error) if the field settings are invalid
+    fn try_build(self) -> crate::Result<F>
+    where
+        Self: Sized,
+    {
+        Ok(self.build())
+    }
 }
 
 /// Diffraction limited observing mode
@@ -31,12 +43,16 @@ pub enum SeeingLimited {}
 /// Adaptive optics observing mode
 #[derive(Debug, Serialize)]
 pub enum AdaptiveOptics {}
+/// Multi-conjugate adaptive optics observing mode
+#[derive(Debug, Serialize)]
+pub enum Mcao {}
 
 /// Trait defining the observing modes
 pub trait ObservingModes: Serialize {}
 impl ObservingModes for DiffractionLimited {}
 impl ObservingModes for SeeingLimited {}
 impl ObservingModes for AdaptiveOptics {}
+impl ObservingModes for Mcao {}
 
 /// Trait limiting the observing modes to the seeing limited modes (including Adaptive Optics)
 pub trait DiffractionModes: ObservingModes {}
@@ -46,3 +62,38 @@ impl DiffractionModes for DiffractionLimited {}
 pub trait SeeingModes: ObservingModes {}
 impl SeeingModes for SeeingLimited {}
 impl SeeingModes for AdaptiveOptics {}
+impl SeeingModes for Mcao {}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::Hst;
+
+    /// Returns the builder of a 51x51 pixels field observed with the [Hst]
+    pub(crate) fn hst_field() -> FieldBuilder<Hst> {
+        FieldBuilder::new(Hst::new()).field_of_view(51)
+    }
+
+    /// Returns the peak intensity of the field built with `builder`
+    pub(crate) fn peak_intensity<Mode>(builder: FieldBuilder<Hst>) -> f64
+    where
+        FieldBuilder<Hst>: Builder<Field<Hst, Mode>>,
+        Mode: ObservingModes + Send,
+        Observing<Mode>: Intensity,
+    {
+        let mut field: Field<Hst, Mode> = builder.build();
+        field.intensity(None).into_iter().fold(0f64, f64::max)
+    }
+
+    /// Returns the ratio of the peak to the sum of the intensity of the field built with `builder`
+    pub(crate) fn strehl_ratio<Mode>(builder: FieldBuilder<Hst>) -> f64
+    where
+        FieldBuilder<Hst>: Builder<Field<Hst, Mode>>,
+        Mode: ObservingModes + Send,
+        Observing<Mode>: Intensity,
+    {
+        let mut field: Field<Hst, Mode> = builder.build();
+        let intensity = field.intensity(None);
+        intensity.iter().fold(0f64, |a, &i| a.max(i)) / intensity.iter().sum::<f64>()
+    }
+}
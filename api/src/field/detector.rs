@@ -0,0 +1,189 @@
+use rand::Rng;
+use rand_distr::{Distribution, Normal, Poisson};
+use serde::Serialize;
+
+use crate::{Error, FitsHeader, Result};
+
+/// Detector model
+///
+/// The detector converts the binned field intensity (in electrons) into a frame
+/// in analog-to-digital units (ADU):
+///  * the dark current is added and the electrons are clipped to the full well capacity,
+///  * the read noise is added,
+///  * the electrons are converted to ADU with the gain and offset by the bias level,
+///  * the ADU are quantized and clipped to the range of the analog-to-digital converter.
+///
+/// The default detector is an ideal 16 bits detector with a unit gain.
+///
+/// # Example
+/// ```
+/// use eyepiece::{Builder, Detector, Field, FieldBuilder, Hst};
+///
+/// let detector = Detector::new()
+///     .read_noise(3.)
+///     .dark_current(2e-3)
+///     .gain(1.5)
+///     .bias(100.)
+///     .bit_depth(16)
+///     .full_well(80e3);
+/// let mut field: Field<Hst> = FieldBuilder::new(Hst::new())
+///     .field_of_view(21)
+///     .detector(detector)
+///     .build();
+/// ```
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Detector {
+    /// read noise [e- rms]
+    read_noise: f64,
+    /// dark current [e-/s]
+    dark_current: f64,
+    /// gain [e-/ADU]
+    gain: f64,
+    /// bias level [ADU]
+    bias: f64,
+    /// analog-to-digital converter bit depth
+    bit_depth: u32,
+    /// full well capacity [e-]
+    full_well: Option<f64>,
+}
+impl Default for Detector {
+    fn default() -> Self {
+        Self {
+            read_noise: 0f64,
+            dark_current: 0f64,
+            gain: 1f64,
+            bias: 0f64,
+            bit_depth: 16,
+            full_well: None,
+        }
+    }
+}
+impl Detector {
+    /// Creates a default detector
+    pub fn new() -> Self {
+        Default::default()
+    }
+    /// Sets the read noise in electrons rms
+    pub fn read_noise(self, read_noise: f64) -> Self {
+        Self { read_noise, ..self }
+    }
+    /// Sets the dark current in electrons per second
+    pub fn dark_current(self, dark_current: f64) -> Self {
+        Self {
+            dark_current,
+            ..self
+        }
+    }
+    /// Sets the gain in electrons per ADU
+    ///
+    /// # Panics
+    /// Panics if the gain is not positive, see [Detector::try_gain]
+    pub fn gain(self, gain: f64) -> Self {
+        self.try_gain(gain).unwrap_or_else(|e| panic!("{e}"))
+    }
+    /// Sets the gain in electrons per ADU
+    ///
+    /// Returns an [error](crate::Error) if the gain is not positive
+    pub fn try_gain(self, gain: f64) -> Result<Self> {
+        if gain <= 0f64 {
+            return Err(Error::Detector(format!(
+                "the gain must be positive, found {gain}"
+            )));
+        }
+        Ok(Self { gain, ..self })
+    }
+    /// Sets the bias level in ADU
+    pub fn bias(self, bias: f64) -> Self {
+        Self { bias, ..self }
+    }
+    /// Sets the bit depth of the analog-to-digital converter
+    pub fn bit_depth(self, bit_depth: u32) -> Self {
+        Self { bit_depth, ..self }
+    }
+    /// Sets the full well capacity in electrons
+    pub fn full_well(self, full_well: f64) -> Self {
+        Self {
+            full_well: Some(full_well),
+            ..self
+        }
+    }
+    /// Returns the largest ADU value
+    pub fn saturation(&self) -> f64 {
+        (2f64.powi(self.bit_depth as i32) - 1f64).max(0f64)
+    }
+    /// Reads out the detector
+    ///
+    /// Converts the electrons collected during the exposure time (in seconds) into ADU
+    pub fn readout(&self, electrons: &[f64], exposure: f64) -> Vec<f64> {
+        self.readout_with(electrons, exposure, &mut rand::thread_rng())
+    }
+    /// Reads out the detector using the given random number generator
+    pub fn readout_with<R: Rng>(&self, electrons: &[f64], exposure: f64, rng: &mut R) -> Vec<f64> {
+        let dark = self.dark_current * exposure;
+        let dark_noise = (dark > 0f64).then(|| Poisson::new(dark).unwrap());
+        let read_noise =
+            (self.read_noise > 0f64).then(|| Normal::new(0f64, self.read_noise).unwrap());
+        let adu_max = self.saturation();
+        electrons
+            .iter()
+            .map(|&e| {
+                let mut e = e + dark_noise.map_or(0f64, |d| d.sample(rng));
+                if let Some(full_well) = self.full_well {
+                    e = e.min(full_well);
+                }
+                e += read_noise.map_or(0f64, |r| r.sample(rng));
+                (e / self.gain + self.bias).round().clamp(0f64, adu_max)
+            })
+            .collect()
+    }
+    /// Returns the detector [FITS](crate::FitsWriter) header keywords
+    pub fn header(&self) -> FitsHeader {
+        let mut header = FitsHeader::new()
+            .keyword("RDNOISE", self.read_noise, "read noise [e-]")
+            .keyword("DARKCUR", self.dark_current, "dark current [e-/s]")
+            .keyword("GAIN", self.gain, "gain [e-/ADU]")
+            .keyword("BIAS", self.bias, "bias level [ADU]")
+            .keyword("NBITS", self.bit_depth as usize, "ADC bit depth");
+        if let Some(full_well) = self.full_well {
+            header.push("FULLWELL", full_well, "full well capacity [e-]");
+        }
+        header
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{field::tests::hst_field, Builder, DiffractionLimited, Field, Hst};
+
+    #[test]
+    fn readout() {
+        let detector = Detector::new().gain(2.).bias(100.).full_well(8e3);
+        let adu = detector.readout(&[0., 10., 1e4], 1.);
+        assert_eq!(adu, vec![100., 105., 4100.]);
+        let adu = detector.bit_depth(12).readout(&[1e4], 1.);
+        assert_eq!(adu, vec![4095.]);
+        assert!(Detector::new().try_gain(0.).is_err());
+    }
+
+    #[test]
+    fn field() {
+        let detector = Detector::new().read_noise(3.).bias(100.);
+        let mut field: Field<Hst, DiffractionLimited> = hst_field()
+            .field_of_view(21)
+            .flux(1e5)
+            .detector(detector)
+            .build();
+        assert_eq!(field.header().get("BUNIT"), Some(&"ADU".into()));
+        let adu = field.intensity(None);
+        assert!(adu.iter().all(|&x| x >= 0. && x.fract() == 0.));
+        // noiseless electrons before the readout
+        let electrons = field.electrons(None);
+        assert!(electrons.iter().any(|&x| x.fract() != 0.));
+        assert!(adu
+            .iter()
+            .zip(&electrons)
+            .filter(|(&a, _)| a < detector.saturation())
+            .all(|(a, e)| (a - 100. - e).abs() < 20.));
+    }
+}
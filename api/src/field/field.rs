@@ -1,13 +1,16 @@
-use image::{ImageResult, Rgb, RgbImage};
+use image::{Rgb, RgbImage};
 use indicatif::{MultiProgress, ProgressBar};
-use skyangle::Conversion;
-use std::{fmt::Display, path::Path};
+use skyangle::{Conversion, SkyAngle};
+use std::{fmt::Display, io, path::Path};
 
 use super::{
-    AdaptiveOptics, Builder, DiffractionLimited, FieldBuilder, FieldOfView, Intensity, Observing,
-    PixelScale, SeeingLimited,
+    AdaptiveOptics, Builder, Detector, DiffractionLimited, FieldBuilder, FieldOfView, FitsKeywords,
+    Intensity, Mcao, Observing, PixelScale, SeeingLimited,
+};
+use crate::{
+    Error, FitsHeader, FitsWriter, Objects, Observer, ObservingModes, Photometry, Result,
+    SkyBackground, Wcs, Zernike,
 };
-use crate::{Objects, Observer, ObservingModes, Photometry};
 
 /// Observer field of regard
 #[derive(Debug)]
@@ -26,35 +29,47 @@ where
     pub(super) observing_mode: Observing<Mode>,
     pub(super) flux: Option<f64>,
     pub(super) intensity_sampling: Option<usize>,
+    pub(super) wcs: Option<Wcs>,
+    pub(super) detector: Option<Detector>,
+    pub(super) sky_background: Option<SkyBackground>,
+    pub(super) bandwidth_sampling: usize,
+    pub(super) aberrations: Option<Zernike>,
+    pub(super) pupil_supersampling: usize,
+    pub(super) position_angle: f64,
+    pub(super) psf_cache: Option<usize>,
 }
 
 impl<T: Observer + Display, Mode: ObservingModes> Display for Field<T, Mode> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "Field in {} band", self.photometry)?;
+        if self.bandwidth_sampling > 1 {
+            writeln!(f, " . bandwidth sampling: {}", self.bandwidth_sampling)?;
+        }
         writeln!(f, " . pixel scale: {:.3}mas", self.resolution().to_mas())?;
+        if self.position_angle != 0f64 {
+            writeln!(
+                f,
+                " . position angle: {:.3}degree",
+                self.position_angle.to_degrees()
+            )?;
+        }
         writeln!(
             f,
             " . field-of-view: {:.3}arcsec",
             self.field_of_view.get(self).to_arcsec()
         )?;
         writeln!(f, " . {}", self.observer)?;
+        if self.pupil_supersampling > 1 {
+            writeln!(f, " . pupil supersampling: {}", self.pupil_supersampling)?;
+        }
+        if let Some(aberrations) = self.aberrations.as_ref() {
+            writeln!(f, " . {aberrations}")?;
+        }
         write!(f, " . {}", self.observing_mode)?;
         // if let Some(seeing) = self.seeing() {
         //     writeln!(f, " . seeing: {:.3}arcsec", seeing.to_arcsec())?;
         // }
-        let n_star: usize = self
-            .objects
-            .iter()
-            .filter_map(|star| {
-                let half_fov = self.field_of_view.get(self) * 0.5;
-                let (x, y) = star.coordinates;
-                if x.to_radians().abs() <= half_fov && y.to_radians().abs() <= half_fov {
-                    Some(1)
-                } else {
-                    None
-                }
-            })
-            .sum();
+        let n_star = self.n_star();
         writeln!(f, " . star #: {n_star}")?;
         let magnitude_max = self
             .objects
@@ -68,10 +83,18 @@ impl<T: Observer + Display, Mode: ObservingModes> Display for Field<T, Mode> {
             f,
             " . star magnitudes: [{magnitude_min:.1},{magnitude_max:.1}]"
         )?;
+        if let Some(sky_brightness) = self.sky_brightness() {
+            writeln!(f, " . sky brightness: {sky_brightness:.1}mag/arcsec^2")?;
+        }
         writeln!(f, " . exposure time: {}s", self.exposure)
     }
 }
 impl<T: Observer> Builder<Field<T, DiffractionLimited>> for FieldBuilder<T> {
+    /// Creates a new field, returning an [error](crate::Error) if the field settings are invalid
+    fn try_build(self) -> Result<Field<T, DiffractionLimited>> {
+        self.validate()?;
+        Ok(self.build())
+    }
     /// Creates a new field
     fn build(self) -> Field<T, DiffractionLimited> {
         let FieldBuilder {
@@ -84,22 +107,43 @@ impl<T: Observer> Builder<Field<T, DiffractionLimited>> for FieldBuilder<T> {
             observer,
             seeing: _,
             flux,
+            wcs,
+            detector,
+            sky_background,
+            bandwidth_sampling,
+            aberrations,
+            pupil_supersampling,
+            position_angle,
+            psf_cache,
         } = self;
         Field {
             pixel_scale,
             field_of_view,
-            photometry: photometry[0],
+            photometry: photometry[0].clone(),
             objects,
             exposure,
             poisson_noise,
             observer,
             observing_mode: Observing::diffraction_limited(),
             flux,
+            wcs,
+            detector,
+            sky_background,
+            bandwidth_sampling,
+            aberrations,
+            pupil_supersampling,
+            position_angle,
+            psf_cache,
             intensity_sampling: None,
         }
     }
 }
 impl<T: Observer> Builder<Field<T, SeeingLimited>> for FieldBuilder<T> {
+    /// Creates a new field, returning an [error](crate::Error) if the field settings are invalid
+    fn try_build(self) -> Result<Field<T, SeeingLimited>> {
+        self.validate()?;
+        Ok(self.build())
+    }
     /// Creates a new field
     fn build(self) -> Field<T, SeeingLimited> {
         let FieldBuilder {
@@ -112,24 +156,58 @@ impl<T: Observer> Builder<Field<T, SeeingLimited>> for FieldBuilder<T> {
             observer,
             seeing,
             flux,
+            wcs,
+            detector,
+            sky_background,
+            bandwidth_sampling,
+            aberrations,
+            pupil_supersampling,
+            position_angle,
+            psf_cache,
         } = self;
         Field {
             pixel_scale,
             field_of_view,
-            photometry: photometry[0],
+            photometry: photometry[0].clone(),
             objects,
             exposure,
             poisson_noise,
             observer,
             observing_mode: Observing::seeing_limited(
-                seeing.map(|seeing| seeing.wavelength(photometry[0])),
+                seeing.map(|seeing| seeing.wavelength(&photometry[0])),
             ),
             flux,
+            wcs,
+            detector,
+            sky_background,
+            bandwidth_sampling,
+            aberrations,
+            pupil_supersampling,
+            position_angle,
+            psf_cache,
             intensity_sampling: None,
         }
     }
 }
 impl<T: Observer> Builder<Field<T, AdaptiveOptics>> for FieldBuilder<T> {
+    /// Creates a new field, returning an [error](crate::Error) if the field settings are invalid
+    /// or if the seeing has a [MCAO](crate::MultiConjugateAdaptiveOptics) configuration,
+    /// MCAO fields are built with the [Mcao] observing mode
+    fn try_build(self) -> Result<Field<T, AdaptiveOptics>> {
+        self.validate()?;
+        if self
+            .seeing
+            .as_ref()
+            .and_then(|seeing| seeing.adaptive_optics.as_ref())
+            .and_then(|ao| ao.multi_conjugate())
+            .is_some()
+        {
+            return Err(Error::AdaptiveOptics(String::from(
+                "the fields with a MCAO configuration must be built with the Mcao observing mode",
+            )));
+        }
+        Ok(self.build())
+    }
     /// Creates a new field
     fn build(self) -> Field<T, AdaptiveOptics> {
         let FieldBuilder {
@@ -142,20 +220,100 @@ impl<T: Observer> Builder<Field<T, AdaptiveOptics>> for FieldBuilder<T> {
             observer,
             seeing,
             flux,
+            wcs,
+            detector,
+            sky_background,
+            bandwidth_sampling,
+            aberrations,
+            pupil_supersampling,
+            position_angle,
+            psf_cache,
         } = self;
 
         Field {
             pixel_scale,
             field_of_view,
-            photometry: photometry[0],
+            photometry: photometry[0].clone(),
             objects,
             exposure,
             poisson_noise,
             observer,
             observing_mode: Observing::seeing_limited(
-                seeing.map(|seeing| seeing.wavelength(photometry[0])),
+                seeing.map(|seeing| seeing.wavelength(&photometry[0])),
             ),
             flux,
+            wcs,
+            detector,
+            sky_background,
+            bandwidth_sampling,
+            aberrations,
+            pupil_supersampling,
+            position_angle,
+            psf_cache,
+            intensity_sampling: None,
+        }
+    }
+}
+impl<T: Observer> Builder<Field<T, Mcao>> for FieldBuilder<T> {
+    /// Creates a new field, returning an [error](crate::Error) if the field settings are invalid
+    /// or if the seeing has no [MCAO](crate::MultiConjugateAdaptiveOptics) configuration
+    fn try_build(self) -> Result<Field<T, Mcao>> {
+        self.validate()?;
+        if self
+            .seeing
+            .as_ref()
+            .and_then(|seeing| seeing.adaptive_optics.as_ref())
+            .and_then(|ao| ao.multi_conjugate())
+            .is_none()
+        {
+            return Err(Error::AdaptiveOptics(String::from(
+                "the seeing has no MCAO configuration",
+            )));
+        }
+        Ok(self.build())
+    }
+    /// Creates a new field
+    fn build(self) -> Field<T, Mcao> {
+        let FieldBuilder {
+            pixel_scale,
+            field_of_view,
+            photometry,
+            objects,
+            exposure,
+            poisson_noise,
+            observer,
+            seeing,
+            flux,
+            wcs,
+            detector,
+            sky_background,
+            bandwidth_sampling,
+            aberrations,
+            pupil_supersampling,
+            position_angle,
+            psf_cache,
+        } = self;
+
+        Field {
+            pixel_scale,
+            field_of_view,
+            photometry: photometry[0].clone(),
+            objects,
+            exposure,
+            poisson_noise,
+            observer,
+            observing_mode: Observing::seeing_limited(
+                seeing.map(|seeing| seeing.wavelength(&photometry[0])),
+            ),
+            flux,
+            wcs,
+            detector,
+            sky_background,
+            bandwidth_sampling,
+            aberrations,
+            pupil_supersampling,
+            position_angle,
+            psf_cache,
             intensity_sampling: None,
         }
     }
@@ -172,18 +330,66 @@ where
     pub fn field_of_view(&self) -> f64 {
         self.field_of_view.get(self)
     }
+    /// Returns the size of the field image in pixels
+    pub fn n_px(&self) -> usize {
+        let b = self
+            .pixel_scale
+            .to_nyquist_clamped_ratio(&self.observer, &self.photometry);
+        let intensity_sampling = (b * self.field_of_view.to_pixelscale_ratio(self)).ceil() as usize;
+        intensity_sampling / b as usize
+    }
+    /// Returns the field [world coordinate system](Wcs)
+    pub fn wcs(&self) -> Option<Wcs> {
+        self.wcs.map(|wcs| {
+            wcs.rotate(SkyAngle::Radian(self.position_angle))
+                .image(self.resolution(), self.n_px())
+        })
+    }
+    /// Returns the sky surface brightness in mag/arcsec^2
+    ///
+    /// Returns `None` if there is no sky background or if its brightness is not finite
+    pub fn sky_brightness(&self) -> Option<f64> {
+        self.sky_background
+            .map(|sky| sky.brightness(&self.photometry))
+            .filter(|mag| mag.is_finite())
+    }
+    /// Returns the number of sky background photons per pixel
+    pub fn sky_photons(&self) -> Option<f64> {
+        self.sky_brightness().map(|mag| {
+            self.photometry.n_photon(mag)
+                * self.exposure
+                * self.observer.supersampled_area(self.pupil_supersampling)
+                * self.resolution().to_arcsec().powi(2)
+        })
+    }
+    /// Returns the number of stars within the field-of-view
+    pub fn n_star(&self) -> usize {
+        let half_fov = self.field_of_view() * 0.5;
+        self.objects
+            .iter()
+            .filter(|star| {
+                let (x, y) = star.rotate(self.position_angle).coordinates;
+                x.to_radians().abs() <= half_fov && y.to_radians().abs() <= half_fov
+            })
+            .count()
+    }
 }
 
 impl<T, Mode> Field<T, Mode>
 where
     T: Observer + Sync + Send,
     Mode: Send + ObservingModes,
-    Observing<Mode>: Intensity,
+    Observing<Mode>: Intensity + FitsKeywords,
 {
     /// Computes image and save it to file
-    pub fn save<P: AsRef<Path>>(&mut self, path: P, save_options: SaveOptions) -> ImageResult<()> {
-        let mut intensity = self.intensity(save_options.bar);
-        match path.as_ref().extension().and_then(|p| p.to_str()) {
+    ///
+    /// The image format is set from the file extension: `png`, `jpg`, `tiff` or `fits`.
+    /// The FITS file holds the photon counts and the field properties in the header.
+    /// An [error](crate::Error) is returned for any other extension.
+    pub fn save<P: AsRef<Path>>(&mut self, path: P, mut save_options: SaveOptions) -> Result<()> {
+        let extension = check_extension(path.as_ref(), &["png", "jpg", "tiff", "fits"])?;
+        let mut intensity = self.intensity(save_options.bar.take());
+        match extension {
             /*             Some("pkl") => {
                 #[derive(serde::Serialize)]
                 struct Data<'a, T, Mode>
@@ -208,7 +414,8 @@ where
                     path.as_ref()
                 ))
             } */
-            Some("png" | "jpg" | "tiff") => {
+            "fits" => save_fits(path, vec![(self.header(), intensity)], &save_options)?,
+            "png" | "jpg" | "tiff" => {
                 if let Some(lufn) = save_options.lufn {
                     intensity.iter_mut().for_each(|i| *i = lufn(*i));
                 }
@@ -222,12 +429,9 @@ where
                 img.pixels_mut().zip(&intensity).for_each(|(p, i)| {
                     *p = Rgb(lut.eval_continuous(*i).into_array());
                 });
-                img.save(path.as_ref()).expect(&format!(
-                    "failed to write field intensity into image {:?}",
-                    path.as_ref()
-                ))
+                img.save(path.as_ref())?
             }
-            _ => unimplemented!(),
+            _ => unreachable!(),
         };
         Ok(())
     }
@@ -271,6 +475,8 @@ pub struct SaveOptions {
     pub(super) mbar: Option<MultiProgress>,
     pub(super) saturation: Saturation,
     pub(super) lufn: Option<fn(f64) -> f64>,
+    pub(super) cube: bool,
+    pub(super) single_precision: bool,
 }
 impl SaveOptions {
     /// Returns the default option
@@ -300,6 +506,80 @@ impl SaveOptions {
             ..self
         }
     }
+    /// Saves multiple fields in a single 3D FITS data cube
+    ///
+    /// The primary header holds the keywords common to all the fields and the header of each field
+    /// is saved in a header only extension following the cube.
+    /// By default, each field is saved in its own FITS extension
+    pub fn cube(self) -> Self {
+        Self { cube: true, ..self }
+    }
+    /// Saves FITS data in single precision (32 bits) floating point format
+    pub fn single_precision(self) -> Self {
+        Self {
+            single_precision: true,
+            ..self
+        }
+    }
+}
+
+/// Returns the file extension if it is one of the given extensions
+pub(super) fn check_extension<'a>(path: &'a Path, extensions: &[&str]) -> Result<&'a str> {
+    path.extension()
+        .and_then(|p| p.to_str())
+        .filter(|ext| extensions.contains(ext))
+        .ok_or_else(|| Error::Extension(path.to_path_buf(), extensions.join(", ")))
+}
+
+/// Writes field images into a FITS file
+///
+/// A single image is written in the primary HDU.
+/// Multiple images are written either in a 3D data cube or in individual IMAGE extensions,
+/// the primary HDU header holding the keywords common to all the images.
+/// The headers of the planes of a data cube are written in header only IMAGE extensions,
+/// one per plane
+pub(super) fn save_fits<P: AsRef<Path>>(
+    path: P,
+    images: Vec<(FitsHeader, Vec<f64>)>,
+    save_options: &SaveOptions,
+) -> io::Result<()> {
+    let mut fits = FitsWriter::create(path)?;
+    let n_px = images
+        .first()
+        .map(|(_, intensity)| (intensity.len() as f64).sqrt() as usize)
+        .unwrap_or_default();
+    let mut write = |header: &FitsHeader, shape: &[usize], data: &[f64]| {
+        if save_options.single_precision {
+            let data: Vec<f32> = data.iter().map(|&x| x as f32).collect();
+            fits.write(header, shape, &data).map(|_| ())
+        } else {
+            fits.write(header, shape, data).map(|_| ())
+        }
+    };
+    match images.len() {
+        0 => Ok(()),
+        1 => write(&images[0].0, &[n_px, n_px], &images[0].1),
+        n if save_options.cube => {
+            let (headers, data): (Vec<_>, Vec<_>) = images.into_iter().unzip();
+            write(
+                &FitsHeader::common(&headers),
+                &[n_px, n_px, n],
+                &data.into_iter().flatten().collect::<Vec<f64>>(),
+            )?;
+            for header in headers.iter() {
+                write(header, &[], &[])?;
+            }
+            Ok(())
+        }
+        _ => {
+            let headers: Vec<_> = images.iter().map(|(header, _)| header.clone()).collect();
+            write(&FitsHeader::common(&headers), &[], &[])?;
+            for (header, intensity) in images.iter() {
+                write(header, &[n_px, n_px], intensity)?;
+            }
+            Ok(())
+        }
+    }
 }
 
 #[cfg(test)]
@@ -308,6 +588,8 @@ mod tests {
 
     use super::*;
 
+    use crate::Star;
+
     type Tel = crate::Hst;
     fn builder() -> FieldBuilder<Tel> {
         let tel = Tel::new();
@@ -329,6 +611,160 @@ mod tests {
         field.dump("seeing.pkl").unwrap();
     }
 
+    #[test]
+    fn psf_cache() {
+        let resolution = {
+            let field: Field<Tel, SeeingLimited> = builder().build();
+            field.resolution().to_degrees() * 3600.
+        };
+        let stars: Vec<_> = [(0., 0.), (5.3, -2.), (-7.61, 4.25), (3., 9.9)]
+            .into_iter()
+            .map(|(x, y)| {
+                Star::new((
+                    skyangle::SkyAngle::Arcsecond(x * resolution),
+                    skyangle::SkyAngle::Arcsecond(y * resolution),
+                ))
+            })
+            .collect();
+        let intensity = |builder: FieldBuilder<Tel>| {
+            let mut field: Field<Tel, SeeingLimited> =
+                builder.field_of_view(41).objects(stars.clone()).build();
+            field.intensity(None)
+        };
+        let per_star = intensity(builder());
+        let cached = intensity(builder().psf_cache(8));
+        let flux: f64 = per_star.iter().sum();
+        // the flux outside the field of view depends on the star positions
+        assert!((cached.iter().sum::<f64>() / flux - 1.).abs() < 1e-3);
+        // the stars are moved by less than 1/16 pixel
+        let peak = per_star.iter().fold(0f64, |a, &i| a.max(i));
+        let error = per_star
+            .iter()
+            .zip(&cached)
+            .fold(0f64, |a, (i, c)| a.max((i - c).abs()));
+        assert!(error / peak < 1e-2, "{}", error / peak);
+        // stars with the same sub-pixel position share the cached point spread function
+        let stars: Vec<_> = [
+            (0.25, -0.375),
+            (5.25, -2.375),
+            (-7.75, 4.625),
+            (3.25, 9.625),
+        ]
+        .into_iter()
+        .map(|(x, y)| {
+            Star::new((
+                skyangle::SkyAngle::Arcsecond(x * resolution),
+                skyangle::SkyAngle::Arcsecond(y * resolution),
+            ))
+        })
+        .collect();
+        let intensity = |builder: FieldBuilder<Tel>| {
+            let mut field: Field<Tel, SeeingLimited> =
+                builder.field_of_view(41).objects(stars.clone()).build();
+            field.intensity(None)
+        };
+        let per_star = intensity(builder());
+        let cached = intensity(builder().psf_cache(8));
+        let peak = per_star.iter().fold(0f64, |a, &i| a.max(i));
+        let error = per_star
+            .iter()
+            .zip(&cached)
+            .fold(0f64, |a, (i, c)| a.max((i - c).abs()));
+        assert!(error / peak < 1e-9, "{}", error / peak);
+    }
+
+    #[test]
+    fn fits() {
+        let mut field: Field<Tel, DiffractionLimited> = builder().field_of_view(21).build();
+        let header = field.header();
+        assert_eq!(header.get("NSTAR"), Some(&crate::FitsValue::Integer(1)));
+        assert_eq!(header.get("TELESCOP"), Some(&"HST".into()));
+        let path = std::env::temp_dir().join("diffraction.fits");
+        field.save(&path, Default::default()).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(bytes.len() % 2880, 0);
+        assert!(bytes.len() >= 2880 + 21 * 21 * 8);
+        // the plane headers of a cube follow the cube
+        let headers = [
+            FitsHeader::new()
+                .keyword("BAND", "V", "")
+                .keyword("EXPTIME", 1f64, ""),
+            FitsHeader::new()
+                .keyword("BAND", "V", "")
+                .keyword("EXPTIME", 2f64, ""),
+        ];
+        let path = std::env::temp_dir().join("cube.fits");
+        save_fits(
+            &path,
+            headers
+                .iter()
+                .cloned()
+                .map(|h| (h, vec![0f64; 4]))
+                .collect(),
+            &SaveOptions::new().cube(),
+        )
+        .unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        let image = crate::FitsImage::from_bytes(&bytes).unwrap();
+        assert_eq!(image.shape, vec![2, 2, 2]);
+        assert_eq!(image.header.get("BAND"), Some(&"V".into()));
+        assert!(image.header.get("EXPTIME").is_none());
+        assert_eq!(bytes.len(), 4 * 2880);
+    }
+
+    #[test]
+    fn bandwidth() {
+        let flux = |n| {
+            let mut field: Field<Tel, SeeingLimited> = builder()
+                .field_of_view(51)
+                .photometry("K")
+                .bandwidth_sampling(n)
+                .build();
+            field.intensity(None).into_iter().sum::<f64>()
+        };
+        let (monochromatic, broadband) = (flux(1), flux(5));
+        assert!((broadband / monochromatic - 1.).abs() < 1e-2);
+    }
+
+    #[test]
+    fn position_angle() {
+        let d = SkyAngle::MilliArcsec(100f64);
+        let star = |x, y| Star::new((x, y));
+        let mut field: Field<Tel, DiffractionLimited> = builder()
+            .field_of_view(21)
+            .objects(star(SkyAngle::MilliArcsec(0f64), d))
+            .position_angle(SkyAngle::Degree(90.))
+            .build();
+        let rotated = field.intensity(None);
+        let mut field: Field<Tel, DiffractionLimited> = builder()
+            .field_of_view(21)
+            .objects(star(d, SkyAngle::MilliArcsec(0f64)))
+            .build();
+        let expected = field.intensity(None);
+        let peak = expected.iter().cloned().fold(0f64, f64::max);
+        assert!(rotated
+            .iter()
+            .zip(&expected)
+            .all(|(r, e)| (r - e).abs() < 1e-6 * peak));
+    }
+
+    #[test]
+    fn try_build() {
+        let field: Result<Field<Tel, DiffractionLimited>> =
+            builder().polychromatic::<&str>(vec![]).try_build();
+        assert!(matches!(field, Err(Error::NoPhotometry)));
+        let mut field: Field<Tel, DiffractionLimited> =
+            builder().field_of_view(21).try_build().unwrap();
+        let Err(error) = field.save("field.bmp", Default::default()) else {
+            panic!("bmp files are not supported")
+        };
+        assert!(matches!(error, Error::Extension(..)));
+        assert_eq!(
+            error.to_string(),
+            r#"unsupported file extension for "field.bmp", expected one of png, jpg, tiff, fits"#
+        );
+    }
+
     #[test]
     fn ser_ao() {
         let mut field: Field<Tel, AdaptiveOptics> = builder().build();
@@ -1,5 +1,10 @@
-use super::{FieldOfView, PixelScale};
-use crate::{Objects, Observer, Photometry, SeeingBuilder, Star};
+use skyangle::SkyAngle;
+
+use super::{Detector, FieldOfView, PixelScale};
+use crate::{
+    adaptive_optics::check_pupil_sampling, Error, Objects, Observer, Photometry, Result,
+    SeeingBuilder, SkyBackground, Star, Wcs, Zernike,
+};
 
 #[derive(Clone, Debug)]
 /// Field builder
@@ -29,6 +34,14 @@ pub struct FieldBuilder<T: Observer> {
     pub(super) observer: T,
     pub(super) seeing: Option<SeeingBuilder>,
     pub(super) flux: Option<f64>,
+    pub(super) wcs: Option<Wcs>,
+    pub(super) detector: Option<Detector>,
+    pub(super) sky_background: Option<SkyBackground>,
+    pub(super) bandwidth_sampling: usize,
+    pub(super) aberrations: Option<Zernike>,
+    pub(super) pupil_supersampling: usize,
+    pub(super) position_angle: f64,
+    pub(super) psf_cache: Option<usize>,
 }
 impl<T: Observer> FieldBuilder<T> {
     /// Creates a default field
@@ -43,9 +56,17 @@ impl<T: Observer> FieldBuilder<T> {
             objects: Star::default().into(),
             exposure: 1f64,
             poisson_noise: false,
-            observer,
             seeing: None,
             flux: None,
+            wcs: None,
+            detector: None,
+            sky_background: None,
+            bandwidth_sampling: 1,
+            aberrations: None,
+            pupil_supersampling: observer.supersampling(),
+            position_angle: 0f64,
+            psf_cache: None,
+            observer,
         }
     }
     /// Sets the [pixel scale](PixelScale)
@@ -76,6 +97,27 @@ impl<T: Observer> FieldBuilder<T> {
             ..self
         }
     }
+    /// Sets the number of wavelengths sampling the spectral bandwidth of the [photometry](Photometry)
+    ///
+    /// The field intensity is the sum of the monochromatic intensities at each wavelength,
+    /// the star flux being distributed among the wavelengths according to the filter transmission.
+    /// The default is a single sample at the central wavelength
+    pub fn bandwidth_sampling(self, n_wavelength: usize) -> Self {
+        Self {
+            bandwidth_sampling: n_wavelength.max(1),
+            ..self
+        }
+    }
+    /// Sets the number of points `n x n` where the observer transmission is evaluated within each pupil sample
+    ///
+    /// The supersampling results in fractional transmissions at the pupil edges, reducing
+    /// the aliasing of the pupil (default: [Observer::supersampling])
+    pub fn pupil_supersampling(self, pupil_supersampling: usize) -> Self {
+        Self {
+            pupil_supersampling: pupil_supersampling.max(1),
+            ..self
+        }
+    }
     /// Sets the [objects](Objects)
     pub fn objects<O: Into<Objects>>(self, objects: O) -> Self {
         Self {
@@ -102,6 +144,73 @@ impl<T: Observer> FieldBuilder<T> {
             ..self
         }
     }
+    /// Adds the [sky background](SkyBackground) to the image
+    ///
+    /// The sky background is added to the binned intensity together with its photon noise
+    /// if [photon noise](FieldBuilder::photon_noise) is set.
+    /// The [default](SkyBackground::Band) sky background of a [custom](crate::Photometry::custom) band
+    /// without [sky brightness](crate::Photometry::sky_background) is rejected when the field is built
+    pub fn sky_background<S: Into<SkyBackground>>(self, sky_background: S) -> Self {
+        Self {
+            sky_background: Some(sky_background.into()),
+            ..self
+        }
+    }
+    /// Sets the [detector](Detector)
+    ///
+    /// The detector is applied to the binned intensity and the field intensity is returned in ADU,
+    /// the electrons before the readout are given by [Field::electrons](crate::Field::electrons)
+    pub fn detector(self, detector: Detector) -> Self {
+        Self {
+            detector: Some(detector),
+            ..self
+        }
+    }
+    /// Sets the static [aberrations](Zernike) of the observer
+    ///
+    /// The aberrations are applied as a phase of the observer pupil in every observing mode
+    pub fn aberrations(self, aberrations: Zernike) -> Self {
+        Self {
+            aberrations: Some(aberrations),
+            ..self
+        }
+    }
+    /// Sets the position angle of the field
+    ///
+    /// The star field is rotated with respect to the detector, the detector +y axis being at
+    /// the position angle from the +y axis of the star coordinates toward the -x axis
+    /// (from north toward east, if north is up and east is left).
+    /// The position angle is added to the position angle of the [world coordinate system](Wcs)
+    pub fn position_angle(self, position_angle: SkyAngle<f64>) -> Self {
+        Self {
+            position_angle: position_angle.to_radians(),
+            ..self
+        }
+    }
+    /// Computes the star images from a cache of point spread functions
+    ///
+    /// The point spread function is computed once per wavelength for each of the `n x n` sub-pixel positions
+    /// of the stars, instead of once per star.
+    /// The star positions are quantised: each star is moved to the nearest sub-pixel position,
+    /// i.e. by up to half a pixel divided by `n` along each axis, and the residual offset is not applied
+    /// to the star image.
+    /// The cache is used only if the point spread function is the same for all the stars:
+    /// for diffraction limited fields and for seeing limited long exposures
+    pub fn psf_cache(self, n: usize) -> Self {
+        Self {
+            psf_cache: Some(n.max(1)),
+            ..self
+        }
+    }
+    /// Sets the [world coordinate system](Wcs) pointing and position angle
+    ///
+    /// The WCS reference pixel and pixel scale are set from the field
+    pub fn wcs(self, wcs: Wcs) -> Self {
+        Self {
+            wcs: Some(wcs),
+            ..self
+        }
+    }
     /// Sets the [seeing](SeeingBuilder)
     pub fn seeing_limited(self, seeing_builder: SeeingBuilder) -> Self {
         Self {
@@ -109,4 +218,34 @@ impl<T: Observer> FieldBuilder<T> {
             ..self
         }
     }
+    /// Checks the field settings
+    pub(super) fn validate(&self) -> Result<()> {
+        self.validate_seeing(self.seeing.as_ref())
+    }
+    /// Checks the field settings with the given seeing
+    pub(super) fn validate_seeing(&self, seeing: Option<&SeeingBuilder>) -> Result<()> {
+        if self.photometry.is_empty() {
+            return Err(Error::NoPhotometry);
+        }
+        if let Some((seeing, ao)) =
+            seeing.and_then(|seeing| seeing.adaptive_optics.as_ref().map(|ao| (seeing, ao)))
+        {
+            check_pupil_sampling(self.observer.resolution())?;
+            if let Some(ltao) = ao.laser_tomography() {
+                ltao.check_sodium_height(&seeing.turbulence)?;
+            }
+        }
+        if let Some(SkyBackground::Band) = self.sky_background {
+            if let Some(photometry) = self
+                .photometry
+                .iter()
+                .find(|photometry| !photometry.sky_brightness().is_finite())
+            {
+                return Err(Error::SkyBackground(format!(
+                    "the {photometry} band has no sky brightness, set it or set the sky surface brightness"
+                )));
+            }
+        }
+        Ok(())
+    }
 }
@@ -10,7 +10,7 @@ pub enum FieldOfView {
     /// ... as a multiple of the pixel scale
     PixelScale(usize),
     /// ... as a multiple of the pixel scale in the given [photometric band](crate::PhotometricBands)
-    PixelScaleAt(usize, String),
+    PixelScaleAt(usize, Photometry),
     /// ... as an [angle in the sky](https://docs.rs/skyangle/)
     SkyAngle(SkyAngle<f64>),
 }
@@ -30,12 +30,15 @@ impl<T: Observer, M: ObservingModes> From<&Field<T, M>> for FieldOfView {
     }
 }
 impl FieldOfView {
+    /// Field-of-view as a multiple of the pixel scale in the given [photometric band](Photometry)
+    pub fn pixel_scale_at<P: Into<Photometry>>(n: usize, band: P) -> Self {
+        FieldOfView::PixelScaleAt(n, band.into())
+    }
     pub(super) fn get<T: Observer, M: ObservingModes>(&self, field: &Field<T, M>) -> f64 {
         match self {
             FieldOfView::PixelScale(n) => field.resolution() * *n as f64,
-            FieldOfView::PixelScaleAt(n, band) => {
-                let photometry: Photometry = band.into();
-                field.pixel_scale.get(&field.observer, &photometry) * *n as f64
+            FieldOfView::PixelScaleAt(n, photometry) => {
+                field.pixel_scale.get(&field.observer, photometry) * *n as f64
             }
             FieldOfView::SkyAngle(val) => val.to_radians(),
         }
@@ -0,0 +1,216 @@
+use skyangle::Conversion;
+
+use crate::{
+    Actuators, AdaptiveOptics, DiffractionLimited, Field, FitsHeader, Mcao, Observer, Observing,
+    ObservingModes, SeeingLimited,
+};
+
+/// Observing mode [FITS](crate::FitsWriter) header keywords
+pub trait FitsKeywords {
+    fn keywords(&self) -> FitsHeader;
+}
+impl FitsKeywords for Observing<DiffractionLimited> {
+    fn keywords(&self) -> FitsHeader {
+        FitsHeader::new().keyword("OBSMODE", "diffraction limited", "observing mode")
+    }
+}
+impl FitsKeywords for Observing<SeeingLimited> {
+    fn keywords(&self) -> FitsHeader {
+        let mut header = FitsHeader::new().keyword("OBSMODE", "seeing limited", "observing mode");
+        if let Some(seeing) = self.seeing.as_ref() {
+            header
+                .push(
+                    "R0",
+                    seeing.fried_parameter,
+                    "Fried parameter at WAVELEN [m]",
+                )
+                .push("L0", seeing.outer_scale, "outer scale [m]");
+        }
+        if let Some((frozen_flow, time)) = self.frame.as_ref() {
+            header
+                .push(
+                    "SCRNSEED",
+                    frozen_flow.seed() as usize,
+                    "phase screens random seed",
+                )
+                .push("FRAMETIM", *time, "frame time [s]");
+        }
+        header
+    }
+}
+impl FitsKeywords for Observing<AdaptiveOptics> {
+    fn keywords(&self) -> FitsHeader {
+        let mut header = FitsHeader::new().keyword("OBSMODE", "adaptive optics", "observing mode");
+        if let Some(seeing) = self.seeing.as_ref() {
+            header
+                .push(
+                    "R0",
+                    seeing.fried_parameter,
+                    "Fried parameter at WAVELEN [m]",
+                )
+                .push("L0", seeing.outer_scale, "outer scale [m]");
+            if let Some(ao) = seeing.adaptive_optics.as_ref() {
+                if let Some(strehl_ratio) = ao.strehl_ratio() {
+                    header.push("AOSTREHL", strehl_ratio, "AO Strehl ratio");
+                }
+                match ao.actuators() {
+                    Some(Actuators::Pitch(pitch)) => {
+                        header.push("ACTPITCH", pitch, "actuator pitch [m]");
+                    }
+                    Some(Actuators::Count(count)) => {
+                        header.push("ACTCOUNT", count, "number of actuators across the pupil");
+                    }
+                    None => (),
+                }
+                if let Some(glao) = ao.ground_layer() {
+                    header
+                        .push(
+                            "GLAONGS",
+                            glao.asterism().len(),
+                            "number of GLAO guide stars",
+                        )
+                        .push(
+                            "DMHEIGHT",
+                            glao.conjugation_height(),
+                            "DM conjugation altitude [m]",
+                        )
+                        .push("ACTPITCH", glao.actuator_pitch(), "actuator pitch [m]");
+                }
+                if let Some(ltao) = ao.laser_tomography() {
+                    header
+                        .push(
+                            "LTAOLGS",
+                            ltao.laser_asterism().len(),
+                            "number of LTAO laser guide stars",
+                        )
+                        .push(
+                            "NAHEIGHT",
+                            ltao.sodium_height(),
+                            "sodium layer altitude [m]",
+                        )
+                        .push(
+                            "TTMAG",
+                            ltao.tip_tilt_star().magnitude,
+                            "tip-tilt star magnitude",
+                        )
+                        .push("ACTPITCH", ltao.actuator_pitch(), "actuator pitch [m]");
+                }
+            }
+        }
+        header
+    }
+}
+impl FitsKeywords for Observing<Mcao> {
+    fn keywords(&self) -> FitsHeader {
+        let mut header = FitsHeader::new().keyword(
+            "OBSMODE",
+            "multi-conjugate adaptive optics",
+            "observing mode",
+        );
+        if let Some(seeing) = self.seeing.as_ref() {
+            header
+                .push(
+                    "R0",
+                    seeing.fried_parameter,
+                    "Fried parameter at WAVELEN [m]",
+                )
+                .push("L0", seeing.outer_scale, "outer scale [m]");
+            if let Some(mcao) = seeing
+                .adaptive_optics
+                .as_ref()
+                .and_then(|ao| ao.multi_conjugate())
+            {
+                header
+                    .push(
+                        "MCAONGS",
+                        mcao.asterism().len(),
+                        "number of MCAO guide stars",
+                    )
+                    .push(
+                        "MCAODMS",
+                        mcao.deformable_mirrors().len(),
+                        "number of MCAO deformable mirrors",
+                    );
+            }
+        }
+        header
+    }
+}
+
+impl<T, Mode> Field<T, Mode>
+where
+    T: Observer,
+    Mode: ObservingModes,
+    Observing<Mode>: FitsKeywords,
+{
+    /// Returns the field [FITS](crate::FitsWriter) header
+    pub fn header(&self) -> FitsHeader {
+        let mut header = FitsHeader::new()
+            .keyword("TELESCOP", self.observer.name(), "telescope")
+            .keyword("BAND", self.photometry.to_string(), "photometric band")
+            .keyword(
+                "WAVELEN",
+                self.photometry.wavelength,
+                "band central wavelength [m]",
+            )
+            .keyword(
+                "PIXSCALE",
+                self.resolution().to_arcsec(),
+                "pixel scale [arcsec]",
+            )
+            .keyword(
+                "BANDWIDT",
+                self.photometry.spectral_bandwidth,
+                "band spectral bandwidth [m]",
+            )
+            .keyword(
+                "NWAVE",
+                self.bandwidth_sampling,
+                "number of wavelengths in the bandwidth",
+            )
+            .keyword(
+                "POSANGLE",
+                self.position_angle.to_degrees(),
+                "field position angle [deg]",
+            )
+            .keyword(
+                "PUPILROT",
+                self.observer.pupil_rotation().to_degrees(),
+                "pupil rotation angle [deg]",
+            )
+            .keyword(
+                "PUPSSAMP",
+                self.pupil_supersampling,
+                "pupil supersampling factor",
+            )
+            .keyword("EXPTIME", self.exposure, "exposure time [s]")
+            .keyword(
+                "NSTAR",
+                self.n_star(),
+                "number of stars in the field-of-view",
+            )
+            .keyword(
+                "BUNIT",
+                if self.detector.is_some() {
+                    "ADU"
+                } else {
+                    "photon"
+                },
+                "",
+            );
+        header.extend(self.observing_mode.keywords());
+        if let Some(aberrations) = self.aberrations.as_ref() {
+            header.extend(aberrations.header());
+        }
+        if let Some(sky_brightness) = self.sky_brightness() {
+            header.push("SKYBRIGH", sky_brightness, "sky brightness [mag/arcsec^2]");
+        }
+        if let Some(detector) = self.detector.as_ref() {
+            header.extend(detector.header());
+        }
+        if let Some(wcs) = self.wcs() {
+            header.extend(wcs.header());
+        }
+        header
+    }
+}
@@ -1,8 +1,17 @@
-use std::path::Path;
+use std::{f64::consts::PI, path::Path};
 
-use image::{ImageResult, Rgb, RgbImage};
+use image::{Rgb, RgbImage};
+use num_complex::Complex;
+use rand_distr::{Distribution, Poisson};
 
-use crate::{Field, Intensity, Observer, Observing, ObservingModes, PixelScale, SaveOptions};
+use super::{
+    field::{check_extension, save_fits},
+    FitsKeywords,
+};
+use crate::{
+    Field, FitsHeader, Intensity, Observer, Observing, ObservingModes, PixelScale, Result,
+    SaveOptions,
+};
 
 fn shift_and_add(buffer: &mut [f64], x0: f64, y0: f64, n: i32, intensity: Vec<f64>) {
     let i0 = x0 as i32;
@@ -60,6 +69,60 @@ fn binning(intensity_sampling: usize, m: usize, buffer: Vec<f64>) -> Vec<f64> {
     image
 }
 
+impl<T, Mode> Field<T, Mode>
+where
+    T: Observer,
+    Mode: ObservingModes,
+{
+    /// Returns the pupil phasor of the [observer](Observer::optical_path_difference) and static
+    /// [aberrations](crate::Zernike) at the given wavelength
+    fn aberrations(&self, wavelength: f64) -> Option<Vec<Complex<f64>>> {
+        let opd = match (
+            self.observer.optical_path_difference_map(),
+            self.aberrations.as_ref(),
+        ) {
+            (Some(mut opd), Some(aberrations)) => {
+                opd.iter_mut()
+                    .zip(aberrations.opd_map(&self.observer))
+                    .for_each(|(o, a)| *o += a);
+                Some(opd)
+            }
+            (None, Some(aberrations)) => Some(aberrations.opd_map(&self.observer)),
+            (opd, None) => opd,
+        };
+        opd.map(|opd| {
+            opd.into_iter()
+                .map(|opd| Complex::from_polar(1f64, 2. * PI * opd / wavelength))
+                .collect()
+        })
+    }
+    /// Bins the intensity by a factor `m` and adds the [sky background](crate::SkyBackground)
+    fn post_processing(&self, intensity_sampling: usize, m: usize, buffer: Vec<f64>) -> Vec<f64> {
+        let mut image = if m == 1 {
+            buffer
+        } else {
+            binning(intensity_sampling, m, buffer)
+        };
+        if let Some(n_sky) = self.sky_photons() {
+            if self.poisson_noise && n_sky > 0f64 {
+                let mut rng = rand::thread_rng();
+                let poi = Poisson::new(n_sky).unwrap();
+                image.iter_mut().for_each(|i| *i += poi.sample(&mut rng));
+            } else {
+                image.iter_mut().for_each(|i| *i += n_sky);
+            }
+        }
+        image
+    }
+    /// Reads out the [detector](crate::Detector), if any, converting the electrons into ADU
+    fn readout(&self, electrons: Vec<f64>) -> Vec<f64> {
+        match self.detector.as_ref() {
+            Some(detector) => detector.readout(&electrons, self.exposure),
+            None => electrons,
+        }
+    }
+}
+
 #[cfg(feature = "parallel")]
 mod parallel;
 #[cfg(not(feature = "parallel"))]
@@ -71,17 +134,19 @@ pub struct FieldImage {
     pixel_scale: PixelScale,
     resolution: (usize, usize),
     pixels: Vec<f64>,
+    header: FitsHeader,
 }
 impl<T, M> From<Field<T, M>> for FieldImage
 where
-    T: Observer,
+    T: Observer + Sync + Send,
     M: ObservingModes + Send,
-    Observing<M>: Intensity,
+    Observing<M>: Intensity + FitsKeywords,
 {
     fn from(mut field: Field<T, M>) -> Self {
         let pixels = field.intensity(None);
-        let n = field.intensity_sampling.unwrap();
+        let n = (pixels.len() as f64).sqrt() as usize;
         FieldImage {
+            header: field.header(),
             pixel_scale: field.pixel_scale,
             resolution: (n, n),
             pixels,
@@ -105,10 +170,19 @@ impl FieldImage {
     pub fn flux(&self) -> f64 {
         self.pixels.iter().sum()
     }
-    pub fn save<P: AsRef<Path>>(&self, path: P, save_options: SaveOptions) -> ImageResult<()> {
+    /// Returns the [FITS](crate::FitsWriter) header of the field the image is derived from
+    pub fn header(&self) -> &FitsHeader {
+        &self.header
+    }
+    /// Saves the image to file
+    ///
+    /// The image format is set from the file extension: `png`, `jpg`, `tiff` or `fits`
+    pub fn save<P: AsRef<Path>>(&self, path: P, save_options: SaveOptions) -> Result<()> {
+        let extension = check_extension(path.as_ref(), &["png", "jpg", "tiff", "fits"])?;
         let mut intensity = self.pixels.clone();
-        match path.as_ref().extension().and_then(|p| p.to_str()) {
-            Some("png" | "jpg" | "tiff") => {
+        match extension {
+            "fits" => save_fits(path, vec![(self.header.clone(), intensity)], &save_options)?,
+            "png" | "jpg" | "tiff" => {
                 if let Some(lufn) = save_options.lufn {
                     intensity.iter_mut().for_each(|i| *i = lufn(*i));
                 }
@@ -122,12 +196,9 @@ impl FieldImage {
                 img.pixels_mut().zip(&intensity).for_each(|(p, i)| {
                     *p = Rgb(lut.eval_continuous(*i).into_array());
                 });
-                img.save(path.as_ref()).expect(&format!(
-                    "failed to write field intensity into image {:?}",
-                    path.as_ref()
-                ))
+                img.save(path.as_ref())?
             }
-            _ => unimplemented!(),
+            _ => unreachable!(),
         };
         Ok(())
     }
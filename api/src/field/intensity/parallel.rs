@@ -1,4 +1,6 @@
-use super::{binning, shift_and_add};
+use std::collections::HashMap;
+
+use super::shift_and_add;
 use crate::{Field, Intensity, Observer, Observing, ObservingModes};
 use indicatif::ProgressBar;
 use rand_distr::{Distribution, Poisson};
@@ -10,7 +12,18 @@ where
     Observing<Mode>: Intensity,
 {
     /// Computes field-of-view intensity map
+    ///
+    /// The intensity is given in ADU if a [detector](crate::Detector) is set,
+    /// see [Field::electrons] for the map of the electrons before the detector readout
     pub fn intensity(&mut self, bar: Option<ProgressBar>) -> Vec<f64> {
+        let electrons = self.electrons(bar);
+        self.readout(electrons)
+    }
+    /// Computes field-of-view map of the electrons collected during the exposure
+    ///
+    /// The map includes the [sky background](crate::SkyBackground) and the photon noise,
+    /// if [set](crate::FieldBuilder::photon_noise), but not the detector noises
+    pub fn electrons(&mut self, bar: Option<ProgressBar>) -> Vec<f64> {
         // Telescope Nyquist-Shannon sampling criteria
         // let nyquist = 0.5 * self.photometry.wavelength / self.observer.diameter();
         // Image resolution to sampling criteria ratio
@@ -19,122 +32,281 @@ where
             .to_nyquist_clamped_ratio(&self.observer, &self.photometry);
         // Intensity sampling (oversampled wrt. image by factor b>=1)
         let intensity_sampling = (b * self.field_of_view.to_pixelscale_ratio(self)).ceil() as usize;
-        // Pupil size according to intensity angular resolution
-        let pupil_size = b * self.photometry.wavelength / self.resolution();
-        // FFT sampling based on pupil spatial resolution
-        let mut n_dft = (pupil_size / self.observer.resolution()).ceil() as usize;
-        // Match parity of FFT and intensity sampling if the latter is larger
-        if intensity_sampling > n_dft && intensity_sampling % 2 != n_dft % 2 {
-            n_dft += 1;
-        }
-        log::debug!(
-            r"
- . Image sampling: {intensity_sampling}:{b}
- . Pupil size    : {pupil_size:.3}m
- . DFT sampling  : {n_dft}
-         "
-        );
-        // Zero-padding discrete Fourier transform
-        self.observing_mode
-            .init_fft(n_dft, self.observer.resolution());
-
         // star image stacking buffer
         let mut buffer = vec![0f64; intensity_sampling.pow(2)];
         let n = intensity_sampling as i32;
         let alpha = self.resolution() / b;
+        // wavelengths sampling the spectral bandwidth
+        let spectral_samples = self.photometry.spectral_samples(self.bandwidth_sampling);
+        let n_wavelength = spectral_samples.len();
+        if let Some(bar) = bar.as_ref().filter(|_| n_wavelength > 1) {
+            bar.set_length((self.objects.len() * n_wavelength) as u64);
+        }
+        let observing_mode = Intensity::clone(&self.observing_mode);
 
         let n_threads = match std::env::var("N_THREAD") {
             Ok(n) => n.parse::<usize>().unwrap(),
             Err(__) => num_cpus::get(),
         };
         log::info!("Computing intensity in parallel using {n_threads} threads");
-        for star_chunk in self.objects.chunks(n_threads) {
-            let intensities: Vec<_> = std::thread::scope(|s| {
-                log::info!("starting intensity batch");
-                let mut intensity_scope = vec![];
-                for star in star_chunk {
-                    bar.as_ref().map(|b| b.inc(1));
-                    // todo: check if star is within FOV (rejection criteria?)
-                    if !star.inside_box(self.field_of_view() + self.resolution() * 2.) {
-                        continue;
-                    }
+        for (wavelength, weight) in spectral_samples {
+            // Pupil size according to intensity angular resolution
+            let pupil_size = b * wavelength / self.resolution();
+            // FFT sampling based on pupil spatial resolution
+            let mut n_dft = (pupil_size / self.observer.resolution()).ceil() as usize;
+            // Match parity of FFT and intensity sampling if the latter is larger
+            if intensity_sampling > n_dft && intensity_sampling % 2 != n_dft % 2 {
+                n_dft += 1;
+            }
+            log::debug!(
+                r"
+ . Wavelength    : {:.3}nm
+ . Image sampling: {intensity_sampling}:{b}
+ . Pupil size    : {pupil_size:.3}m
+ . DFT sampling  : {n_dft}
+         ",
+                wavelength * 1e9
+            );
+            // seeing at the sampled wavelength
+            self.observing_mode =
+                observing_mode.at_wavelength(wavelength, self.photometry.wavelength);
+            // pupil aberrations at the sampled wavelength
+            let aberrations = self.aberrations(wavelength);
+            // atmospheric dispersion at the sampled wavelength
+            let dispersion = self
+                .observing_mode
+                .dispersion(wavelength, self.photometry.wavelength);
+            // Zero-padding discrete Fourier transform
+            self.observing_mode.init_fft(
+                n_dft,
+                self.observer.resolution(),
+                self.observer.diameter(),
+            );
+            // point spread functions of a single photon at the sub-pixel positions of the stars
+            let mut psf_cache: HashMap<(i64, i64), Vec<f64>> = HashMap::new();
+            let psf_sampling = self
+                .psf_cache
+                .filter(|_| self.observing_mode.shift_invariant());
+            for star_chunk in self.objects.chunks(n_threads) {
+                // stars in the detector frame with their number of photons, their pixel and sub-pixel positions
+                let stars: Vec<_> = star_chunk
+                    .iter()
+                    .filter_map(|star| {
+                        if let Some(b) = bar.as_ref() {
+                            b.inc(1)
+                        }
+                        // star in the detector frame
+                        let image_star = dispersion
+                            .map_or(*star, |dispersion| star.shift(dispersion))
+                            .rotate(self.position_angle);
+                        // todo: check if star is within FOV (rejection criteria?)
+                        if !image_star.inside_box(self.field_of_view() + self.resolution() * 2.) {
+                            return None;
+                        }
 
-                    let n_photon = self.flux.unwrap_or(
-                        self.photometry.n_photon(star.magnitude)
-                            * self.exposure
-                            * self.observer.resolution().powi(2), //  * self.observer.area() ,
-                    );
-                    // star coordinates
-                    let (x, y) = star.coordinates;
-                    // integer part
-                    let x0 = -(y / alpha).round();
-                    let y0 = (x / alpha).round();
-                    // fractional part
-                    let fr_x0 = -y.to_radians() - x0 * alpha;
-                    let fr_y0 = x.to_radians() - y0 * alpha;
-                    // image fractional translation by Fourier interpolation
-                    let shift = if intensity_sampling % 2 == 0 {
-                        Some((
-                            0.5 / pupil_size + fr_x0 / self.photometry.wavelength,
-                            0.5 / pupil_size + fr_y0 / self.photometry.wavelength,
-                        ))
-                    } else {
-                        Some((
-                            fr_x0 / self.photometry.wavelength,
-                            fr_y0 / self.photometry.wavelength,
-                        ))
-                    };
-                    let mut observing_mode = self.observing_mode.clone();
+                        let n_photon = self.flux.unwrap_or(
+                            self.photometry.n_photon(star.magnitude)
+                                * self.exposure
+                                * self.observer.resolution().powi(2), //  * self.observer.area() ,
+                        ) * weight;
+                        // star coordinates
+                        let (x, y) = image_star.coordinates;
+                        // integer part
+                        let x0 = -(y / alpha).round();
+                        let y0 = (x / alpha).round();
+                        // fractional part
+                        let fr_x0 = -y.to_radians() - x0 * alpha;
+                        let fr_y0 = x.to_radians() - y0 * alpha;
+                        // nearest sub-pixel position of the cached point spread functions
+                        let (fr_x0, fr_y0, key) = match psf_sampling {
+                            Some(m) => {
+                                let step = alpha / m as f64;
+                                let (i, j) = ((fr_x0 / step).round(), (fr_y0 / step).round());
+                                (i * step, j * step, Some((i as i64, j as i64)))
+                            }
+                            None => (fr_x0, fr_y0, None),
+                        };
+                        // image fractional translation by Fourier interpolation
+                        let shift = if intensity_sampling.is_multiple_of(2) {
+                            Some((
+                                0.5 / pupil_size + fr_x0 / wavelength,
+                                0.5 / pupil_size + fr_y0 / wavelength,
+                            ))
+                        } else {
+                            Some((fr_x0 / wavelength, fr_y0 / wavelength))
+                        };
+                        Some((star, image_star, n_photon, (x0, y0), shift, key))
+                    })
+                    .collect();
+
+                let pupil_resolution = self.observer.resolution();
+                let pupil_diameter = self.observer.diameter();
+                let pupil_supersampling = self.pupil_supersampling;
+                let observer = &self.observer;
+                let aberrations = aberrations.as_ref();
+                // sub-pixel positions of the chunk missing from the cache
+                let mut missing: Vec<_> = stars
+                    .iter()
+                    .filter_map(|(star, _, _, _, shift, key)| {
+                        key.filter(|key| !psf_cache.contains_key(key))
+                            .map(|key| (key, *shift, *star))
+                    })
+                    .collect();
+                missing.sort_by_key(|(key, ..)| *key);
+                missing.dedup_by_key(|(key, ..)| *key);
+                // point spread functions of a single photon at the missing sub-pixel positions
+                let psfs: Vec<_> = std::thread::scope(|s| {
+                    let psf_threads: Vec<_> = missing
+                        .into_iter()
+                        .map(|(key, shift, star)| {
+                            let mut observing_mode = self.observing_mode.clone();
+                            s.spawn(move || {
+                                observing_mode.init_fft(n_dft, pupil_resolution, pupil_diameter);
+                                let mut pupil =
+                                    observer.supersampled_pupil(shift, pupil_supersampling);
+                                if let Some(aberrations) = aberrations {
+                                    pupil.iter_mut().zip(aberrations).for_each(|(p, a)| *p *= a);
+                                }
+                                let psf = observing_mode
+                                    .intensity(pupil, intensity_sampling, star)
+                                    .unwrap();
+                                (key, psf)
+                            })
+                        })
+                        .collect();
+                    psf_threads
+                        .into_iter()
+                        .map(|psf_thread| psf_thread.join().unwrap())
+                        .collect()
+                });
+                psf_cache.extend(psfs);
 
-                    let pupil_resolution = self.observer.resolution();
-                    let mut pupil = self.observer.pupil(shift);
-                    pupil.iter_mut().for_each(|p| *p *= n_photon.sqrt());
+                let intensities: Vec<_> = std::thread::scope(|s| {
+                    log::info!("starting intensity batch");
+                    let mut intensity_scope = vec![];
+                    for (star, image_star, n_photon, (x0, y0), shift, key) in stars {
+                        let psf = key.and_then(|key| psf_cache.get(&key));
+                        // star pupil, unless the point spread function is cached
+                        let pupil = psf.is_none().then(|| {
+                            let mut pupil = observer.supersampled_pupil(shift, pupil_supersampling);
+                            pupil.iter_mut().for_each(|p| *p *= n_photon.sqrt());
+                            if let Some(aberrations) = aberrations {
+                                pupil.iter_mut().zip(aberrations).for_each(|(p, a)| *p *= a);
+                            }
+                            // short-exposure atmospheric phase screens in the star direction
+                            if let Some(phase_screen) = self
+                                .observing_mode
+                                .phase_screen(&self.observer, &image_star)
+                            {
+                                pupil
+                                    .iter_mut()
+                                    .zip(phase_screen)
+                                    .for_each(|(p, a)| *p *= a);
+                            }
+                            (self.observing_mode.clone(), pupil)
+                        });
 
-                    let poisson_noise = self.poisson_noise;
+                        let poisson_noise = self.poisson_noise;
 
-                    let intensity_thread = s.spawn(move || {
-                        let mut rng = rand::thread_rng();
-                        // star intensity map
-                        // Zero-padding discrete Fourier transform
-                        observing_mode.init_fft(n_dft, pupil_resolution);
-                        let mut intensity = observing_mode
-                            .intensity(pupil, intensity_sampling, star)
-                            .unwrap();
-                        // intensity set to # of photon & Poisson noise
-                        // log::debug!("Image flux: {n_photon}");
-                        if poisson_noise {
-                            intensity.iter_mut().for_each(|i| {
-                                if *i == 0f64 {
-                                    *i = 0f64;
-                                } else {
-                                    let poi = Poisson::new(*i).unwrap();
-                                    *i = poi.sample(&mut rng)
+                        let intensity_thread = s.spawn(move || {
+                            let mut rng = rand::thread_rng();
+                            // star intensity map
+                            let mut intensity = match (psf, pupil) {
+                                (Some(psf), _) => psf.iter().map(|i| i * n_photon).collect(),
+                                (None, None) => {
+                                    unreachable!("the pupil is set if the PSF is not cached")
                                 }
-                            })
-                        };
-                        (x0, y0, intensity)
-                    });
-                    intensity_scope.push(intensity_thread);
-                }
-                intensity_scope
-                    .into_iter()
-                    .map(|intensity_thread| intensity_thread.join().unwrap())
-                    .collect()
-            });
+                                (None, Some((mut observing_mode, pupil))) => {
+                                    // Zero-padding discrete Fourier transform
+                                    observing_mode.init_fft(
+                                        n_dft,
+                                        pupil_resolution,
+                                        pupil_diameter,
+                                    );
+                                    observing_mode
+                                        .intensity(pupil, intensity_sampling, star)
+                                        .unwrap()
+                                }
+                            };
+                            // intensity set to # of photon & Poisson noise
+                            // log::debug!("Image flux: {n_photon}");
+                            if poisson_noise {
+                                intensity.iter_mut().for_each(|i| {
+                                    if *i == 0f64 {
+                                        *i = 0f64;
+                                    } else {
+                                        let poi = Poisson::new(*i).unwrap();
+                                        *i = poi.sample(&mut rng)
+                                    }
+                                })
+                            };
+                            (x0, y0, intensity)
+                        });
+                        intensity_scope.push(intensity_thread);
+                    }
+                    intensity_scope
+                        .into_iter()
+                        .map(|intensity_thread| intensity_thread.join().unwrap())
+                        .collect()
+                });
 
-            log::info!("star images shift & add");
-            for (x0, y0, intensity) in intensities.into_iter() {
-                // shift and add star images
-                shift_and_add(buffer.as_mut_slice(), x0, y0, n, intensity);
+                log::info!("star images shift & add");
+                for (x0, y0, intensity) in intensities.into_iter() {
+                    // shift and add star images
+                    shift_and_add(buffer.as_mut_slice(), x0, y0, n, intensity);
+                }
             }
         }
-        bar.as_ref().map(|b| b.finish());
-
-        let m = b as usize;
-        if m == 1 {
-            return buffer;
+        self.observing_mode = observing_mode;
+        if let Some(b) = bar.as_ref() {
+            b.finish()
         }
-        // binning
-        binning(intensity_sampling, m, buffer)
+
+        self.intensity_sampling = Some(intensity_sampling);
+
+        self.post_processing(intensity_sampling, b as usize, buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{field::tests::hst_field, Builder, DiffractionLimited, Field, Hst, Star};
+    use skyangle::SkyAngle;
+
+    #[test]
+    fn psf_cache() {
+        let resolution = {
+            let field: Field<Hst, DiffractionLimited> = hst_field().build();
+            field.resolution().to_degrees() * 3600.
+        };
+        // more stars than threads, sharing a few sub-pixel positions within and across the star chunks
+        let n_star = 2 * num_cpus::get() + 1;
+        let stars: Vec<_> = (0..n_star)
+            .map(|k| {
+                let (x, y) = ((k % 7) as f64 - 3., (k / 7) as f64 - 3.);
+                let fr = 0.25 * (k % 3) as f64;
+                Star::new((
+                    SkyAngle::Arcsecond((x + fr) * resolution),
+                    SkyAngle::Arcsecond((y - fr) * resolution),
+                ))
+            })
+            .collect();
+        let intensity = |psf_cache: Option<usize>| {
+            let builder = hst_field().objects(stars.clone());
+            let mut field: Field<Hst, DiffractionLimited> = match psf_cache {
+                Some(m) => builder.psf_cache(m),
+                None => builder,
+            }
+            .build();
+            field.intensity(None)
+        };
+        let per_star = intensity(None);
+        let cached = intensity(Some(8));
+        let peak = per_star.iter().fold(0f64, |a, &i| a.max(i));
+        let error = per_star
+            .iter()
+            .zip(&cached)
+            .fold(0f64, |a, (i, c)| a.max((i - c).abs()));
+        assert!(error / peak < 1e-9, "{}", error / peak);
     }
 }
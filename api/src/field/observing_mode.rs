@@ -1,7 +1,9 @@
-use std::{fmt::Display, marker::PhantomData};
+use std::{fmt::Display, marker::PhantomData, sync::Arc};
 
-use super::{AdaptiveOptics, DiffractionLimited, ObservingModes, SeeingLimited, SeeingModes};
-use crate::{atmosphere_transfer_function, SeeingBuilder, Star, ZpDft};
+use super::{AdaptiveOptics, DiffractionLimited, Mcao, ObservingModes, SeeingLimited, SeeingModes};
+use crate::{
+    atmosphere_transfer_function, phase_screen::FrozenFlow, Observer, SeeingBuilder, Star, ZpDft,
+};
 use num_complex::Complex;
 
 /// Observing configurations
@@ -11,6 +13,7 @@ pub struct Observing<Mode: ObservingModes> {
     ifft: Option<ZpDft>,
     otf: Option<Vec<Complex<f64>>>,
     pub(crate) seeing: Option<SeeingBuilder>,
+    pub(crate) frame: Option<(Arc<FrozenFlow>, f64)>,
     mode: PhantomData<Mode>,
 }
 
@@ -32,6 +35,7 @@ impl Observing<DiffractionLimited> {
             ifft: None,
             otf: None,
             seeing: None,
+            frame: None,
             mode: PhantomData,
         }
     }
@@ -45,13 +49,69 @@ impl<M: SeeingModes> Observing<M> {
             ifft: None,
             otf: None,
             seeing,
+            frame: None,
             mode: PhantomData,
         }
     }
 }
 
+impl<Mode: ObservingModes> Observing<Mode>
+where
+    Observing<Mode>: Intensity,
+{
+    /// Returns a new observing mode with the seeing scaled from the reference wavelength to the given wavelength
+    pub(crate) fn at_wavelength(&self, wavelength: f64, reference_wavelength: f64) -> Self {
+        let mut observing_mode = Intensity::clone(self);
+        observing_mode.seeing = observing_mode
+            .seeing
+            .map(|seeing| seeing.scale_wavelength(wavelength, reference_wavelength));
+        observing_mode
+    }
+    /// Returns the sky coordinates offsets in radians of the stars at the wavelength
+    /// due to the [atmospheric refraction](crate::AtmosphericRefraction)
+    pub(crate) fn dispersion(
+        &self,
+        wavelength: f64,
+        reference_wavelength: f64,
+    ) -> Option<(f64, f64)> {
+        self.seeing
+            .as_ref()
+            .and_then(|seeing| seeing.dispersion(wavelength, reference_wavelength))
+    }
+    /// Returns the pupil phasor of the short-exposure [phase screens](crate::PhaseScreen)
+    /// in the direction of the star
+    ///
+    /// Returns `None` if the seeing is not set or for long exposures
+    pub(crate) fn phase_screen<T: Observer>(
+        &self,
+        observer: &T,
+        star: &Star,
+    ) -> Option<Vec<Complex<f64>>> {
+        self.frame
+            .as_ref()
+            .zip(self.seeing.as_ref())
+            .map(|((frozen_flow, time), seeing)| {
+                let (x, y) = star.coordinates;
+                // star direction in the pupil frame
+                let theta = (-y.to_radians(), x.to_radians());
+                let diameter = observer.diameter();
+                let n_px = (diameter / observer.resolution()).round() as usize + 1;
+                let l = (n_px - 1) as f64;
+                (0..n_px)
+                    .flat_map(|i| (0..n_px).map(move |j| (i, j)))
+                    .map(|(i, j)| {
+                        let x = (j as f64 / l - 0.5) * diameter;
+                        let y = (i as f64 / l - 0.5) * diameter;
+                        let phase = frozen_flow.phase((x, y), theta, *time, seeing.fried_parameter);
+                        Complex::from_polar(1f64, phase)
+                    })
+                    .collect()
+            })
+    }
+}
+
 pub trait Intensity {
-    fn init_fft(&mut self, n_dft: usize, pupil_resolution: f64);
+    fn init_fft(&mut self, n_dft: usize, pupil_resolution: f64, pupil_diameter: f64);
     fn clone(&self) -> Self;
     fn intensity(
         &mut self,
@@ -59,9 +119,13 @@ pub trait Intensity {
         intensity_sampling: usize,
         star: &Star,
     ) -> Option<Vec<f64>>;
+    /// Returns true if the intensity is the same for all the stars apart from their positions
+    fn shift_invariant(&self) -> bool {
+        false
+    }
 }
 impl Intensity for Observing<DiffractionLimited> {
-    fn init_fft(&mut self, n_dft: usize, _pupil_resolution: f64) {
+    fn init_fft(&mut self, n_dft: usize, _pupil_resolution: f64, _pupil_diameter: f64) {
         self.fft = Some(ZpDft::forward(n_dft));
     }
 
@@ -85,10 +149,17 @@ impl Intensity for Observing<DiffractionLimited> {
     fn clone(&self) -> Observing<DiffractionLimited> {
         Self::diffraction_limited()
     }
+    fn shift_invariant(&self) -> bool {
+        true
+    }
 }
 impl Intensity for Observing<SeeingLimited> {
-    fn init_fft(&mut self, n_dft: usize, pupil_resolution: f64) {
+    fn init_fft(&mut self, n_dft: usize, pupil_resolution: f64, _pupil_diameter: f64) {
         self.fft = Some(ZpDft::forward(n_dft));
+        // short exposures are diffraction limited images of the phase screens
+        if self.frame.is_some() {
+            return;
+        }
         self.ifft = Some(ZpDft::inverse(n_dft));
         self.otf = match self.seeing {
             Some(SeeingBuilder {
@@ -110,6 +181,17 @@ impl Intensity for Observing<SeeingLimited> {
         intensity_sampling: usize,
         _: &Star,
     ) -> Option<Vec<f64>> {
+        if self.frame.is_some() {
+            return self.fft.as_mut().map(|zp_dft| {
+                zp_dft
+                    .reset()
+                    .zero_padding(pupil)
+                    .process()
+                    .shift()
+                    .resize(intensity_sampling)
+                    .norm_sqr()
+            });
+        }
         self.fft
             .as_mut()
             .zip(self.ifft.as_mut())
@@ -138,11 +220,19 @@ impl Intensity for Observing<SeeingLimited> {
     }
 
     fn clone(&self) -> Self {
-        Self::seeing_limited(self.seeing.clone())
+        Self {
+            frame: self.frame.clone(),
+            ..Self::seeing_limited(self.seeing.clone())
+        }
+    }
+    fn shift_invariant(&self) -> bool {
+        // short exposures depend on the phase screens in the star direction
+        self.frame.is_none()
     }
 }
-impl Intensity for Observing<AdaptiveOptics> {
-    fn init_fft(&mut self, n_dft: usize, pupil_resolution: f64) {
+impl<M: SeeingModes> Observing<M> {
+    /// Initializes the FFTs and the transfer function of the adaptive optics correction
+    fn init_adaptive_optics(&mut self, n_dft: usize, pupil_resolution: f64, pupil_diameter: f64) {
         self.fft = Some(ZpDft::forward(n_dft));
         self.ifft = Some(ZpDft::inverse(n_dft));
         self.seeing.as_mut().map(
@@ -150,11 +240,13 @@ impl Intensity for Observing<AdaptiveOptics> {
                  fried_parameter,
                  outer_scale,
                  adaptive_optics,
+                 ..
              }| {
                 adaptive_optics.as_mut().map(|aoc| {
                     aoc.init_transfer_function(
                         n_dft,
                         pupil_resolution,
+                        pupil_diameter,
                         *fried_parameter,
                         *outer_scale,
                     )
@@ -162,7 +254,8 @@ impl Intensity for Observing<AdaptiveOptics> {
             },
         );
     }
-    fn intensity(
+    /// Returns the intensity map of the star corrected by adaptive optics
+    fn adaptive_optics_intensity(
         &mut self,
         pupil: Vec<Complex<f64>>,
         intensity_sampling: usize,
@@ -179,12 +272,17 @@ impl Intensity for Observing<AdaptiveOptics> {
                         fried_parameter,
                         outer_scale,
                         adaptive_optics,
+                        turbulence,
+                        wavelength,
+                        ..
                     },
                 )| {
                     let otf: Vec<_> = adaptive_optics.as_mut().unwrap().transfer_function(
                         *fried_parameter,
                         *outer_scale,
+                        turbulence,
                         star,
+                        *wavelength,
                     );
                     // .into_iter()
                     // .map(|o| Complex::new(o, 0f64))
@@ -211,6 +309,36 @@ impl Intensity for Observing<AdaptiveOptics> {
                 },
             )
     }
+}
+impl Intensity for Observing<AdaptiveOptics> {
+    fn init_fft(&mut self, n_dft: usize, pupil_resolution: f64, pupil_diameter: f64) {
+        self.init_adaptive_optics(n_dft, pupil_resolution, pupil_diameter);
+    }
+    fn intensity(
+        &mut self,
+        pupil: Vec<Complex<f64>>,
+        intensity_sampling: usize,
+        star: &Star,
+    ) -> Option<Vec<f64>> {
+        self.adaptive_optics_intensity(pupil, intensity_sampling, star)
+    }
+
+    fn clone(&self) -> Self {
+        Self::seeing_limited(self.seeing.clone())
+    }
+}
+impl Intensity for Observing<Mcao> {
+    fn init_fft(&mut self, n_dft: usize, pupil_resolution: f64, pupil_diameter: f64) {
+        self.init_adaptive_optics(n_dft, pupil_resolution, pupil_diameter);
+    }
+    fn intensity(
+        &mut self,
+        pupil: Vec<Complex<f64>>,
+        intensity_sampling: usize,
+        star: &Star,
+    ) -> Option<Vec<f64>> {
+        self.adaptive_optics_intensity(pupil, intensity_sampling, star)
+    }
 
     fn clone(&self) -> Self {
         Self::seeing_limited(self.seeing.clone())
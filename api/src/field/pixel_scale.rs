@@ -9,11 +9,11 @@ pub enum PixelScale {
     /// ... as a multiple of the Nyquist sampling criteria λ/2D
     Nyquist(u32),
     /// ... as a multiple of the Nyquist sampling criteria λ/2D in the given [photometric band](crate::PhotometricBands)
-    NyquistAt(u32, String),
+    NyquistAt(u32, Photometry),
     /// ... as a fraction of the Nyquist sampling criteria λ/2D
     NyquistFraction(u32),
     /// ... as a fraction of the Nyquist sampling criteria λ/2D in the given [photometric band](crate::PhotometricBands)
-    NyquistFractionAt(u32, String),
+    NyquistFractionAt(u32, Photometry),
     /// ... as an [angle in the sky](https://docs.rs/skyangle/)
     SkyAngle(SkyAngle<f64>),
 }
@@ -33,18 +33,24 @@ impl<T: Observer, M: ObservingModes> From<&Field<T, M>> for PixelScale {
     }
 }
 impl PixelScale {
+    /// Pixel scale as a multiple of the Nyquist sampling criteria in the given [photometric band](Photometry)
+    pub fn nyquist_at<P: Into<Photometry>>(n: u32, band: P) -> Self {
+        PixelScale::NyquistAt(n, band.into())
+    }
+    /// Pixel scale as a fraction of the Nyquist sampling criteria in the given [photometric band](Photometry)
+    pub fn nyquist_fraction_at<P: Into<Photometry>>(n: u32, band: P) -> Self {
+        PixelScale::NyquistFractionAt(n, band.into())
+    }
     pub(super) fn get<T: Observer>(&self, observer: &T, photometry: &Photometry) -> f64 {
         match self {
             PixelScale::NyquistFraction(n) => {
                 0.5 * photometry.wavelength / observer.diameter() / *n as f64
             }
-            PixelScale::NyquistFractionAt(n, band) => {
-                let photometry: Photometry = band.into();
+            PixelScale::NyquistFractionAt(n, photometry) => {
                 0.5 * photometry.wavelength / observer.diameter() / *n as f64
             }
             PixelScale::Nyquist(n) => 0.5 * photometry.wavelength / observer.diameter() * *n as f64,
-            PixelScale::NyquistAt(n, band) => {
-                let photometry: Photometry = band.into();
+            PixelScale::NyquistAt(n, photometry) => {
                 0.5 * photometry.wavelength / observer.diameter() * *n as f64
             }
             PixelScale::SkyAngle(val) => val.to_radians(),
@@ -1,11 +1,12 @@
 use std::path::Path;
 
-use image::{ImageResult, Rgb, RgbImage};
+use image::{Rgb, RgbImage};
 use indicatif::{ProgressBar, ProgressStyle};
 
-use crate::Observer;
+use crate::{Observer, Result};
 
 use super::{
+    field::{check_extension, save_fits},
     Builder, DiffractionLimited, Field, FieldBuilder, Observing, SaveOptions, SeeingLimited,
 };
 
@@ -18,6 +19,11 @@ impl<T: Observer> Builder<PolychromaticField<T>> for FieldBuilder<T> {
     fn build(self) -> PolychromaticField<T> {
         PolychromaticField(self)
     }
+    /// Creates a new field, returning an [error](crate::Error) if the field settings are invalid
+    fn try_build(self) -> Result<PolychromaticField<T>> {
+        self.validate()?;
+        Ok(self.build())
+    }
 }
 impl<T> PolychromaticField<T>
 where
@@ -28,9 +34,14 @@ where
         self.0.photometry.len()
     }
     /// Computes image and save it to file
-    pub fn save<P: AsRef<Path>>(&mut self, path: P, save_options: SaveOptions) -> ImageResult<()> {
+    ///
+    /// The images are either saved side by side in a `png`, `jpg` or `tiff` file
+    /// or in a `fits` file, see [SaveOptions::cube]
+    pub fn save<P: AsRef<Path>>(&mut self, path: P, save_options: SaveOptions) -> Result<()> {
+        let extension = check_extension(path.as_ref(), &["png", "jpg", "tiff", "fits"])?;
         let mut intensities = vec![];
-        for field_photometry in self.0.photometry.iter().cloned() {
+        let mut headers = vec![];
+        for field_photometry in self.0.photometry.iter() {
             let FieldBuilder {
                 pixel_scale,
                 field_of_view,
@@ -41,6 +52,14 @@ where
                 observer,
                 seeing,
                 flux,
+                wcs,
+                detector,
+                sky_background,
+                bandwidth_sampling,
+                aberrations,
+                pupil_supersampling,
+                position_angle,
+                psf_cache,
             } = self.0.clone();
             let bar = save_options
                 .mbar
@@ -55,25 +74,34 @@ where
                     .unwrap(),
                 )
             });
-            let mut intensity = if seeing.is_none() {
+            let intensity = if seeing.is_none() {
                 let mut field: Field<T, DiffractionLimited> = Field {
                     pixel_scale,
                     field_of_view,
-                    photometry: field_photometry,
+                    photometry: field_photometry.clone(),
                     objects,
                     exposure,
                     poisson_noise,
                     observer,
                     observing_mode: Observing::diffraction_limited(),
                     flux,
+                    wcs,
+                    detector,
+                    sky_background,
+                    bandwidth_sampling,
+                    aberrations,
+                    pupil_supersampling,
+                    position_angle,
+                    psf_cache,
                     intensity_sampling: None,
                 };
+                headers.push(field.header());
                 field.intensity(None)
             } else {
                 let mut field: Field<T, SeeingLimited> = Field {
                     pixel_scale,
                     field_of_view,
-                    photometry: field_photometry,
+                    photometry: field_photometry.clone(),
                     objects,
                     exposure,
                     poisson_noise,
@@ -82,16 +110,36 @@ where
                         seeing.map(|seeing| seeing.wavelength(field_photometry)),
                     ),
                     flux,
+                    wcs,
+                    detector,
+                    sky_background,
+                    bandwidth_sampling,
+                    aberrations,
+                    pupil_supersampling,
+                    position_angle,
+                    psf_cache,
                     intensity_sampling: None,
                 };
+                headers.push(field.header());
                 field.intensity(bar)
             };
-            if let Some(lufn) = save_options.lufn {
-                intensity.iter_mut().for_each(|i| *i = lufn(*i));
-            }
             intensities.push(intensity);
         }
 
+        if extension == "fits" {
+            return Ok(save_fits(
+                path,
+                headers.into_iter().zip(intensities).collect(),
+                &save_options,
+            )?);
+        }
+
+        if let Some(lufn) = save_options.lufn {
+            intensities
+                .iter_mut()
+                .for_each(|intensity| intensity.iter_mut().for_each(|i| *i = lufn(*i)));
+        }
+
         let threshold = save_options
             .saturation
             .threshold(intensities.iter().flatten());
@@ -109,6 +157,6 @@ where
                 })
             }
         }
-        img.save(path)
+        Ok(img.save(path)?)
     }
 }
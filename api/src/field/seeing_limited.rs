@@ -1,10 +1,13 @@
 use std::path::Path;
 
-use image::{ImageResult, Rgb, RgbImage};
+use image::{Rgb, RgbImage};
 use indicatif::{ProgressBar, ProgressStyle};
 
-use super::{Builder, Field, FieldBuilder, Observing, SeeingLimited};
-use crate::{AdaptiveOptics, Observer, SaveOptions, SeeingBuilder};
+use super::{
+    field::{check_extension, save_fits},
+    Builder, Field, FieldBuilder, Observing, SeeingLimited,
+};
+use crate::{AdaptiveOptics, Observer, Result, SaveOptions, SeeingBuilder};
 
 /**
 Seeing limited fields container
@@ -46,6 +49,13 @@ impl<T: Observer> Builder<SeeingLimitedField<T>> for (FieldBuilder<T>, Vec<Seein
             seeing_builders: self.1,
         }
     }
+    /// Creates a set of seeing limited fields, returning an [error](crate::Error) if the field settings are invalid
+    fn try_build(self) -> Result<SeeingLimitedField<T>> {
+        for seeing in self.1.iter() {
+            self.0.validate_seeing(Some(seeing))?;
+        }
+        Ok(self.build())
+    }
 }
 impl<T: Observer> Builder<SeeingLimitedField<T>> for (FieldBuilder<T>, SeeingBuilder) {
     /// Creates a set of seeing limited fields from a [FieldBuilder] and a [SeeingBuilder]
@@ -55,6 +65,13 @@ impl<T: Observer> Builder<SeeingLimitedField<T>> for (FieldBuilder<T>, SeeingBui
             vec![self.1],
         ))
     }
+    /// Creates a set of seeing limited fields, returning an [error](crate::Error) if the field settings are invalid
+    fn try_build(self) -> Result<SeeingLimitedField<T>> {
+        <(FieldBuilder<T>, Vec<SeeingBuilder>) as Builder<SeeingLimitedField<T>>>::try_build((
+            self.0,
+            vec![self.1],
+        ))
+    }
 }
 impl<T> SeeingLimitedField<T>
 where
@@ -65,8 +82,13 @@ where
         self.seeing_builders.len()
     }
     /// Computes image and save it to file
-    pub fn save<P: AsRef<Path>>(&mut self, path: P, save_options: SaveOptions) -> ImageResult<()> {
+    ///
+    /// The images are either saved side by side in a `png`, `jpg` or `tiff` file
+    /// or in a `fits` file, see [SaveOptions::cube]
+    pub fn save<P: AsRef<Path>>(&mut self, path: P, save_options: SaveOptions) -> Result<()> {
+        let extension = check_extension(path.as_ref(), &["png", "jpg", "tiff", "fits"])?;
         let mut intensities = vec![];
+        let mut headers = vec![];
         for seeing_builder in self.seeing_builders.iter() {
             let FieldBuilder {
                 pixel_scale,
@@ -78,6 +100,14 @@ where
                 observer,
                 seeing: _,
                 flux,
+                wcs,
+                detector,
+                sky_background,
+                bandwidth_sampling,
+                aberrations,
+                pupil_supersampling,
+                position_angle,
+                psf_cache,
             } = self.field_builder.clone();
             let bar = save_options
                 .mbar
@@ -92,45 +122,74 @@ where
                     .unwrap(),
                 )
             });
-            let mut intensity = if seeing_builder.adaptive_optics.is_none() {
+            let intensity = if seeing_builder.adaptive_optics.is_none() {
                 let mut field: Field<T, SeeingLimited> = Field {
                     pixel_scale,
                     field_of_view,
-                    photometry: photometry[0],
+                    photometry: photometry[0].clone(),
                     objects,
                     exposure,
                     poisson_noise,
                     observer,
                     observing_mode: Observing::seeing_limited(Some(
-                        seeing_builder.clone().wavelength(photometry[0]),
+                        seeing_builder.clone().wavelength(&photometry[0]),
                     )),
                     flux,
+                    wcs,
+                    detector,
+                    sky_background,
+                    bandwidth_sampling,
+                    aberrations,
+                    pupil_supersampling,
+                    position_angle,
+                    psf_cache,
                     intensity_sampling: None,
                 };
+                headers.push(field.header());
                 field.intensity(bar)
             } else {
                 let mut field: Field<T, AdaptiveOptics> = Field {
                     pixel_scale,
                     field_of_view,
-                    photometry: photometry[0],
+                    photometry: photometry[0].clone(),
                     objects,
                     exposure,
                     poisson_noise,
                     observer,
                     observing_mode: Observing::seeing_limited(Some(
-                        seeing_builder.clone().wavelength(photometry[0]),
+                        seeing_builder.clone().wavelength(&photometry[0]),
                     )),
                     flux,
+                    wcs,
+                    detector,
+                    sky_background,
+                    bandwidth_sampling,
+                    aberrations,
+                    pupil_supersampling,
+                    position_angle,
+                    psf_cache,
                     intensity_sampling: None,
                 };
+                headers.push(field.header());
                 field.intensity(bar)
             };
-            if let Some(lufn) = save_options.lufn {
-                intensity.iter_mut().for_each(|i| *i = lufn(*i));
-            }
             intensities.push(intensity);
         }
 
+        if extension == "fits" {
+            return Ok(save_fits(
+                path,
+                headers.into_iter().zip(intensities).collect(),
+                &save_options,
+            )?);
+        }
+
+        if let Some(lufn) = save_options.lufn {
+            intensities
+                .iter_mut()
+                .for_each(|intensity| intensity.iter_mut().for_each(|i| *i = lufn(*i)));
+        }
+
         let threshold = save_options
             .saturation
             .threshold(intensities.iter().flatten());
@@ -148,6 +207,6 @@ where
                 })
             }
         }
-        img.save(path)
+        Ok(img.save(path)?)
     }
 }
@@ -1,9 +1,9 @@
 use std::{fs::File, path::Path};
 
 use crate::{
-    AdaptiveOptics, DiffractionLimited, Field, Observer, Observing, ObservingModes, SeeingLimited,
+    AdaptiveOptics, DiffractionLimited, Field, Mcao, Observer, Observing, ObservingModes,
+    SeeingLimited,
 };
-use image::ImageError;
 use serde::ser::{Serialize, SerializeStruct, Serializer};
 
 impl Serialize for Observing<DiffractionLimited> {
@@ -38,6 +38,14 @@ impl Serialize for Observing<AdaptiveOptics> {
         self.seeing.serialize(serializer)
     }
 }
+impl Serialize for Observing<Mcao> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.seeing.serialize(serializer)
+    }
+}
 
 impl<T> Serialize for Field<T, DiffractionLimited>
 where
@@ -47,15 +55,26 @@ where
     where
         S: Serializer,
     {
-        let mut s = serializer.serialize_struct("Field", 8)?;
+        let mut s = serializer.serialize_struct("Field", 16)?;
         s.serialize_field("pixel_scale", &self.pixel_scale)?;
         s.serialize_field("field_of_view", &self.field_of_view)?;
         s.serialize_field("photometry", &self.photometry)?;
+        s.serialize_field("bandwidth_sampling", &self.bandwidth_sampling)?;
         s.serialize_field("objects", &self.objects)?;
         s.serialize_field("exposure", &self.exposure)?;
         s.serialize_field("poisson_noise", &self.poisson_noise)?;
         s.serialize_field("observer", &self.observer)?;
+        s.serialize_field(
+            "pupil_rotation",
+            &self.observer.pupil_rotation().to_degrees(),
+        )?;
+        s.serialize_field("position_angle", &self.position_angle.to_degrees())?;
+        s.serialize_field("aberrations", &self.aberrations)?;
+        s.serialize_field("pupil_supersampling", &self.pupil_supersampling)?;
         s.serialize_field("observing_mode", &self.observing_mode)?;
+        s.serialize_field("wcs", &self.wcs())?;
+        s.serialize_field("detector", &self.detector)?;
+        s.serialize_field("sky_brightness", &self.sky_brightness())?;
         s.end()
     }
 }
@@ -67,15 +86,26 @@ where
     where
         S: Serializer,
     {
-        let mut s = serializer.serialize_struct("Field", 8)?;
+        let mut s = serializer.serialize_struct("Field", 16)?;
         s.serialize_field("pixel_scale", &self.pixel_scale)?;
         s.serialize_field("field_of_view", &self.field_of_view)?;
         s.serialize_field("photometry", &self.photometry)?;
+        s.serialize_field("bandwidth_sampling", &self.bandwidth_sampling)?;
         s.serialize_field("objects", &self.objects)?;
         s.serialize_field("exposure", &self.exposure)?;
         s.serialize_field("poisson_noise", &self.poisson_noise)?;
         s.serialize_field("observer", &self.observer)?;
+        s.serialize_field(
+            "pupil_rotation",
+            &self.observer.pupil_rotation().to_degrees(),
+        )?;
+        s.serialize_field("position_angle", &self.position_angle.to_degrees())?;
+        s.serialize_field("aberrations", &self.aberrations)?;
+        s.serialize_field("pupil_supersampling", &self.pupil_supersampling)?;
         s.serialize_field("observing_mode", &self.observing_mode)?;
+        s.serialize_field("wcs", &self.wcs())?;
+        s.serialize_field("detector", &self.detector)?;
+        s.serialize_field("sky_brightness", &self.sky_brightness())?;
         s.end()
     }
 }
@@ -87,15 +117,57 @@ where
     where
         S: Serializer,
     {
-        let mut s = serializer.serialize_struct("Field", 8)?;
+        let mut s = serializer.serialize_struct("Field", 16)?;
+        s.serialize_field("pixel_scale", &self.pixel_scale)?;
+        s.serialize_field("field_of_view", &self.field_of_view)?;
+        s.serialize_field("photometry", &self.photometry)?;
+        s.serialize_field("bandwidth_sampling", &self.bandwidth_sampling)?;
+        s.serialize_field("objects", &self.objects)?;
+        s.serialize_field("exposure", &self.exposure)?;
+        s.serialize_field("poisson_noise", &self.poisson_noise)?;
+        s.serialize_field("observer", &self.observer)?;
+        s.serialize_field(
+            "pupil_rotation",
+            &self.observer.pupil_rotation().to_degrees(),
+        )?;
+        s.serialize_field("position_angle", &self.position_angle.to_degrees())?;
+        s.serialize_field("aberrations", &self.aberrations)?;
+        s.serialize_field("pupil_supersampling", &self.pupil_supersampling)?;
+        s.serialize_field("observing_mode", &self.observing_mode)?;
+        s.serialize_field("wcs", &self.wcs())?;
+        s.serialize_field("detector", &self.detector)?;
+        s.serialize_field("sky_brightness", &self.sky_brightness())?;
+        s.end()
+    }
+}
+impl<T> Serialize for Field<T, Mcao>
+where
+    T: Observer + Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut s = serializer.serialize_struct("Field", 16)?;
         s.serialize_field("pixel_scale", &self.pixel_scale)?;
         s.serialize_field("field_of_view", &self.field_of_view)?;
         s.serialize_field("photometry", &self.photometry)?;
+        s.serialize_field("bandwidth_sampling", &self.bandwidth_sampling)?;
         s.serialize_field("objects", &self.objects)?;
         s.serialize_field("exposure", &self.exposure)?;
         s.serialize_field("poisson_noise", &self.poisson_noise)?;
         s.serialize_field("observer", &self.observer)?;
+        s.serialize_field(
+            "pupil_rotation",
+            &self.observer.pupil_rotation().to_degrees(),
+        )?;
+        s.serialize_field("position_angle", &self.position_angle.to_degrees())?;
+        s.serialize_field("aberrations", &self.aberrations)?;
+        s.serialize_field("pupil_supersampling", &self.pupil_supersampling)?;
         s.serialize_field("observing_mode", &self.observing_mode)?;
+        s.serialize_field("wcs", &self.wcs())?;
+        s.serialize_field("detector", &self.detector)?;
+        s.serialize_field("sky_brightness", &self.sky_brightness())?;
         s.end()
     }
 }
@@ -149,22 +221,33 @@ where
         s.end()
     }
 }
+impl<'a, T> Serialize for Data<'a, T, Mcao>
+where
+    T: Observer + Serialize + Send + Sync,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut s = serializer.serialize_struct("Data", 2)?;
+        s.serialize_field("field", self.field)?;
+        s.serialize_field("intensity", &self.intensity)?;
+        s.end()
+    }
+}
 
 impl<T> Field<T, DiffractionLimited>
 where
     T: Observer + Serialize + Send + Sync,
 {
-    pub fn dump<P: AsRef<Path>>(&mut self, path: P) -> Result<(), ImageError> {
+    /// Computes the image and saves it, together with the field, into a pickle file
+    pub fn dump<P: AsRef<Path>>(&mut self, path: P) -> crate::Result<()> {
         let intensity = self.intensity(Default::default());
         let data = Data {
             field: self,
             intensity,
         };
-        serde_pickle::to_writer(&mut File::create(path.as_ref())?, &data, Default::default())
-            .expect(&format!(
-                "failed to write field intensity into pickle file {:?}",
-                path.as_ref()
-            ));
+        serde_pickle::to_writer(&mut File::create(path.as_ref())?, &data, Default::default())?;
         Ok(())
     }
 }
@@ -173,17 +256,14 @@ impl<T> Field<T, SeeingLimited>
 where
     T: Observer + Serialize + Send + Sync,
 {
-    pub fn dump<P: AsRef<Path>>(&mut self, path: P) -> Result<(), ImageError> {
+    /// Computes the image and saves it, together with the field, into a pickle file
+    pub fn dump<P: AsRef<Path>>(&mut self, path: P) -> crate::Result<()> {
         let intensity = self.intensity(Default::default());
         let data = Data {
             field: self,
             intensity,
         };
-        serde_pickle::to_writer(&mut File::create(path.as_ref())?, &data, Default::default())
-            .expect(&format!(
-                "failed to write field intensity into pickle file {:?}",
-                path.as_ref()
-            ));
+        serde_pickle::to_writer(&mut File::create(path.as_ref())?, &data, Default::default())?;
         Ok(())
     }
 }
@@ -192,17 +272,30 @@ impl<T> Field<T, AdaptiveOptics>
 where
     T: Observer + Serialize + Send + Sync,
 {
-    pub fn dump<P: AsRef<Path>>(&mut self, path: P) -> Result<(), ImageError> {
+    /// Computes the image and saves it, together with the field, into a pickle file
+    pub fn dump<P: AsRef<Path>>(&mut self, path: P) -> crate::Result<()> {
+        let intensity = self.intensity(Default::default());
+        let data = Data {
+            field: self,
+            intensity,
+        };
+        serde_pickle::to_writer(&mut File::create(path.as_ref())?, &data, Default::default())?;
+        Ok(())
+    }
+}
+
+impl<T> Field<T, Mcao>
+where
+    T: Observer + Serialize + Send + Sync,
+{
+    /// Computes the image and saves it, together with the field, into a pickle file
+    pub fn dump<P: AsRef<Path>>(&mut self, path: P) -> crate::Result<()> {
         let intensity = self.intensity(Default::default());
         let data = Data {
             field: self,
             intensity,
         };
-        serde_pickle::to_writer(&mut File::create(path.as_ref())?, &data, Default::default())
-            .expect(&format!(
-                "failed to write field intensity into pickle file {:?}",
-                path.as_ref()
-            ));
+        serde_pickle::to_writer(&mut File::create(path.as_ref())?, &data, Default::default())?;
         Ok(())
     }
 }
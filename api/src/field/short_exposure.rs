@@ -0,0 +1,272 @@
+use std::{path::Path, sync::Arc};
+
+use image::{Rgb, RgbImage};
+use indicatif::ProgressBar;
+
+use super::{
+    field::{check_extension, save_fits},
+    Field, SeeingLimited,
+};
+use crate::{phase_screen::FrozenFlow, Error, FitsHeader, Observer, Result, SaveOptions};
+
+impl<T> Field<T, SeeingLimited>
+where
+    T: Observer + Sync + Send,
+{
+    /// Returns the layered phase screens of the seeing for the given duration in seconds
+    fn frozen_flow(&self, duration: f64, seed: u64) -> Result<Option<Arc<FrozenFlow>>> {
+        self.observing_mode
+            .seeing
+            .as_ref()
+            .map(|seeing| {
+                FrozenFlow::new(
+                    seeing,
+                    self.observer.diameter(),
+                    self.observer.pupil_sample_size(),
+                    self.field_of_view() + self.resolution() * 2.,
+                    duration,
+                    seed,
+                )
+                .map(Arc::new)
+            })
+            .transpose()
+    }
+    /// Computes the intensity maps and the headers of a sequence of frames
+    ///
+    /// The phase screens and the time of the `k`th frame are given by `frame(k)`
+    fn frames<F>(
+        &mut self,
+        n_frame: usize,
+        frame: F,
+        bar: Option<ProgressBar>,
+    ) -> Result<(Vec<FitsHeader>, Vec<Vec<f64>>)>
+    where
+        F: Fn(&Self, usize) -> Result<Option<(Arc<FrozenFlow>, f64)>>,
+    {
+        if let Some(bar) = bar.as_ref() {
+            bar.set_length(n_frame as u64);
+        }
+        let mut headers = vec![];
+        let mut intensities = vec![];
+        for k in 0..n_frame {
+            self.observing_mode.frame = match frame(self, k) {
+                Ok(frame) => frame,
+                Err(e) => {
+                    self.observing_mode.frame = None;
+                    return Err(e);
+                }
+            };
+            intensities.push(self.intensity(None));
+            headers.push(self.header());
+            if let Some(bar) = bar.as_ref() {
+                bar.inc(1);
+            }
+        }
+        self.observing_mode.frame = None;
+        if let Some(bar) = bar.as_ref() {
+            bar.finish();
+        }
+        Ok((headers, intensities))
+    }
+    /// Computes a sequence of short-exposure intensity maps
+    ///
+    /// Each frame is the diffraction limited image through the von Kármán [phase screens](crate::PhaseScreen)
+    /// of the layers of the [turbulence profile](crate::TurbulenceProfile),
+    /// the random realization of the phase screens of the `k`th frame is seeded with `seed + k`
+    ///
+    /// Returns an [error](crate::Error) if a phase screen has more than [PhaseScreen::MAX_SAMPLES](crate::PhaseScreen::MAX_SAMPLES) samples
+    pub fn short_exposures(&mut self, n_frame: usize, seed: u64) -> Result<Vec<Vec<f64>>> {
+        self.frames(
+            n_frame,
+            |field, k| {
+                field
+                    .frozen_flow(0f64, seed + k as u64)
+                    .map(|ff| ff.map(|ff| (ff, 0f64)))
+            },
+            None,
+        )
+        .map(|(_, intensities)| intensities)
+    }
+    /// Computes a sequence of short-exposure images and saves it to file(s)
+    ///
+    /// The frames are either saved in a single `fits` file, see [SaveOptions::cube],
+    /// or in `png`, `jpg` or `tiff` files, one per frame, numbered after the file stem.
+    pub fn save_short_exposures<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        n_frame: usize,
+        seed: u64,
+        mut save_options: SaveOptions,
+    ) -> Result<()> {
+        let extension = check_extension(path.as_ref(), &["png", "jpg", "tiff", "fits"])?;
+        let (headers, intensities) = self.frames(
+            n_frame,
+            |field, k| {
+                field
+                    .frozen_flow(0f64, seed + k as u64)
+                    .map(|ff| ff.map(|ff| (ff, 0f64)))
+            },
+            save_options.bar.take(),
+        )?;
+        save_frames(path.as_ref(), extension, headers, intensities, save_options)
+    }
+    /// Computes a time series of short-exposure intensity maps
+    ///
+    /// The phase screens of the layers of the [turbulence profile](crate::TurbulenceProfile) are translated
+    /// across the pupil by the wind of each layer (Taylor frozen flow hypothesis).
+    /// The frames are sampled at `frame_rate` in Hz for `duration` seconds and each frame is
+    /// exposed for `1/frame_rate` seconds.
+    /// The phase screens are extended along the wind by the distance it travels in `duration` seconds.
+    ///
+    /// Returns an [error](crate::Error) if the wind is not set for all the turbulence layers
+    /// or if a phase screen has more than [PhaseScreen::MAX_SAMPLES](crate::PhaseScreen::MAX_SAMPLES) samples
+    pub fn time_series(
+        &mut self,
+        frame_rate: f64,
+        duration: f64,
+        seed: u64,
+    ) -> Result<Vec<Vec<f64>>> {
+        self.frozen_flow_frames(frame_rate, duration, seed, None)
+            .map(|(_, intensities)| intensities)
+    }
+    /// Computes a time series of short-exposure images and saves it to file(s)
+    ///
+    /// See [Field::time_series] and [Field::save_short_exposures]
+    pub fn save_time_series<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        frame_rate: f64,
+        duration: f64,
+        seed: u64,
+        mut save_options: SaveOptions,
+    ) -> Result<()> {
+        let extension = check_extension(path.as_ref(), &["png", "jpg", "tiff", "fits"])?;
+        let (headers, intensities) =
+            self.frozen_flow_frames(frame_rate, duration, seed, save_options.bar.take())?;
+        save_frames(path.as_ref(), extension, headers, intensities, save_options)
+    }
+    fn frozen_flow_frames(
+        &mut self,
+        frame_rate: f64,
+        duration: f64,
+        seed: u64,
+        bar: Option<ProgressBar>,
+    ) -> Result<(Vec<FitsHeader>, Vec<Vec<f64>>)> {
+        if let Some(seeing) = self.observing_mode.seeing.as_ref() {
+            if seeing
+                .turbulence
+                .layers()
+                .iter()
+                .any(|layer| layer.wind.is_none())
+            {
+                return Err(Error::TurbulenceProfile(String::from(
+                    "the wind is not set for all the turbulence layers",
+                )));
+            }
+        }
+        let n_frame = (frame_rate * duration).round() as usize;
+        let frozen_flow = self.frozen_flow(duration, seed)?;
+        let exposure = self.exposure;
+        self.exposure = frame_rate.recip();
+        let frames = self.frames(
+            n_frame,
+            |_, k| Ok(frozen_flow.clone().map(|ff| (ff, k as f64 / frame_rate))),
+            bar,
+        );
+        self.exposure = exposure;
+        frames
+    }
+}
+
+/// Saves a sequence of frames either in a `fits` file or in numbered image files
+fn save_frames(
+    path: &Path,
+    extension: &str,
+    headers: Vec<FitsHeader>,
+    mut intensities: Vec<Vec<f64>>,
+    save_options: SaveOptions,
+) -> Result<()> {
+    if extension == "fits" {
+        return Ok(save_fits(
+            path,
+            headers.into_iter().zip(intensities).collect(),
+            &save_options,
+        )?);
+    }
+
+    if let Some(lufn) = save_options.lufn {
+        intensities
+            .iter_mut()
+            .for_each(|intensity| intensity.iter_mut().for_each(|i| *i = lufn(*i)));
+    }
+    // same saturation threshold for all the frames
+    let threshold = save_options
+        .saturation
+        .threshold(intensities.iter().flatten());
+
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    let lut = colorous::CUBEHELIX;
+    for (k, intensity) in intensities.into_iter().enumerate() {
+        let n_px = (intensity.len() as f64).sqrt() as usize;
+        let mut img = RgbImage::new(n_px as u32, n_px as u32);
+        img.pixels_mut().zip(&intensity).for_each(|(p, i)| {
+            *p = Rgb(lut.eval_continuous(i / threshold).into_array());
+        });
+        img.save(path.with_file_name(format!("{stem}_{k:04}.{extension}")))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Builder, Error, Field, FieldBuilder, SeeingBuilder, SeeingLimited, Telescope,
+        TurbulenceProfile,
+    };
+    use skyangle::SkyAngle;
+
+    #[test]
+    fn short_exposures() {
+        let mut field: Field<Telescope, SeeingLimited> =
+            FieldBuilder::new(Telescope::new(4.).build())
+                .pixel_scale(SkyAngle::Arcsecond(0.05))
+                .field_of_view(32)
+                .seeing_limited(SeeingBuilder::new(16e-2))
+                .build();
+        let frames = field.short_exposures(2, 11).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_ne!(frames[0], frames[1]);
+        // the realizations are reproducible
+        assert_eq!(field.short_exposures(1, 12).unwrap()[0], frames[1]);
+        // the flux is preserved
+        let long_exposure = field.intensity(None);
+        let flux: f64 = long_exposure.iter().sum();
+        let frame_flux: f64 = frames[0].iter().sum();
+        assert!(
+            (frame_flux / flux - 1.).abs() < 0.05,
+            "{frame_flux} vs {flux}"
+        );
+        // a time series requires the wind of all the layers
+        assert!(field.time_series(100., 0.02, 0).is_err());
+        let mut field: Field<Telescope, SeeingLimited> =
+            FieldBuilder::new(Telescope::new(4.).build())
+                .pixel_scale(SkyAngle::Arcsecond(0.05))
+                .field_of_view(32)
+                .seeing_limited(
+                    SeeingBuilder::new(16e-2)
+                        .turbulence_profile(TurbulenceProfile::default().bufton_wind(0.)),
+                )
+                .build();
+        let frames = field.time_series(100., 0.03, 0).unwrap();
+        assert_eq!(frames.len(), 3);
+        assert_ne!(frames[0], frames[2]);
+        // the phase screens are limited in size
+        assert!(matches!(
+            field.time_series(100., 1e4, 0),
+            Err(Error::PhaseScreen(_))
+        ));
+    }
+}
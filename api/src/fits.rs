@@ -0,0 +1,671 @@
+//! # FITS
+//!
+//! A minimal [FITS](https://fits.gsfc.nasa.gov/fits_standard.html) writer for
+//! 2D and 3D images with a primary HDU and optional IMAGE extensions,
+//! and a reader for the primary HDU image
+
+use std::{
+    fmt::Display,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+const BLOCK_SIZE: usize = 2880;
+const CARD_SIZE: usize = 80;
+/// Maximum length of a `HIERARCH` keyword leaving room for a fixed format value
+const HIERARCH_SIZE: usize = CARD_SIZE - 32;
+
+/// FITS header keyword value
+#[derive(Debug, Clone, PartialEq)]
+pub enum FitsValue {
+    Logical(bool),
+    Integer(i64),
+    Real(f64),
+    Text(String),
+}
+impl From<bool> for FitsValue {
+    fn from(value: bool) -> Self {
+        FitsValue::Logical(value)
+    }
+}
+impl From<i64> for FitsValue {
+    fn from(value: i64) -> Self {
+        FitsValue::Integer(value)
+    }
+}
+impl From<usize> for FitsValue {
+    fn from(value: usize) -> Self {
+        FitsValue::Integer(value as i64)
+    }
+}
+impl From<f64> for FitsValue {
+    fn from(value: f64) -> Self {
+        FitsValue::Real(value)
+    }
+}
+impl From<&str> for FitsValue {
+    fn from(value: &str) -> Self {
+        FitsValue::Text(value.to_string())
+    }
+}
+impl From<String> for FitsValue {
+    fn from(value: String) -> Self {
+        FitsValue::Text(value)
+    }
+}
+impl Display for FitsValue {
+    /// Fixed-format value, right justified to column 30 (strings start at column 11)
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FitsValue::Logical(value) => write!(f, "{:>20}", if *value { "T" } else { "F" }),
+            FitsValue::Integer(value) => write!(f, "{value:>20}"),
+            FitsValue::Real(value) => write!(f, "{:>20}", format!("{value:.12E}")),
+            FitsValue::Text(value) => {
+                write!(f, "{:<20}", format!("'{:<8}'", value.replace('\'', "''")))
+            }
+        }
+    }
+}
+
+impl FitsValue {
+    /// Parses a fixed or free format value
+    fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        if let Some(text) = value.strip_prefix('\'') {
+            return text
+                .rfind('\'')
+                .map(|end| FitsValue::Text(text[..end].replace("''", "'").trim_end().to_string()));
+        }
+        match value {
+            "T" => Some(FitsValue::Logical(true)),
+            "F" => Some(FitsValue::Logical(false)),
+            _ => value
+                .parse::<i64>()
+                .map(FitsValue::Integer)
+                .or_else(|_| {
+                    value
+                        .replace(['D', 'd'], "E")
+                        .parse::<f64>()
+                        .map(FitsValue::Real)
+                })
+                .ok(),
+        }
+    }
+    /// Returns the value as a floating point number
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            FitsValue::Integer(value) => Some(*value as f64),
+            FitsValue::Real(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+/// FITS header keyword record
+#[derive(Debug, Clone, PartialEq)]
+struct Card {
+    keyword: String,
+    value: FitsValue,
+    comment: Option<String>,
+}
+impl Card {
+    /// Parses a 80 characters record, returns `None` if the record has no value
+    fn parse(record: &str) -> Option<Self> {
+        let (keyword, field) = if let Some(hierarch) = record.strip_prefix("HIERARCH ") {
+            let (keyword, field) = hierarch.split_once('=')?;
+            (keyword.trim().to_string(), field)
+        } else {
+            if record.len() < 10 || &record[8..10] != "= " {
+                return None;
+            }
+            (record[..8].trim().to_string(), &record[10..])
+        };
+        // the comment separator is the first slash outside of a string
+        let mut in_string = false;
+        let split = field.char_indices().find_map(|(i, c)| {
+            if c == '\'' {
+                in_string = !in_string;
+            }
+            (c == '/' && !in_string).then_some(i)
+        });
+        let (value, comment) = match split {
+            Some(i) => (&field[..i], Some(field[i + 1..].trim().to_string())),
+            None => (field, None),
+        };
+        Some(Self {
+            keyword,
+            value: FitsValue::parse(value)?,
+            comment: comment.filter(|c| !c.is_empty()),
+        })
+    }
+    /// Returns the 80 characters record
+    ///
+    /// String values too long for a single record are split over several records
+    /// following the `CONTINUE` long string convention
+    fn record(&self) -> String {
+        if let FitsValue::Text(value) = &self.value {
+            if value.replace('\'', "''").len() > CARD_SIZE - self.name().len() - 2 {
+                return self.long_string_records(value);
+            }
+        }
+        let mut record = format!("{}{}", self.name(), self.value);
+        if let Some(comment) = &self.comment {
+            record.push_str(" / ");
+            record.push_str(comment);
+        }
+        format!("{:<80.80}", record)
+    }
+    /// Returns the keyword and the value indicator, using the `HIERARCH` convention
+    /// for keywords longer than 8 characters
+    fn name(&self) -> String {
+        if self.keyword.len() > 8 {
+            format!("HIERARCH {} = ", self.keyword)
+        } else {
+            format!("{:<8}= ", self.keyword)
+        }
+    }
+    /// Returns the records of a long string value, each string but the last one ending with `&`
+    fn long_string_records(&self, value: &str) -> String {
+        let name = self.name();
+        let mut chunks = vec![String::new()];
+        for c in value.chars() {
+            let escaped = if c == '\'' {
+                "''".to_string()
+            } else {
+                c.to_string()
+            };
+            // room left by the keyword or by `CONTINUE`, the quotes and the ampersand
+            let size = if chunks.len() == 1 {
+                CARD_SIZE - name.len() - 3
+            } else {
+                CARD_SIZE - 13
+            };
+            if chunks.last().unwrap().len() + escaped.len() > size {
+                chunks.push(String::new());
+            }
+            chunks.last_mut().unwrap().push_str(&escaped);
+        }
+        let n = chunks.len();
+        chunks
+            .into_iter()
+            .enumerate()
+            .map(|(i, chunk)| {
+                let mut record = if i == 0 {
+                    format!("{name}'{chunk}")
+                } else {
+                    format!("CONTINUE  '{chunk}")
+                };
+                if i + 1 < n {
+                    record.push_str("&'");
+                } else {
+                    record.push('\'');
+                    if let Some(comment) = &self.comment {
+                        record.push_str(" / ");
+                        record.push_str(comment);
+                    }
+                }
+                format!("{:<80.80}", record)
+            })
+            .collect()
+    }
+}
+
+/// FITS header
+///
+/// The mandatory keywords (`SIMPLE`, `BITPIX`, `NAXIS`, ...) are written by [FitsWriter],
+/// a [FitsHeader] only holds the user keywords
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FitsHeader(Vec<Card>);
+impl FitsHeader {
+    /// Creates an empty header
+    pub fn new() -> Self {
+        Default::default()
+    }
+    /// Adds a keyword, or updates its value if the keyword already exists
+    ///
+    /// See [FitsHeader::push]
+    pub fn keyword<V: Into<FitsValue>>(mut self, keyword: &str, value: V, comment: &str) -> Self {
+        self.push(keyword, value, comment);
+        self
+    }
+    /// Adds a keyword, or updates its value if the keyword already exists
+    ///
+    /// Keywords longer than 8 characters are written with the `HIERARCH` convention.
+    /// Not a number and infinite values are not legal FITS values, the keyword is removed instead.
+    ///
+    /// # Panics
+    /// Panics if the keyword is invalid, see [FitsHeader::try_push]
+    pub fn push<V: Into<FitsValue>>(
+        &mut self,
+        keyword: &str,
+        value: V,
+        comment: &str,
+    ) -> &mut Self {
+        let keyword = keyword.to_uppercase();
+        match value.into() {
+            FitsValue::Real(value) if !value.is_finite() => {
+                self.0.retain(|c| c.keyword != keyword);
+                self
+            }
+            value => self
+                .try_push(&keyword, value, comment)
+                .unwrap_or_else(|e| panic!("{e}")),
+        }
+    }
+    /// Adds a keyword, or updates its value if the keyword already exists
+    ///
+    /// Returns an error if the keyword is empty, longer than 48 characters or has characters
+    /// other than letters, digits, hyphens and underscores, or if the value is not a number or is infinite
+    pub fn try_push<V: Into<FitsValue>>(
+        &mut self,
+        keyword: &str,
+        value: V,
+        comment: &str,
+    ) -> io::Result<&mut Self> {
+        let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidInput, msg);
+        let keyword = keyword.to_uppercase();
+        if keyword.is_empty()
+            || keyword.len() > HIERARCH_SIZE
+            || !keyword
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(invalid(format!("invalid FITS keyword: {keyword:?}")));
+        }
+        let value = value.into();
+        if let FitsValue::Real(value) = value {
+            if !value.is_finite() {
+                return Err(invalid(format!("invalid FITS {keyword} value: {value}")));
+            }
+        }
+        let card = Card {
+            keyword,
+            value,
+            comment: (!comment.is_empty()).then(|| comment.to_string()),
+        };
+        match self.0.iter_mut().find(|c| c.keyword == card.keyword) {
+            Some(c) => *c = card,
+            None => self.0.push(card),
+        }
+        Ok(self)
+    }
+    /// Appends the keywords of another header
+    pub fn extend(&mut self, header: FitsHeader) -> &mut Self {
+        header.0.into_iter().for_each(|card| {
+            match self.0.iter_mut().find(|c| c.keyword == card.keyword) {
+                Some(c) => *c = card,
+                None => self.0.push(card),
+            }
+        });
+        self
+    }
+    /// Returns the header with only the keywords common to all the headers
+    pub fn common(headers: &[FitsHeader]) -> Self {
+        let mut common = FitsHeader::new();
+        if let Some(first) = headers.first() {
+            common.0 = first
+                .0
+                .iter()
+                .filter(|card| {
+                    headers[1..]
+                        .iter()
+                        .all(|header| header.get(&card.keyword) == Some(&card.value))
+                })
+                .cloned()
+                .collect();
+        }
+        common
+    }
+    /// Returns the value of a keyword
+    pub fn get(&self, keyword: &str) -> Option<&FitsValue> {
+        let keyword = keyword.to_uppercase();
+        self.0
+            .iter()
+            .find(|c| c.keyword == keyword)
+            .map(|c| &c.value)
+    }
+    /// Returns the number of keywords
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    /// Checks if the header has no keywords
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Data types that can be written in a FITS HDU
+pub trait FitsData: Copy {
+    /// FITS `BITPIX` value
+    const BITPIX: i64;
+    /// Writes the data in big-endian order
+    fn write_be<W: Write>(&self, writer: &mut W) -> io::Result<()>;
+}
+impl FitsData for f64 {
+    const BITPIX: i64 = -64;
+    fn write_be<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.to_be_bytes())
+    }
+}
+impl FitsData for f32 {
+    const BITPIX: i64 = -32;
+    fn write_be<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.to_be_bytes())
+    }
+}
+
+/// FITS file writer
+///
+/// The first HDU written is the primary HDU, all the following ones are IMAGE extensions.
+/// The data of 2D images are expected in row-major order with the first row at the top
+/// of the image, the rows are flipped in the file so the images are displayed the same way
+/// by FITS viewers.
+///
+/// # Example
+/// ```no_run
+/// use eyepiece::{FitsWriter, FitsHeader};
+///
+/// let data = vec![0f64; 64 * 64];
+/// FitsWriter::create("image.fits")
+///     .unwrap()
+///     .write(&FitsHeader::new().keyword("EXPTIME", 1f64, "exposure time [s]"), &[64, 64], &data)
+///     .unwrap();
+/// ```
+pub struct FitsWriter<W: Write> {
+    writer: W,
+    n_hdu: usize,
+}
+impl FitsWriter<BufWriter<File>> {
+    /// Creates a new FITS file
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self::new(BufWriter::new(File::create(path)?)))
+    }
+}
+impl<W: Write> FitsWriter<W> {
+    /// Creates a FITS writer from any [Write] implementor
+    pub fn new(writer: W) -> Self {
+        Self { writer, n_hdu: 0 }
+    }
+    /// Returns the number of HDUs written so far
+    pub fn len(&self) -> usize {
+        self.n_hdu
+    }
+    /// Checks if no HDU has been written yet
+    pub fn is_empty(&self) -> bool {
+        self.n_hdu == 0
+    }
+    /// Writes a header only HDU
+    ///
+    /// If it is the primary HDU, the `EXTEND` keyword is set
+    pub fn write_header(&mut self, header: &FitsHeader) -> io::Result<&mut Self> {
+        self.write::<f64>(header, &[], &[])
+    }
+    /// Writes a HDU
+    ///
+    /// `shape` is given with the fastest varying axis first (i.e. `[NAXIS1, NAXIS2, ...]`)
+    pub fn write<T: FitsData>(
+        &mut self,
+        header: &FitsHeader,
+        shape: &[usize],
+        data: &[T],
+    ) -> io::Result<&mut Self> {
+        let n_data: usize = if shape.is_empty() {
+            0
+        } else {
+            shape.iter().product()
+        };
+        if n_data != data.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "FITS data length ({}) does not match the shape {:?}",
+                    data.len(),
+                    shape
+                ),
+            ));
+        }
+
+        let mut mandatory = FitsHeader::new();
+        if self.n_hdu == 0 {
+            mandatory.push("SIMPLE", true, "conforms to FITS standard");
+        } else {
+            mandatory.push("XTENSION", "IMAGE", "image extension");
+        }
+        mandatory.push("BITPIX", T::BITPIX, "IEEE floating point");
+        mandatory.push("NAXIS", shape.len(), "number of data axes");
+        for (i, n) in shape.iter().enumerate() {
+            mandatory.push(&format!("NAXIS{}", i + 1), *n, "");
+        }
+        if self.n_hdu == 0 {
+            mandatory.push("EXTEND", true, "FITS extensions may be present");
+        } else {
+            mandatory.push("PCOUNT", 0i64, "");
+            mandatory.push("GCOUNT", 1i64, "");
+        }
+
+        let mut records: String = mandatory
+            .0
+            .iter()
+            .chain(header.0.iter())
+            .map(|card| card.record())
+            .collect();
+        records.push_str(&format!("{:<80}", "END"));
+        let n_record = records.len() / CARD_SIZE;
+        let n_pad = (BLOCK_SIZE / CARD_SIZE - n_record % (BLOCK_SIZE / CARD_SIZE))
+            % (BLOCK_SIZE / CARD_SIZE);
+        records.push_str(&" ".repeat(n_pad * CARD_SIZE));
+        self.writer.write_all(records.as_bytes())?;
+
+        if n_data > 0 {
+            // rows are written from the bottom to the top of each image
+            let n_col = shape[0];
+            let n_plane = n_col * shape.get(1).cloned().unwrap_or(1);
+            for plane in data.chunks(n_plane) {
+                for row in plane.chunks(n_col).rev() {
+                    for value in row {
+                        value.write_be(&mut self.writer)?;
+                    }
+                }
+            }
+            let n_byte = n_data * T::BITPIX.unsigned_abs() as usize / 8;
+            let n_pad = (BLOCK_SIZE - n_byte % BLOCK_SIZE) % BLOCK_SIZE;
+            self.writer.write_all(&vec![0u8; n_pad])?;
+        }
+        self.writer.flush()?;
+
+        self.n_hdu += 1;
+        Ok(self)
+    }
+}
+
+/// FITS image
+///
+/// The image of the primary HDU of a FITS file.
+/// The data are in row-major order with the first row at the top of the image,
+/// as expected by [FitsWriter].
+///
+/// # Example
+/// ```no_run
+/// use eyepiece::FitsImage;
+///
+/// let image = FitsImage::open("image.fits").unwrap();
+/// let (n_col, n_row) = (image.shape[0], image.shape[1]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct FitsImage {
+    /// The header keywords, including the mandatory ones
+    pub header: FitsHeader,
+    /// The image shape with the fastest varying axis first (i.e. `[NAXIS1, NAXIS2, ...]`)
+    pub shape: Vec<usize>,
+    /// The image data, scaled with `BSCALE` and `BZERO`
+    pub data: Vec<f64>,
+}
+impl FitsImage {
+    /// Reads the primary HDU image of a FITS file
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+    /// Reads the primary HDU image from the content of a FITS file
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+        let mut header = FitsHeader::new();
+        let mut n_header = 0;
+        for record in bytes.chunks(CARD_SIZE) {
+            n_header += CARD_SIZE;
+            let record = std::str::from_utf8(record).map_err(|_| invalid("non ASCII header"))?;
+            if record.starts_with("END ") || record.trim_end() == "END" {
+                break;
+            }
+            if let Some(field) = record.strip_prefix("CONTINUE  ") {
+                // long string continued from the previous record
+                if let Some((Card { value, comment, .. }, continued)) = header
+                    .0
+                    .last_mut()
+                    .zip(Card::parse(&format!("CONTINUE= {field}")))
+                {
+                    if let (FitsValue::Text(text), FitsValue::Text(more)) = (value, continued.value)
+                    {
+                        if text.ends_with('&') {
+                            text.pop();
+                            text.push_str(&more);
+                            *comment = continued.comment;
+                        }
+                    }
+                }
+                continue;
+            }
+            if let Some(card) = Card::parse(record) {
+                header.0.push(card);
+            }
+        }
+        let n_header = n_header.div_ceil(BLOCK_SIZE) * BLOCK_SIZE;
+        let int = |keyword: &str| match header.get(keyword) {
+            Some(FitsValue::Integer(value)) => Ok(*value),
+            _ => Err(invalid(&format!("missing {keyword} keyword"))),
+        };
+        let bitpix = int("BITPIX")?;
+        let shape = (1..=int("NAXIS")?)
+            .map(|i| int(&format!("NAXIS{i}")).map(|n| n as usize))
+            .collect::<io::Result<Vec<_>>>()?;
+        let n_data: usize = if shape.is_empty() {
+            0
+        } else {
+            shape.iter().product()
+        };
+        let n_byte = bitpix.unsigned_abs() as usize / 8;
+        let raw = bytes
+            .get(n_header..n_header + n_data * n_byte)
+            .ok_or_else(|| invalid("truncated data"))?;
+        let bscale = header
+            .get("BSCALE")
+            .and_then(|v| v.as_f64())
+            .unwrap_or(1f64);
+        let bzero = header.get("BZERO").and_then(|v| v.as_f64()).unwrap_or(0f64);
+        let values: Vec<f64> = raw
+            .chunks(n_byte)
+            .map(|b| match bitpix {
+                8 => Ok(b[0] as f64),
+                16 => Ok(i16::from_be_bytes([b[0], b[1]]) as f64),
+                32 => Ok(i32::from_be_bytes(b.try_into().unwrap()) as f64),
+                64 => Ok(i64::from_be_bytes(b.try_into().unwrap()) as f64),
+                -32 => Ok(f32::from_be_bytes(b.try_into().unwrap()) as f64),
+                -64 => Ok(f64::from_be_bytes(b.try_into().unwrap())),
+                _ => Err(invalid(&format!("unsupported BITPIX: {bitpix}"))),
+            })
+            .map(|v| v.map(|v| bzero + bscale * v))
+            .collect::<io::Result<_>>()?;
+        // rows are read from the bottom to the top of each image
+        let mut data = Vec::with_capacity(n_data);
+        if n_data > 0 {
+            let n_col = shape[0];
+            let n_plane = n_col * shape.get(1).cloned().unwrap_or(1);
+            for plane in values.chunks(n_plane) {
+                for row in plane.chunks(n_col).rev() {
+                    data.extend_from_slice(row);
+                }
+            }
+        }
+        Ok(Self {
+            header,
+            shape,
+            data,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn card() {
+        let header = FitsHeader::new()
+            .keyword("exptime", 900f64, "exposure time [s]")
+            .keyword("BAND", "V", "")
+            .keyword("NSTAR", 10usize, "");
+        let records: Vec<_> = header.0.iter().map(|c| c.record()).collect();
+        assert!(records.iter().all(|r| r.len() == CARD_SIZE));
+        assert_eq!(&records[1][..20], "BAND    = 'V       '");
+        assert_eq!(&records[2][..30], format!("NSTAR   = {:>20}", 10));
+        // long strings are continued over several records
+        let text = format!("{}'s {}", "x".repeat(60), "y".repeat(60));
+        let header = FitsHeader::new().keyword("OBJECT", text.as_str(), "object");
+        let records = header.0[0].record();
+        assert_eq!(records.len(), 2 * CARD_SIZE);
+        assert!(records[..CARD_SIZE].trim_end().ends_with("&'"));
+        assert!(records[CARD_SIZE..].starts_with("CONTINUE  '"));
+        let mut fits = FitsWriter::new(Vec::<u8>::new());
+        fits.write(&header, &[1, 1], &[0f64]).unwrap();
+        let image = FitsImage::from_bytes(&fits.writer).unwrap();
+        assert_eq!(
+            image.header.get("OBJECT"),
+            Some(&FitsValue::Text(text.clone()))
+        );
+        // long keywords and non finite values
+        let mut header = FitsHeader::new().keyword("LONGKEYWORD", 1f64, "");
+        assert!(header.0[0].record().starts_with("HIERARCH LONGKEYWORD = "));
+        header.push("LONGKEYWORD", f64::NAN, "");
+        assert!(header.is_empty());
+        assert!(header.try_push("STREHL", f64::INFINITY, "").is_err());
+        assert!(header.try_push("BAD KEY", 1f64, "").is_err());
+        let header = FitsHeader::new().keyword("LONGKEYWORD", 1f64, "").keyword(
+            "LONGSTRINGKEYWORD",
+            text.as_str(),
+            "",
+        );
+        let mut fits = FitsWriter::new(Vec::<u8>::new());
+        fits.write(&header, &[1, 1], &[0f64]).unwrap();
+        let image = FitsImage::from_bytes(&fits.writer).unwrap();
+        assert_eq!(image.header.get("LONGKEYWORD"), Some(&FitsValue::Real(1.)));
+        assert_eq!(
+            image.header.get("LONGSTRINGKEYWORD"),
+            Some(&FitsValue::Text(text))
+        );
+    }
+
+    #[test]
+    fn hdu() {
+        let n = 5;
+        let data: Vec<f64> = (0..n * n).map(|i| i as f64).collect();
+        let mut fits = FitsWriter::new(Vec::<u8>::new());
+        fits.write(&FitsHeader::new().keyword("BAND", "K", ""), &[n, n], &data)
+            .unwrap()
+            .write(&FitsHeader::new(), &[n, n], &data)
+            .unwrap();
+        let bytes = fits.writer;
+        assert_eq!(bytes.len(), 4 * BLOCK_SIZE);
+        assert_eq!(&bytes[..30], format!("SIMPLE  = {:>20}", "T").as_bytes());
+        assert_eq!(
+            &bytes[2 * BLOCK_SIZE..2 * BLOCK_SIZE + 20],
+            b"XTENSION= 'IMAGE   '"
+        );
+        // first value is the first pixel of the last row
+        let first = f64::from_be_bytes(bytes[BLOCK_SIZE..BLOCK_SIZE + 8].try_into().unwrap());
+        assert_eq!(first, (n * (n - 1)) as f64);
+        let image = FitsImage::from_bytes(&bytes).unwrap();
+        assert_eq!(image.shape, vec![n, n]);
+        assert_eq!(image.data, data);
+        assert_eq!(image.header.get("BAND"), Some(&"K".into()));
+    }
+}
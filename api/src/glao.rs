@@ -0,0 +1,206 @@
+use std::{f64::consts::PI, fmt::Display};
+
+use num_complex::Complex;
+use serde::Serialize;
+
+use crate::{Error, Result, Star, TurbulenceProfile};
+
+/// Ground layer adaptive optics configuration
+///
+/// A single deformable mirror, conjugated at a given altitude, is driven by the average of the wavefronts
+/// measured on an asterism of natural guide stars.
+/// The part of a turbulence layer seen in the direction of a star and common to all the guide stars is corrected,
+/// so the layers close to the conjugation altitude are corrected across the field
+/// whereas the higher layers are hardly corrected.
+/// The spatial frequencies above the cutoff set by the actuator pitch are not corrected.
+///
+/// # Example
+/// ```
+/// use eyepiece::{GroundLayerAdaptiveOptics, SeeingBuilder, Star};
+/// use skyangle::SkyAngle;
+///
+/// let asterism: Vec<_> = (0..4)
+///     .map(|i| {
+///         let (s, c) = (i as f64 * 90f64).to_radians().sin_cos();
+///         Star::new((SkyAngle::Arcminute(3. * c), SkyAngle::Arcminute(3. * s))).magnitude(12.)
+///     })
+///     .collect();
+/// let glao = GroundLayerAdaptiveOptics::new(asterism, 0., 0.5).unwrap();
+/// let seeing = SeeingBuilder::new(16e-2).ground_layer_adaptive_optics(glao);
+/// ```
+#[derive(Debug, Clone, Serialize)]
+pub struct GroundLayerAdaptiveOptics {
+    asterism: Vec<Star>,
+    conjugation_height: f64,
+    actuator_pitch: f64,
+}
+impl Display for GroundLayerAdaptiveOptics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "ground layer adaptive optics")?;
+        writeln!(
+            f,
+            " . DM conjugated at {:.0}m with {:.3}m actuator pitch",
+            self.conjugation_height, self.actuator_pitch
+        )?;
+        writeln!(f, " . asterism of {} guide stars:", self.asterism.len())?;
+        for star in &self.asterism {
+            writeln!(f, "   . {}", star)?;
+        }
+        Ok(())
+    }
+}
+impl GroundLayerAdaptiveOptics {
+    /// Creates a new GLAO configuration from the guide star asterism,
+    /// the deformable mirror conjugation altitude in meters and the actuator pitch in meters
+    ///
+    /// Returns an [error](crate::Error) if the asterism is empty or if the actuator pitch is not positive
+    pub fn new(asterism: Vec<Star>, conjugation_height: f64, actuator_pitch: f64) -> Result<Self> {
+        if asterism.is_empty() {
+            return Err(Error::AdaptiveOptics(String::from(
+                "the GLAO asterism has no guide star",
+            )));
+        }
+        if actuator_pitch <= 0f64 {
+            return Err(Error::AdaptiveOptics(format!(
+                "the actuator pitch must be positive, found {actuator_pitch}"
+            )));
+        }
+        Ok(Self {
+            asterism,
+            conjugation_height,
+            actuator_pitch,
+        })
+    }
+    /// Returns the guide star asterism
+    pub fn asterism(&self) -> &[Star] {
+        self.asterism.as_slice()
+    }
+    /// Returns the deformable mirror conjugation altitude in meters
+    pub fn conjugation_height(&self) -> f64 {
+        self.conjugation_height
+    }
+    /// Returns the actuator pitch in meters
+    pub fn actuator_pitch(&self) -> f64 {
+        self.actuator_pitch
+    }
+    /// Returns the angular diameter of the asterism in radians
+    pub fn asterism_diameter(&self) -> f64 {
+        self.asterism
+            .iter()
+            .flat_map(|a| self.asterism.iter().map(move |b| (a, b)))
+            .map(|(a, b)| {
+                let (xa, ya) = a.coordinates;
+                let (xb, yb) = b.coordinates;
+                (xa.to_radians() - xb.to_radians()).hypot(ya.to_radians() - yb.to_radians())
+            })
+            .fold(0f64, f64::max)
+    }
+    /// Returns the fraction of the turbulence corrected across the asterism
+    ///
+    /// A layer is corrected if the footprints of the pupil in the directions of the guide stars
+    /// are shifted by less than one actuator pitch with respect to each other at the layer altitude
+    pub fn corrected_fraction(&self, turbulence: &TurbulenceProfile) -> f64 {
+        let diameter = self.asterism_diameter();
+        turbulence
+            .iter()
+            .filter(|(h, _)| (h - self.conjugation_height).abs() * diameter < self.actuator_pitch)
+            .map(|(_, w)| w)
+            .sum()
+    }
+    /// Returns the ratio of the residual to the turbulence phase PSDs at the spatial frequency `(fx,fy)`
+    /// in the direction of the star
+    pub(crate) fn residual(
+        &self,
+        turbulence: &TurbulenceProfile,
+        star: &Star,
+        fx: f64,
+        fy: f64,
+    ) -> f64 {
+        let (x_star, y_star) = star.coordinates;
+        let n = self.asterism.len() as f64;
+        turbulence
+            .iter()
+            .map(|(h, w)| {
+                let dh = h - self.conjugation_height;
+                let correction = self
+                    .asterism
+                    .iter()
+                    .map(|guide_star| {
+                        let (x_gs, y_gs) = guide_star.coordinates;
+                        let red = 2.
+                            * PI
+                            * dh
+                            * (fx * (x_gs.to_radians() - x_star.to_radians())
+                                + fy * (y_gs.to_radians() - y_star.to_radians()));
+                        Complex::from_polar(1f64, red)
+                    })
+                    .sum::<Complex<f64>>()
+                    / n;
+                w * (1f64 - correction).norm_sqr()
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        field::tests::{hst_field, strehl_ratio},
+        AdaptiveOptics, SeeingBuilder, SeeingLimited,
+    };
+    use skyangle::SkyAngle;
+
+    #[test]
+    fn residual() {
+        let asterism: Vec<_> = [(1., 0.), (-1., 0.), (0., 1.), (0., -1.)]
+            .into_iter()
+            .map(|(x, y)| Star::new((SkyAngle::Arcminute(x), SkyAngle::Arcminute(y))))
+            .collect();
+        let glao = GroundLayerAdaptiveOptics::new(asterism, 0., 0.5).unwrap();
+        assert!(GroundLayerAdaptiveOptics::new(vec![], 0., 0.5).is_err());
+        let star = Star::new((SkyAngle::Arcsecond(20.), SkyAngle::Arcsecond(-10.)));
+        // the ground layer is fully corrected
+        let ground = TurbulenceProfile::ground_layer();
+        assert!(glao.residual(&ground, &star, 0.4, 0.2) < 1e-12);
+        // a high layer is barely corrected
+        let high = TurbulenceProfile::single_layer(10e3);
+        assert!(glao.residual(&high, &star, 0.4, 0.2) > 0.5);
+        let profile = TurbulenceProfile::from_layers(&[0., 10e3], &[0.6, 0.4]).unwrap();
+        assert_eq!(glao.corrected_fraction(&profile), 0.6);
+    }
+
+    #[test]
+    fn field() {
+        let peak = |seeing: SeeingBuilder, star: Star| {
+            strehl_ratio::<AdaptiveOptics>(hst_field().objects(star).seeing_limited(seeing))
+        };
+        let on_axis = Star::default();
+        let seeing_limited =
+            strehl_ratio::<SeeingLimited>(hst_field().seeing_limited(SeeingBuilder::new(16e-2)));
+        let asterism: Vec<_> = [(1., 0.), (-1., 0.), (0., 1.), (0., -1.)]
+            .into_iter()
+            .map(|(x, y)| Star::new((SkyAngle::Arcminute(x), SkyAngle::Arcminute(y))))
+            .collect();
+        let glao = GroundLayerAdaptiveOptics::new(asterism, 0., 0.2).unwrap();
+        let corrected = peak(
+            SeeingBuilder::new(16e-2).ground_layer_adaptive_optics(glao),
+            on_axis,
+        );
+        assert!(
+            corrected > seeing_limited,
+            "{corrected} vs {seeing_limited}"
+        );
+        // the correction of an high layer degrades away from the guide star
+        let seeing = |guide_star: Star| {
+            let glao = GroundLayerAdaptiveOptics::new(vec![guide_star], 0., 0.2).unwrap();
+            SeeingBuilder::new(16e-2)
+                .turbulence_profile(TurbulenceProfile::single_layer(5e3))
+                .ground_layer_adaptive_optics(glao)
+        };
+        let off_axis = Star::new((SkyAngle::Arcsecond(10.), SkyAngle::Arcsecond(0.)));
+        let near = peak(seeing(on_axis), on_axis);
+        let far = peak(seeing(off_axis), on_axis);
+        assert!(far < near, "{far} vs {near}");
+    }
+}
@@ -32,26 +32,73 @@ use std::path::Path;
 use image::{ImageResult, Rgb, RgbImage};
 use num_complex::Complex;
 use num_traits::Zero;
+use skyangle::SkyAngle;
 
+mod error;
+pub use error::{Error, Result};
 mod zpdft;
 pub use zpdft::ZpDft;
 mod telescope;
-pub use telescope::{Gmt, Hexagon, Hst, Jwst, Telescope, TelescopeBuilder};
+pub use telescope::{
+    Elt, Gmt, GmtBuilder, GmtSegment, Hexagon, Hst, Jwst, Keck, PupilMask, Rotated, Spider, Subaru,
+    Telescope, TelescopeBuilder, Tmt, Vlt,
+};
 mod photometry;
-pub use photometry::{PhotometricBands, Photometry};
+pub use photometry::{FilterTransmission, PhotometricBands, Photometry, SkyBackground};
 mod field;
 pub use field::*;
 mod objects;
 pub use objects::{MagnitudeDistribution, Objects, Star, StarDistribution};
 mod seeing;
 pub use seeing::SeeingBuilder;
+mod turbulence;
+pub use turbulence::{TurbulenceLayer, TurbulenceProfile};
 mod adaptive_optics;
+pub use adaptive_optics::Actuators;
 pub(crate) use adaptive_optics::AdaptiveOpticsCorrection;
+mod glao;
+pub use glao::GroundLayerAdaptiveOptics;
+mod ltao;
+pub use ltao::LaserTomographyAdaptiveOptics;
+mod mcao;
+pub use mcao::MultiConjugateAdaptiveOptics;
 mod bessel_knu;
+mod fits;
 mod optust;
+pub use fits::{FitsData, FitsHeader, FitsImage, FitsValue, FitsWriter};
+mod wcs;
+pub use wcs::Wcs;
+mod zernike;
+pub use zernike::Zernike;
+mod phase_screen;
+pub use phase_screen::PhaseScreen;
+mod refraction;
+pub use refraction::AtmosphericRefraction;
+
+/// Returns the optical path difference of the observer sampled like the [pupil](Observer::pupil)
+///
+/// Returns `None` if the optical path difference is null everywhere
+pub(crate) fn optical_path_difference_map<T: Observer>(observer: &T) -> Option<Vec<f64>> {
+    let diameter = observer.diameter();
+    let n_px = (diameter / observer.resolution()).round() as usize + 1;
+    let l = (n_px - 1) as f64;
+    let opd: Vec<f64> = (0..n_px)
+        .flat_map(|i| (0..n_px).map(move |j| (i, j)))
+        .map(|(i, j)| {
+            let x = (j as f64 / l - 0.5) * diameter;
+            let y = (i as f64 / l - 0.5) * diameter;
+            observer.optical_path_difference(x, y)
+        })
+        .collect();
+    opd.iter().any(|opd| *opd != 0f64).then_some(opd)
+}
 
 /// Methods common to all telescopes
 pub trait Observer: Clone {
+    /// Returns telescope name
+    fn name(&self) -> String {
+        String::from("Telescope")
+    }
     /// Returns telescope diameter
     fn diameter(&self) -> f64;
     /// Returns pupil resolution
@@ -60,48 +107,126 @@ pub trait Observer: Clone {
     }
     /// Checks if a point is inside the pupil
     fn inside_pupil(&self, x: f64, y: f64) -> bool;
+    /// Returns the pupil amplitude transmission at a given point
+    ///
+    /// The transmission is 1 inside the pupil and 0 outside by default
+    fn transmission(&self, x: f64, y: f64) -> f64 {
+        if self.inside_pupil(x, y) {
+            1f64
+        } else {
+            0f64
+        }
+    }
+    /// Returns the pupil optical path difference in meters at a given point
+    ///
+    /// The optical path difference is null by default
+    fn optical_path_difference(&self, _x: f64, _y: f64) -> f64 {
+        0f64
+    }
+    /// Returns the optical path difference in meters sampled like the [pupil](Observer::pupil)
+    ///
+    /// Returns `None` if the optical path difference is null everywhere
+    fn optical_path_difference_map(&self) -> Option<Vec<f64>> {
+        optical_path_difference_map(self)
+    }
+    /// Returns the pupil rotation angle in radians
+    ///
+    /// The rotation is counterclockwise, the default is 0
+    fn pupil_rotation(&self) -> f64 {
+        0f64
+    }
+    /// Rotates the pupil counterclockwise by `angle`
+    ///
+    /// For alt-az telescopes, the pupil rotates with respect to the sky
+    fn rotate(self, angle: SkyAngle<f64>) -> Rotated<Self>
+    where
+        Self: Sized,
+    {
+        Rotated::new(self, angle)
+    }
+    /// Returns the pupil supersampling factor
+    ///
+    /// Each pupil sample is subdivided into `n x n` points where the [transmission](Observer::transmission)
+    /// is evaluated, resulting in fractional transmissions at the pupil edges.
+    /// The default is 1, the transmission is evaluated at the center of each pupil sample
+    fn supersampling(&self) -> usize {
+        1
+    }
+    /// Returns the distance between two consecutive pupil samples
+    ///
+    /// The pupil diameter is sampled with an integer number of samples, so the sample size
+    /// may differ slightly from the [resolution](Observer::resolution)
+    fn pupil_sample_size(&self) -> f64 {
+        let diameter = self.diameter();
+        diameter / (diameter / self.resolution()).round().max(1f64)
+    }
+    /// Returns the amplitude and intensity transmissions of the pupil sample centered at a given point
+    ///
+    /// The transmissions are averaged over `n x n` points evenly distributed within the pupil sample
+    fn sample_transmission(&self, x: f64, y: f64, n: usize) -> (f64, f64) {
+        let n = n.max(1);
+        if n == 1 {
+            let t = self.transmission(x, y);
+            return (t, t * t);
+        }
+        let sample_size = self.pupil_sample_size();
+        let d = sample_size / n as f64;
+        let o = 0.5 * (sample_size - d);
+        let (a, i) = (0..n)
+            .flat_map(|i| (0..n).map(move |j| (i, j)))
+            .map(|(i, j)| self.transmission(x - o + j as f64 * d, y - o + i as f64 * d))
+            .fold((0f64, 0f64), |(a, i), t| (a + t, i + t * t));
+        let n2 = (n * n) as f64;
+        (a / n2, i / n2)
+    }
     /// Computes the pupil map
     fn pupil(&self, shift: Option<(f64, f64)>) -> Vec<Complex<f64>> {
+        self.supersampled_pupil(shift, self.supersampling())
+    }
+    /// Computes the pupil map with `n x n` points per pupil sample
+    fn supersampled_pupil(&self, shift: Option<(f64, f64)>, n: usize) -> Vec<Complex<f64>> {
         let diameter = self.diameter();
         let n_px = (diameter / self.resolution()).round() as usize + 1;
         let l = (n_px - 1) as f64;
         let mut buffer: Vec<Complex<f64>> = vec![Complex::zero(); n_px * n_px];
-        if let Some((hx, hy)) = shift {
-            for i in 0..n_px {
-                let y = (i as f64 / l - 0.5) * diameter;
-                for j in 0..n_px {
-                    let x = (j as f64 / l - 0.5) * diameter;
-                    if self.inside_pupil(x, y) {
-                        let k = i * n_px + j;
-                        buffer[k] = Complex::new(1f64, 0f64)
-                            * Complex::from_polar(
-                                1f64,
-                                -2. * std::f64::consts::PI * (x * hx + y * hy),
-                            );
-                    }
-                }
-            }
-        } else {
-            for i in 0..n_px {
-                let y = (i as f64 / l - 0.5) * diameter;
-                for j in 0..n_px {
-                    let x = (j as f64 / l - 0.5) * diameter;
-                    if self.inside_pupil(x, y) {
-                        let k = i * n_px + j;
-                        buffer[k] = Complex::new(1f64, 0f64);
-                    }
+        let (hx, hy) = shift.unwrap_or_default();
+        for i in 0..n_px {
+            let y = (i as f64 / l - 0.5) * diameter;
+            for j in 0..n_px {
+                let x = (j as f64 / l - 0.5) * diameter;
+                let (t, _) = self.sample_transmission(x, y, n);
+                if t > 0f64 {
+                    let k = i * n_px + j;
+                    buffer[k] = if shift.is_some() {
+                        Complex::from_polar(t, -2. * std::f64::consts::PI * (x * hx + y * hy))
+                    } else {
+                        Complex::new(t, 0f64)
+                    };
                 }
             }
         }
         buffer
     }
     /// Returns the pupil area
+    ///
+    /// The area is weighted by the pupil intensity transmission
     fn area(&self) -> f64 {
-        self.pupil(None)
-            .into_iter()
-            .filter_map(|p| (p.norm_sqr() > 0f64).then(|| 1f64))
+        self.supersampled_area(self.supersampling())
+    }
+    /// Returns the pupil area with `n x n` points per pupil sample
+    fn supersampled_area(&self, n: usize) -> f64 {
+        let diameter = self.diameter();
+        let n_px = (diameter / self.resolution()).round() as usize + 1;
+        let l = (n_px - 1) as f64;
+        (0..n_px)
+            .flat_map(|i| (0..n_px).map(move |j| (i, j)))
+            .map(|(i, j)| {
+                let x = (j as f64 / l - 0.5) * diameter;
+                let y = (i as f64 / l - 0.5) * diameter;
+                self.sample_transmission(x, y, n).1
+            })
             .sum::<f64>()
-            * self.resolution().powi(2)
+            * self.pupil_sample_size().powi(2)
     }
     /// Saves the pupil in an image file
     fn show_pupil<P: AsRef<Path>>(&self, path: Option<P>) -> ImageResult<()> {
@@ -110,7 +235,10 @@ pub trait Observer: Clone {
         img.pixels_mut()
             .zip(self.pupil(None).into_iter())
             .filter(|(_, pup)| pup.norm() > 0f64)
-            .for_each(|(px, _)| *px = Rgb([255, 255, 255]));
+            .for_each(|(px, pup)| {
+                let level = (255. * pup.norm().min(1f64)).round() as u8;
+                *px = Rgb([level, level, level])
+            });
         img.save(
             path.as_ref()
                 .map(|p| p.as_ref())
@@ -0,0 +1,254 @@
+use std::fmt::Display;
+
+use serde::Serialize;
+
+use crate::{adaptive_optics::footprint_coverage, Error, Result, Star, TurbulenceProfile};
+
+/// Laser tomography adaptive optics configuration
+///
+/// The high order wavefront in the direction of a star is reconstructed layer by layer
+/// from the wavefronts measured on an asterism of laser guide stars.
+/// At the altitude of a layer, the pupil footprints of the laser guide stars are shrunk by the cone effect
+/// and the part of the star footprint outside of all the laser footprints is not corrected.
+/// The tip-tilt is measured on a natural guide star and the tip-tilt anisoplanatism elongates the images
+/// along the direction of the tip-tilt star.
+/// The spatial frequencies above the cutoff set by the actuator pitch are not corrected.
+///
+/// # Example
+/// ```
+/// use eyepiece::{LaserTomographyAdaptiveOptics, SeeingBuilder, Star};
+/// use skyangle::SkyAngle;
+///
+/// let laser_asterism: Vec<_> = (0..6)
+///     .map(|i| {
+///         let (s, c) = (i as f64 * 60f64).to_radians().sin_cos();
+///         Star::new((SkyAngle::Arcsecond(30. * c), SkyAngle::Arcsecond(30. * s)))
+///     })
+///     .collect();
+/// let tip_tilt_star =
+///     Star::new((SkyAngle::Arcsecond(20.), SkyAngle::Arcsecond(-10.))).magnitude(15.);
+/// let ltao = LaserTomographyAdaptiveOptics::new(laser_asterism, 90e3, tip_tilt_star, 0.5).unwrap();
+/// let seeing = SeeingBuilder::new(16e-2).laser_tomography_adaptive_optics(ltao);
+/// ```
+#[derive(Debug, Clone, Serialize)]
+pub struct LaserTomographyAdaptiveOptics {
+    laser_asterism: Vec<Star>,
+    sodium_height: f64,
+    tip_tilt_star: Star,
+    actuator_pitch: f64,
+}
+impl Display for LaserTomographyAdaptiveOptics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "laser tomography adaptive optics")?;
+        writeln!(f, " . {:.3}m actuator pitch", self.actuator_pitch)?;
+        writeln!(
+            f,
+            " . {} laser guide stars at {:.0}km:",
+            self.laser_asterism.len(),
+            self.sodium_height * 1e-3
+        )?;
+        for star in &self.laser_asterism {
+            let (x, y) = star.coordinates;
+            writeln!(
+                f,
+                "   . @({:.3},{:.3})arcsec",
+                x.to_radians().to_degrees() * 3600.,
+                y.to_radians().to_degrees() * 3600.
+            )?;
+        }
+        writeln!(f, " . tip-tilt {}", self.tip_tilt_star)
+    }
+}
+impl LaserTomographyAdaptiveOptics {
+    /// Creates a new LTAO configuration from the laser guide star positions, the sodium layer altitude in meters,
+    /// the natural tip-tilt guide star and the actuator pitch in meters
+    ///
+    /// Returns an [error](crate::Error) if there is no laser guide star or if the sodium layer altitude
+    /// or the actuator pitch is not positive.
+    /// The sodium layer must also be above the [turbulence profile](crate::TurbulenceProfile) of the seeing,
+    /// which is checked when the field is built
+    pub fn new(
+        laser_asterism: Vec<Star>,
+        sodium_height: f64,
+        tip_tilt_star: Star,
+        actuator_pitch: f64,
+    ) -> Result<Self> {
+        if laser_asterism.is_empty() {
+            return Err(Error::AdaptiveOptics(String::from(
+                "the LTAO asterism has no laser guide star",
+            )));
+        }
+        if !(sodium_height.is_finite() && sodium_height > 0f64) {
+            return Err(Error::AdaptiveOptics(format!(
+                "the sodium layer altitude must be positive, found {sodium_height}"
+            )));
+        }
+        if actuator_pitch <= 0f64 {
+            return Err(Error::AdaptiveOptics(format!(
+                "the actuator pitch must be positive, found {actuator_pitch}"
+            )));
+        }
+        Ok(Self {
+            laser_asterism,
+            sodium_height,
+            tip_tilt_star,
+            actuator_pitch,
+        })
+    }
+    /// Returns the laser guide star positions
+    pub fn laser_asterism(&self) -> &[Star] {
+        self.laser_asterism.as_slice()
+    }
+    /// Returns the altitude of the sodium layer in meters
+    pub fn sodium_height(&self) -> f64 {
+        self.sodium_height
+    }
+    /// Returns the natural tip-tilt guide star
+    pub fn tip_tilt_star(&self) -> &Star {
+        &self.tip_tilt_star
+    }
+    /// Returns the actuator pitch in meters
+    pub fn actuator_pitch(&self) -> f64 {
+        self.actuator_pitch
+    }
+    /// Returns the fraction of the pupil footprint of the star at the altitude `height` in meters
+    /// that is covered by the footprints of the laser guide stars, for a pupil of the given diameter in meters
+    pub fn coverage(&self, star: &Star, height: f64, diameter: f64) -> f64 {
+        footprint_coverage(
+            star,
+            &self.laser_asterism,
+            height,
+            diameter,
+            Some(self.sodium_height),
+        )
+    }
+    /// Checks that the sodium layer is above all the layers of the turbulence profile
+    pub(crate) fn check_sodium_height(&self, turbulence: &TurbulenceProfile) -> Result<()> {
+        match turbulence
+            .iter()
+            .map(|(h, _)| h)
+            .find(|&h| h >= self.sodium_height)
+        {
+            Some(height) => Err(Error::AdaptiveOptics(format!(
+                "the sodium layer at {:.0}m must be above the turbulence layer at {:.0}m",
+                self.sodium_height, height
+            ))),
+            None => Ok(()),
+        }
+    }
+    /// Returns the ratio of the high order residual to the turbulence phase PSDs in the direction of the star,
+    /// for a pupil of the given diameter in meters
+    pub(crate) fn residual(
+        &self,
+        turbulence: &TurbulenceProfile,
+        star: &Star,
+        diameter: f64,
+    ) -> f64 {
+        turbulence
+            .iter()
+            .map(|(h, w)| w * (1. - self.coverage(star, h, diameter)))
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{field::tests::hst_field, AdaptiveOptics, Builder, Field, Hst, SeeingBuilder};
+    use skyangle::SkyAngle;
+
+    #[test]
+    fn coverage() {
+        let laser_asterism: Vec<_> = (0..4)
+            .map(|i| {
+                let (s, c) = (i as f64 * 90f64).to_radians().sin_cos();
+                Star::new((SkyAngle::Arcsecond(10. * c), SkyAngle::Arcsecond(10. * s)))
+            })
+            .collect();
+        let ltao =
+            LaserTomographyAdaptiveOptics::new(laser_asterism, 90e3, Star::default(), 0.5).unwrap();
+        assert!(LaserTomographyAdaptiveOptics::new(vec![], 90e3, Star::default(), 0.5).is_err());
+        for sodium_height in [0., -90e3, f64::NAN] {
+            assert!(matches!(
+                LaserTomographyAdaptiveOptics::new(
+                    ltao.laser_asterism().to_vec(),
+                    sodium_height,
+                    Star::default(),
+                    0.5
+                ),
+                Err(Error::AdaptiveOptics(_))
+            ));
+        }
+        let on_axis = Star::default();
+        // the ground layer is fully covered
+        assert_eq!(ltao.coverage(&on_axis, 0., 8.), 1.);
+        // the cone effect leaves the edge of the footprint uncovered at high altitude
+        let c = ltao.coverage(&on_axis, 15e3, 8.);
+        assert!(c > 0.5 && c < 1., "{c}");
+        // stars outside the asterism are less corrected
+        let off_axis = Star::new((SkyAngle::Arcsecond(60.), SkyAngle::Arcsecond(0.)));
+        let profile = TurbulenceProfile::default();
+        assert!(ltao.residual(&profile, &off_axis, 8.) > ltao.residual(&profile, &on_axis, 8.));
+        // the sodium layer is above the turbulence
+        let low_sodium = LaserTomographyAdaptiveOptics::new(
+            ltao.laser_asterism().to_vec(),
+            10e3,
+            Star::default(),
+            0.5,
+        )
+        .unwrap();
+        let field: Result<Field<Hst, AdaptiveOptics>> = hst_field()
+            .seeing_limited(SeeingBuilder::new(16e-2).laser_tomography_adaptive_optics(low_sodium))
+            .try_build();
+        assert!(matches!(field, Err(Error::AdaptiveOptics(_))));
+        let field: Result<Field<Hst, AdaptiveOptics>> = hst_field()
+            .seeing_limited(SeeingBuilder::new(16e-2).laser_tomography_adaptive_optics(ltao))
+            .try_build();
+        assert!(field.is_ok());
+    }
+
+    #[test]
+    fn field() {
+        let n = 51;
+        // second moments of the image along the rows and the columns
+        let moments = |tip_tilt_star: Star| {
+            let laser_asterism: Vec<_> = [(1., 0.), (-1., 0.), (0., 1.), (0., -1.)]
+                .into_iter()
+                .map(|(x, y)| {
+                    Star::new((SkyAngle::Arcsecond(x * 20.), SkyAngle::Arcsecond(y * 20.)))
+                })
+                .collect();
+            let ltao = LaserTomographyAdaptiveOptics::new(laser_asterism, 90e3, tip_tilt_star, 0.2)
+                .unwrap();
+            let mut field: Field<Hst, AdaptiveOptics> = hst_field()
+                .seeing_limited(SeeingBuilder::new(16e-2).laser_tomography_adaptive_optics(ltao))
+                .build();
+            let intensity = field.intensity(None);
+            let flux = intensity.iter().sum::<f64>();
+            let c = (n / 2) as f64;
+            intensity
+                .iter()
+                .enumerate()
+                .map(|(k, i)| {
+                    let (r, c) = ((k / n) as f64 - c, (k % n) as f64 - c);
+                    (r * r * i / flux, c * c * i / flux)
+                })
+                .fold((0f64, 0f64), |(a, b), (r, c)| (a + r, b + c))
+        };
+        // the image is round with the tip-tilt star on-axis
+        let (r, c) = moments(Star::default());
+        assert!((r / c - 1.).abs() < 1e-6);
+        // and elongated along the direction of an off-axis tip-tilt star,
+        // the tip-tilt jitter adding to the moments of the residual halo
+        let (r_x, c_x) = moments(Star::new((
+            SkyAngle::Arcminute(1.),
+            SkyAngle::Arcminute(0.),
+        )));
+        assert!(c_x - c > 1.5 * (r_x - r) && r_x > r, "{r} {r_x} {c_x}");
+        let (r_y, c_y) = moments(Star::new((
+            SkyAngle::Arcminute(0.),
+            SkyAngle::Arcminute(1.),
+        )));
+        assert!(r_y - r > 1.5 * (c_y - c) && c_y > c, "{c} {r_y} {c_y}");
+    }
+}
@@ -0,0 +1,256 @@
+use std::{f64::consts::PI, fmt::Display};
+
+use serde::Serialize;
+
+use crate::{
+    adaptive_optics::footprint_coverage, optust, Error, Photometry, Result, SeeingBuilder, Star,
+    TurbulenceProfile,
+};
+
+/// Correction of a turbulence layer by a MCAO system
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct LayerCorrection {
+    weight: f64,
+    // layer altitude minus the conjugation altitude of the DM correcting the layer
+    height_error: f64,
+    // DM fitting cutoff frequency
+    cutoff: f64,
+    // fraction of the star footprint covered by the guide stars
+    coverage: f64,
+}
+
+/// Multi-conjugate adaptive optics configuration
+///
+/// Several deformable mirrors, each conjugated at a given altitude and with its own actuator pitch,
+/// are driven by the tomographic reconstruction of the turbulence from the wavefronts measured
+/// on an asterism of natural guide stars.
+///
+/// The correction is computed layer by layer from the [turbulence profile](TurbulenceProfile):
+///  - each layer is corrected by the deformable mirror conjugated the closest to the layer altitude,
+///  - the part of the star footprint at the layer altitude that is not covered by the guide star footprints is not corrected,
+///  - the difference between the layer and the mirror altitudes results in a generalized anisoplanatism error
+///    that increases with the distance of the star from the center of the field,
+///  - the spatial frequencies above the cutoff set by the mirror actuator pitch are not corrected.
+///
+/// # Example
+/// ```
+/// use eyepiece::{Builder, Field, FieldBuilder, Gmt, Mcao, MultiConjugateAdaptiveOptics, SeeingBuilder, Star};
+/// use skyangle::SkyAngle;
+///
+/// let asterism: Vec<_> = (0..3)
+///     .map(|i| {
+///         let (s, c) = (i as f64 * 120f64).to_radians().sin_cos();
+///         Star::new((SkyAngle::Arcsecond(45. * c), SkyAngle::Arcsecond(45. * s))).magnitude(12.)
+///     })
+///     .collect();
+/// let mcao = MultiConjugateAdaptiveOptics::new(asterism, &[(0., 0.5), (8e3, 0.8)]).unwrap();
+/// let field: Field<Gmt, Mcao> = FieldBuilder::new(Gmt::new())
+///     .seeing_limited(SeeingBuilder::new(16e-2).multi_conjugate_adaptive_optics(mcao))
+///     .build();
+/// ```
+#[derive(Debug, Clone, Serialize)]
+pub struct MultiConjugateAdaptiveOptics {
+    asterism: Vec<Star>,
+    deformable_mirrors: Vec<(f64, f64)>,
+}
+impl Display for MultiConjugateAdaptiveOptics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "multi-conjugate adaptive optics")?;
+        for (height, pitch) in &self.deformable_mirrors {
+            writeln!(
+                f,
+                " . DM conjugated at {:.0}m with {:.3}m actuator pitch",
+                height, pitch
+            )?;
+        }
+        writeln!(f, " . asterism of {} guide stars:", self.asterism.len())?;
+        for star in &self.asterism {
+            writeln!(f, "   . {}", star)?;
+        }
+        Ok(())
+    }
+}
+impl MultiConjugateAdaptiveOptics {
+    /// Creates a new MCAO configuration from the guide star asterism and the conjugation altitudes
+    /// and the actuator pitches, both in meters, of the deformable mirrors
+    ///
+    /// Returns an [error](crate::Error) if there is no guide star or no deformable mirror,
+    /// or if any of the actuator pitches is not positive
+    pub fn new(asterism: Vec<Star>, deformable_mirrors: &[(f64, f64)]) -> Result<Self> {
+        if asterism.is_empty() {
+            return Err(Error::AdaptiveOptics(String::from(
+                "the MCAO asterism has no guide star",
+            )));
+        }
+        if deformable_mirrors.is_empty() {
+            return Err(Error::AdaptiveOptics(String::from(
+                "the MCAO system has no deformable mirror",
+            )));
+        }
+        if let Some((_, pitch)) = deformable_mirrors.iter().find(|(_, pitch)| *pitch <= 0f64) {
+            return Err(Error::AdaptiveOptics(format!(
+                "the actuator pitch must be positive, found {pitch}"
+            )));
+        }
+        Ok(Self {
+            asterism,
+            deformable_mirrors: deformable_mirrors.to_vec(),
+        })
+    }
+    /// Returns the guide star asterism
+    pub fn asterism(&self) -> &[Star] {
+        self.asterism.as_slice()
+    }
+    /// Returns the conjugation altitudes and the actuator pitches of the deformable mirrors
+    pub fn deformable_mirrors(&self) -> &[(f64, f64)] {
+        self.deformable_mirrors.as_slice()
+    }
+    /// Returns the smallest actuator pitch
+    pub(crate) fn actuator_pitch(&self) -> f64 {
+        self.deformable_mirrors
+            .iter()
+            .map(|(_, pitch)| *pitch)
+            .fold(f64::INFINITY, f64::min)
+    }
+    /// Returns the correction of the layers of the turbulence in the direction of the star,
+    /// for a pupil of the given diameter in meters
+    pub(crate) fn layers(
+        &self,
+        turbulence: &TurbulenceProfile,
+        star: &Star,
+        diameter: f64,
+    ) -> Vec<LayerCorrection> {
+        turbulence
+            .iter()
+            .map(|(h, w)| {
+                let (h_dm, pitch) = self
+                    .deformable_mirrors
+                    .iter()
+                    .copied()
+                    .min_by(|(a, _), (b, _)| (h - a).abs().total_cmp(&(h - b).abs()))
+                    .unwrap();
+                LayerCorrection {
+                    weight: w,
+                    height_error: h - h_dm,
+                    cutoff: 0.5 / pitch,
+                    coverage: footprint_coverage(star, &self.asterism, h, diameter, None),
+                }
+            })
+            .collect()
+    }
+    /// Returns the ratio of the residual to the turbulence phase PSDs at the spatial frequency `(fx,fy)`
+    /// in the direction of the star
+    pub(crate) fn residual(layers: &[LayerCorrection], star: &Star, fx: f64, fy: f64) -> f64 {
+        let (x_star, y_star) = star.coordinates;
+        let f = fx.hypot(fy);
+        layers
+            .iter()
+            .map(|layer| {
+                if f < layer.cutoff {
+                    let red = 2.
+                        * PI
+                        * layer.height_error
+                        * (fx * x_star.to_radians() + fy * y_star.to_radians());
+                    layer.weight * (layer.coverage * 2. * (1. - red.cos()) + 1. - layer.coverage)
+                } else {
+                    layer.weight
+                }
+            })
+            .sum()
+    }
+    /// Returns the Strehl ratio in the direction of the star at the wavelength of the [photometric band](crate::Photometry),
+    /// for a telescope of the given diameter in meters
+    ///
+    /// The Strehl ratio is derived from the variance of the residual phase, without the piston, with the Maréchal approximation.
+    /// Only the fitting, tomography and generalized anisoplanatism errors are taken into account.
+    pub fn strehl_ratio<P: Into<Photometry>>(
+        &self,
+        seeing: &SeeingBuilder,
+        band: P,
+        diameter: f64,
+        star: &Star,
+    ) -> f64 {
+        let fried_parameter = seeing.fried_parameter(band);
+        let layers = self.layers(&seeing.turbulence, star, diameter);
+        // logarithmic radial sampling of the spatial frequencies from 1e-3 to 1e3 cycle/m
+        let (n_f, n_o) = (2000, 90);
+        let d_log_f = 6. * 10f64.ln() / n_f as f64;
+        let d_o = 2. * PI / n_o as f64;
+        let variance: f64 = (0..n_f)
+            .map(|i| {
+                let f = 1e-3 * ((i as f64 + 0.5) * d_log_f).exp();
+                let piston = optust::jinc(PI * f * diameter).powi(2);
+                let residual = (0..n_o)
+                    .map(|j| {
+                        let (s, c) = (j as f64 * d_o).sin_cos();
+                        Self::residual(&layers, star, f * c, f * s)
+                    })
+                    .sum::<f64>()
+                    * d_o;
+                f * f
+                    * residual
+                    * (1. - piston)
+                    * optust::phase::spectrum(f, fried_parameter, seeing.outer_scale)
+            })
+            .sum::<f64>()
+            * d_log_f;
+        (-variance).exp()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        field::tests::{hst_field, strehl_ratio},
+        AdaptiveOptics, Builder, Field, Hst, Mcao, SeeingLimited,
+    };
+    use skyangle::SkyAngle;
+
+    #[test]
+    fn strehl_map() {
+        let asterism: Vec<_> = [(1., 0.), (-1., 0.), (0., 1.), (0., -1.)]
+            .into_iter()
+            .map(|(x, y)| Star::new((SkyAngle::Arcsecond(30. * x), SkyAngle::Arcsecond(30. * y))))
+            .collect();
+        assert!(MultiConjugateAdaptiveOptics::new(asterism.clone(), &[]).is_err());
+        let seeing = SeeingBuilder::new(16e-2);
+        let strehl = |mcao: &MultiConjugateAdaptiveOptics, x: f64| {
+            let star = Star::new((SkyAngle::Arcsecond(x), SkyAngle::Arcsecond(0.)));
+            mcao.strehl_ratio(&seeing, "K", 8., &star)
+        };
+        let scao = MultiConjugateAdaptiveOptics::new(asterism.clone(), &[(0., 0.25)]).unwrap();
+        let mcao = MultiConjugateAdaptiveOptics::new(asterism, &[(0., 0.25), (8e3, 0.25)]).unwrap();
+        // the correction is better with 2 DMs and it degrades away from the center of the field
+        assert!(strehl(&mcao, 0.) > 0.5);
+        assert!(strehl(&mcao, 20.) > strehl(&scao, 20.));
+        assert!(strehl(&mcao, 60.) < strehl(&mcao, 0.));
+    }
+
+    #[test]
+    fn field() {
+        let asterism: Vec<_> = [(1., 0.), (-1., 0.), (0., 1.), (0., -1.)]
+            .into_iter()
+            .map(|(x, y)| Star::new((SkyAngle::Arcsecond(x * 30.), SkyAngle::Arcsecond(y * 30.))))
+            .collect();
+        let mcao = MultiConjugateAdaptiveOptics::new(asterism, &[(0., 0.2), (8e3, 0.2)]).unwrap();
+        let field: Result<Field<Hst, Mcao>> = hst_field()
+            .seeing_limited(SeeingBuilder::new(16e-2).ngao(0.75, None))
+            .try_build();
+        assert!(matches!(field, Err(Error::AdaptiveOptics(_))));
+        let field: Result<Field<Hst, AdaptiveOptics>> = hst_field()
+            .seeing_limited(SeeingBuilder::new(16e-2).multi_conjugate_adaptive_optics(mcao.clone()))
+            .try_build();
+        assert!(matches!(field, Err(Error::AdaptiveOptics(_))));
+        let corrected = strehl_ratio::<Mcao>(
+            hst_field()
+                .seeing_limited(SeeingBuilder::new(16e-2).multi_conjugate_adaptive_optics(mcao)),
+        );
+        let seeing_limited =
+            strehl_ratio::<SeeingLimited>(hst_field().seeing_limited(SeeingBuilder::new(16e-2)));
+        assert!(
+            corrected > 2. * seeing_limited,
+            "{corrected} vs {seeing_limited}"
+        );
+    }
+}
@@ -32,6 +32,35 @@ impl Star {
     }
 }
 impl Star {
+    /// Returns the star in the frame rotated by the position angle in radians
+    ///
+    /// The +y axis of the rotated frame is at the position angle from the +y axis toward the -x axis
+    pub fn rotate(&self, position_angle: f64) -> Self {
+        if position_angle == 0f64 {
+            return *self;
+        }
+        let (x, y) = self.coordinates;
+        let (x, y) = (x.to_radians(), y.to_radians());
+        let (s, c) = position_angle.sin_cos();
+        Self {
+            coordinates: (
+                SkyAngle::Radian(x * c + y * s),
+                SkyAngle::Radian(-x * s + y * c),
+            ),
+            ..*self
+        }
+    }
+    /// Returns the star moved by the offsets `(dx,dy)` in radians
+    pub fn shift(&self, (dx, dy): (f64, f64)) -> Self {
+        let (x, y) = self.coordinates;
+        Self {
+            coordinates: (
+                SkyAngle::Radian(x.to_radians() + dx),
+                SkyAngle::Radian(y.to_radians() + dy),
+            ),
+            ..*self
+        }
+    }
     pub fn inside_box(&self, width: f64) -> bool {
         let (x, y) = self.coordinates;
         let h = width / 2.;
@@ -53,6 +53,30 @@ fn ln_gamma(f64: f64) -> f64 {
 pub fn gamma(x: f64) -> f64 {
     ln_gamma(x).exp()
 }
+/// Returns `2J1(x)/x`, J1 being the Bessel function of the first kind of order 1
+///
+/// The polynomial approximations of J1 are from Abramowitz and Stegun, 9.4.4 and 9.4.6
+pub fn jinc(x: f64) -> f64 {
+    let x = x.abs();
+    if x <= 3f64 {
+        let y = (x / 3.).powi(2);
+        2. * (0.5
+            + y * (-0.56249985
+                + y * (0.21093573
+                    + y * (-0.03954289 + y * (0.00443319 + y * (-0.00031761 + y * 0.00001109))))))
+    } else {
+        let y = 3. / x;
+        let f1 = 0.79788456
+            + y * (0.00000156
+                + y * (0.01659667
+                    + y * (0.00017105 + y * (-0.00249511 + y * (0.00113653 - y * 0.00020033)))));
+        let theta1 = x - 2.35619449
+            + y * (0.12499612
+                + y * (0.00005650
+                    + y * (-0.00637879 + y * (0.00074348 + y * (0.00079824 - y * 0.00029166)))));
+        2. * f1 * theta1.cos() / x.powf(1.5)
+    }
+}
 
 pub mod phase {
     use super::*;
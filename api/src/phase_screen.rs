@@ -0,0 +1,376 @@
+use std::f64::consts::PI;
+
+use num_complex::Complex;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rand_distr::{Distribution, StandardNormal};
+use rustfft::FftPlanner;
+use serde::Serialize;
+
+use crate::{optust, Error, Result, SeeingBuilder};
+
+/// Von Kármán phase screen generator
+///
+/// The random phase screens are white noise filtered with the von Kármán phase [spectrum](optust::phase::spectrum)
+/// computed with a FFT twice the size of the screen along each axis.
+/// The low spatial frequencies missed by the FFT are added with subharmonics
+/// (Lane et al., Waves in Random Media, 2(3), 1992).
+///
+/// # Example
+/// ```
+/// use eyepiece::PhaseScreen;
+///
+/// let screen = PhaseScreen::new(16e-2, 25., 64, 0.125);
+/// let phase = screen.realization(7);
+/// assert_eq!(phase.len(), 64 * 64);
+/// assert_eq!(phase, screen.realization(7));
+/// ```
+#[derive(Debug, Clone, Serialize)]
+pub struct PhaseScreen {
+    fried_parameter: f64,
+    outer_scale: f64,
+    shape: (usize, usize),
+    sampling: f64,
+    n_subharmonic: usize,
+}
+impl PhaseScreen {
+    /// Largest number of samples of a phase screen
+    ///
+    /// The FFT of a screen with that many samples needs 1GiB of memory
+    pub const MAX_SAMPLES: usize = 1 << 24;
+    /// Creates a new phase screen generator
+    ///
+    /// The screens are `n_px x n_px` maps of the phase in radians at the wavelength of the Fried parameter,
+    /// sampled every `sampling` meters. The number of subharmonic levels is set to 3.
+    pub fn new(fried_parameter: f64, outer_scale: f64, n_px: usize, sampling: f64) -> Self {
+        Self {
+            fried_parameter,
+            outer_scale,
+            shape: (n_px, n_px),
+            sampling,
+            n_subharmonic: 3,
+        }
+    }
+    /// Sets the number of subharmonic levels
+    pub fn subharmonics(self, n_subharmonic: usize) -> Self {
+        Self {
+            n_subharmonic,
+            ..self
+        }
+    }
+    /// Sets the screen shape as `(# of rows, # of columns)`
+    pub fn shape(self, shape: (usize, usize)) -> Self {
+        Self { shape, ..self }
+    }
+    /// Returns the screen width along the x axis in meters
+    pub fn size(&self) -> f64 {
+        (self.shape.1 - 1) as f64 * self.sampling
+    }
+    /// Returns the phase screen realization for the given seed
+    ///
+    /// The same seed always gives the same realization.
+    /// The phase is given row-wise with the rows along the y axis.
+    pub fn realization(&self, seed: u64) -> Vec<f64> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut gaussian = || -> Complex<f64> {
+            Complex::new(
+                StandardNormal.sample(&mut rng),
+                StandardNormal.sample(&mut rng),
+            )
+        };
+        let (n_row, n_col) = self.shape;
+        let (n_fft_row, n_fft_col) = (2 * n_row, 2 * n_col);
+        // FFT frequency sampling
+        let dfx = (n_fft_col as f64 * self.sampling).recip();
+        let dfy = (n_fft_row as f64 * self.sampling).recip();
+        let spectrum = |fx: f64, fy: f64| {
+            optust::phase::spectrum(fx.hypot(fy), self.fried_parameter, self.outer_scale)
+        };
+        let mut buffer: Vec<Complex<f64>> = (0..n_fft_row)
+            .flat_map(|i| (0..n_fft_col).map(move |j| (i, j)))
+            .map(|(i, j)| {
+                let fy = (i as f64 - if i < n_row { 0. } else { n_fft_row as f64 }) * dfy;
+                let fx = (j as f64 - if j < n_col { 0. } else { n_fft_col as f64 }) * dfx;
+                if i == 0 && j == 0 {
+                    Complex::new(0f64, 0f64)
+                } else {
+                    gaussian() * (spectrum(fx, fy) * dfx * dfy).sqrt()
+                }
+            })
+            .collect();
+        // FFT of the rows and then of the columns of the transposed buffer
+        let mut planner = FftPlanner::new();
+        planner.plan_fft_forward(n_fft_col).process(&mut buffer);
+        let mut transposed = vec![Complex::new(0f64, 0f64); buffer.len()];
+        transpose::transpose(&buffer, &mut transposed, n_fft_col, n_fft_row);
+        drop(buffer);
+        planner.plan_fft_forward(n_fft_row).process(&mut transposed);
+        let mut phase: Vec<f64> = (0..n_row)
+            .flat_map(|i| (0..n_col).map(move |j| (i, j)))
+            .map(|(i, j)| transposed[j * n_fft_row + i].re)
+            .collect();
+        drop(transposed);
+        // subharmonics
+        for p in 1..=self.n_subharmonic {
+            let (dfx_p, dfy_p) = (dfx / 3f64.powi(p as i32), dfy / 3f64.powi(p as i32));
+            for (m, l) in (-1..=1)
+                .flat_map(|m| (-1..=1).map(move |l| (m, l)))
+                .filter(|ml| *ml != (0, 0))
+            {
+                let (fx, fy) = (m as f64 * dfx_p, l as f64 * dfy_p);
+                let c = gaussian() * (spectrum(fx, fy) * dfx_p * dfy_p).sqrt();
+                for i in 0..n_row {
+                    let y = i as f64 * self.sampling;
+                    for j in 0..n_col {
+                        let x = j as f64 * self.sampling;
+                        phase[i * n_col + j] +=
+                            (c * Complex::from_polar(1., 2. * PI * (fx * x + fy * y))).re;
+                    }
+                }
+            }
+        }
+        let mean = phase.iter().sum::<f64>() / phase.len() as f64;
+        phase.iter_mut().for_each(|p| *p -= mean);
+        phase
+    }
+}
+
+/// Phase screen of a turbulence layer
+///
+/// The screen columns are along the wind direction and the screen rows across it.
+/// The screen extends upwind from the pupil footprint by the distance the wind travels during the observation.
+#[derive(Debug, Clone)]
+struct LayerScreen {
+    height: f64,
+    velocity: (f64, f64),
+    // wind direction (cosine, sine)
+    direction: (f64, f64),
+    // screen origin in the wind frame
+    origin: (f64, f64),
+    shape: (usize, usize),
+    sampling: f64,
+    phase: Vec<f64>,
+}
+impl LayerScreen {
+    /// Returns the phase at the point `(x,y)` by bilinear interpolation
+    fn phase(&self, x: f64, y: f64) -> f64 {
+        let (n_row, n_col) = self.shape;
+        let (c, s) = self.direction;
+        // coordinates along and across the wind
+        let (x, y) = (x * c + y * s, y * c - x * s);
+        let u = ((x - self.origin.0) / self.sampling).clamp(0f64, (n_col - 1) as f64);
+        let v = ((y - self.origin.1) / self.sampling).clamp(0f64, (n_row - 1) as f64);
+        let (j, i) = ((u as usize).min(n_col - 2), (v as usize).min(n_row - 2));
+        let (a, b) = (u - j as f64, v - i as f64);
+        let k = i * n_col + j;
+        (1. - b) * ((1. - a) * self.phase[k] + a * self.phase[k + 1])
+            + b * ((1. - a) * self.phase[k + n_col] + a * self.phase[k + n_col + 1])
+    }
+}
+
+/// Layered phase screens under Taylor frozen flow
+///
+/// Each layer of the [turbulence profile](crate::TurbulenceProfile) has its own [phase screen](PhaseScreen)
+/// with the Fried parameter `r0 w^(-3/5)`, `w` being the layer weight.
+/// The screens translate across the pupil at the layer wind velocity and the pupil of an off-axis star
+/// is shifted on each screen according to the layer altitude.
+#[derive(Debug, Clone)]
+pub(crate) struct FrozenFlow {
+    seed: u64,
+    fried_parameter: f64,
+    layers: Vec<LayerScreen>,
+}
+impl FrozenFlow {
+    /// Creates the layer screens of the `seeing` turbulence
+    ///
+    /// The screens are sampled every `sampling` meters and are large enough to cover a pupil of
+    /// the given diameter, for stars within the field-of-view in radians and for the given duration in seconds.
+    /// The screens are extended only along the wind and the layers without wind are static.
+    ///
+    /// Returns an error if a screen has more than [PhaseScreen::MAX_SAMPLES] samples
+    pub fn new(
+        seeing: &SeeingBuilder,
+        diameter: f64,
+        sampling: f64,
+        field_of_view: f64,
+        duration: f64,
+        seed: u64,
+    ) -> Result<Self> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let layers = seeing
+            .turbulence
+            .layers()
+            .iter()
+            .filter(|layer| layer.weight > 0f64)
+            .map(|layer| {
+                let (speed, direction) = layer.wind.unwrap_or_default();
+                let (s, c) = direction.sin_cos();
+                // radius of the footprint of the square pupil for all the stars
+                let radius = (diameter + layer.height * field_of_view) / 2f64.sqrt() + sampling;
+                let length = 2. * radius + speed * duration;
+                let shape = (
+                    (2. * radius / sampling).ceil() as usize + 1,
+                    (length / sampling).ceil() as usize + 1,
+                );
+                if shape.0 * shape.1 > PhaseScreen::MAX_SAMPLES {
+                    return Err(Error::PhaseScreen(format!(
+                        "the {:.1}x{:.1}m screen of the layer at {}m has more than {} samples",
+                        length,
+                        2. * radius,
+                        layer.height,
+                        PhaseScreen::MAX_SAMPLES
+                    )));
+                }
+                let phase = PhaseScreen::new(
+                    seeing.fried_parameter * layer.weight.powf(-0.6),
+                    seeing.outer_scale,
+                    0,
+                    sampling,
+                )
+                .shape(shape)
+                .realization(rng.gen());
+                Ok(LayerScreen {
+                    height: layer.height,
+                    velocity: (speed * c, speed * s),
+                    direction: (c, s),
+                    origin: (radius - length, -radius),
+                    shape,
+                    sampling,
+                    phase,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            seed,
+            fried_parameter: seeing.fried_parameter,
+            layers,
+        })
+    }
+    /// Returns the random generator seed
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+    /// Returns the phase in radians at the point `(x,y)` of the pupil in the direction `(theta_x,theta_y)`
+    /// at the time `time`
+    ///
+    /// The phase is given for the Fried parameter `fried_parameter`
+    pub fn phase(
+        &self,
+        (x, y): (f64, f64),
+        (theta_x, theta_y): (f64, f64),
+        time: f64,
+        fried_parameter: f64,
+    ) -> f64 {
+        self.layers
+            .iter()
+            .map(|layer| {
+                layer.phase(
+                    x + layer.height * theta_x - layer.velocity.0 * time,
+                    y + layer.height * theta_y - layer.velocity.1 * time,
+                )
+            })
+            .sum::<f64>()
+            * (self.fried_parameter / fried_parameter).powf(5. / 6.)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn structure_function() {
+        let (r0, l0) = (0.2, 25.);
+        let screen = PhaseScreen::new(r0, l0, 65, 0.125);
+        let n = 65;
+        let lag = 16;
+        let n_realization = 50;
+        let d = (0..n_realization)
+            .map(|seed| {
+                let phase = screen.realization(seed);
+                let mut d = 0f64;
+                for i in 0..n {
+                    for j in 0..n - lag {
+                        d += (phase[i * n + j + lag] - phase[i * n + j]).powi(2);
+                    }
+                }
+                d / (n * (n - lag)) as f64
+            })
+            .sum::<f64>()
+            / n_realization as f64;
+        let expected = optust::phase::structure_function(lag as f64 * 0.125, r0, l0);
+        assert!((d / expected - 1.).abs() < 0.2, "{d} vs {expected}");
+    }
+
+    #[test]
+    fn rectangular_structure_function() {
+        let (r0, l0) = (0.2, 25.);
+        let (n_row, n_col) = (33, 129);
+        let screen = PhaseScreen::new(r0, l0, 0, 0.125).shape((n_row, n_col));
+        let lag = 8;
+        let n_realization = 50;
+        let (dx, dy) = (0..n_realization)
+            .map(|seed| {
+                let phase = screen.realization(seed);
+                assert_eq!(phase.len(), n_row * n_col);
+                let (mut dx, mut dy) = (0f64, 0f64);
+                for i in 0..n_row - lag {
+                    for j in 0..n_col - lag {
+                        let k = i * n_col + j;
+                        dx += (phase[k + lag] - phase[k]).powi(2);
+                        dy += (phase[k + lag * n_col] - phase[k]).powi(2);
+                    }
+                }
+                let n = ((n_row - lag) * (n_col - lag)) as f64;
+                (dx / n, dy / n)
+            })
+            .fold((0f64, 0f64), |(a, b), (dx, dy)| (a + dx, b + dy));
+        let expected =
+            optust::phase::structure_function(lag as f64 * 0.125, r0, l0) * n_realization as f64;
+        assert!((dx / expected - 1.).abs() < 0.2, "{dx} vs {expected}");
+        assert!((dy / expected - 1.).abs() < 0.2, "{dy} vs {expected}");
+    }
+
+    #[test]
+    fn frozen_flow() {
+        let seeing = SeeingBuilder::new(0.2).turbulence_profile(
+            crate::TurbulenceProfile::single_layer(1e3)
+                .wind(&[(10., 0.)])
+                .unwrap(),
+        );
+        let frozen_flow = FrozenFlow::new(&seeing, 4., 0.1, 0., 0.2, 1).unwrap();
+        // the phase at x at time t is the phase at x-v*t at time 0
+        let phase = |x: f64, t: f64| frozen_flow.phase((x, 0.3), (0., 0.), t, 0.2);
+        assert!((phase(1.5, 0.1) - phase(0.5, 0.)).abs() < 1e-9);
+        // a star 1mrad off-axis sees the layer shifted by 1m
+        let off_axis = frozen_flow.phase((0.5, 0.3), (1e-3, 0.), 0., 0.2);
+        assert!((off_axis - phase(1.5, 0.)).abs() < 1e-9);
+        // the screen is extended along the wind only
+        let layer = &frozen_flow.layers[0];
+        assert!(layer.shape.1 > layer.shape.0);
+    }
+
+    #[test]
+    fn frozen_flow_direction() {
+        let seeing = SeeingBuilder::new(0.2).turbulence_profile(
+            crate::TurbulenceProfile::single_layer(1e3)
+                .wind(&[(10., std::f64::consts::FRAC_PI_2)])
+                .unwrap(),
+        );
+        let frozen_flow = FrozenFlow::new(&seeing, 4., 0.1, 0., 0.2, 1).unwrap();
+        // the wind blows along y
+        let phase = |y: f64, t: f64| frozen_flow.phase((0.3, y), (0., 0.), t, 0.2);
+        assert!((phase(1.5, 0.1) - phase(0.5, 0.)).abs() < 1e-9);
+        assert!((phase(-0.5, 0.2) - phase(-1.5, 0.1)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn frozen_flow_size_limit() {
+        let seeing = SeeingBuilder::new(0.2).turbulence_profile(
+            crate::TurbulenceProfile::single_layer(1e3)
+                .wind(&[(10., 0.)])
+                .unwrap(),
+        );
+        let frozen_flow = FrozenFlow::new(&seeing, 8., 2.5e-2, 0., 1e3, 1);
+        assert!(matches!(frozen_flow, Err(Error::PhaseScreen(_))));
+    }
+}
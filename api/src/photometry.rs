@@ -1,16 +1,28 @@
-use std::{fmt::Display, ops::Deref};
+use std::{fmt::Display, fs, io, ops::Deref, path::Path};
 
 use serde::Serialize;
 
+use crate::{Error, Result};
+
 /// Star photometry
 ///
-/// Photometry is available for the following bands: V, R, I, J, H and K
+/// Photometry is available for the following bands:
+///  * Johnson-Cousins V, R and I and near-infrared Z, Y, J, H, K, L and M,
+///  * SDSS u, g, r, i and z,
+///  * Gaia G, BP and RP.
+///
+/// Each band comes with a default sky surface brightness for a dark site,
+/// e.g. 21.8, 20.9, 19.9, 16.5, 14.4 and 13.5 mag/arcsec^2 in V, R, I, J, H and K, respectively.
+///
+/// User defined bands are created with [Photometry::custom] and
+/// may be given a [filter transmission](FilterTransmission) curve.
 /// ## Example
 /// ```
 /// use eyepiece::Photometry;
 /// let photometry: Photometry = "V".into();
+/// let photometry = Photometry::custom("Halpha", 656.3e-9, 3e-9, 1.2e8);
 /// ```
-#[derive(Debug, Clone, Copy, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub enum Photometry {
     V(PhotometryData),
     R(PhotometryData),
@@ -18,6 +30,19 @@ pub enum Photometry {
     J(PhotometryData),
     H(PhotometryData),
     K(PhotometryData),
+    SdssU(PhotometryData),
+    SdssG(PhotometryData),
+    SdssR(PhotometryData),
+    SdssI(PhotometryData),
+    SdssZ(PhotometryData),
+    GaiaG(PhotometryData),
+    GaiaBp(PhotometryData),
+    GaiaRp(PhotometryData),
+    Y(PhotometryData),
+    Z(PhotometryData),
+    L(PhotometryData),
+    M(PhotometryData),
+    Custom(String, PhotometryData),
 }
 impl Deref for Photometry {
     type Target = PhotometryData;
@@ -30,80 +55,453 @@ impl Deref for Photometry {
             Photometry::J(p) => p,
             Photometry::H(p) => p,
             Photometry::K(p) => p,
+            Photometry::SdssU(p) => p,
+            Photometry::SdssG(p) => p,
+            Photometry::SdssR(p) => p,
+            Photometry::SdssI(p) => p,
+            Photometry::SdssZ(p) => p,
+            Photometry::GaiaG(p) => p,
+            Photometry::GaiaBp(p) => p,
+            Photometry::GaiaRp(p) => p,
+            Photometry::Y(p) => p,
+            Photometry::Z(p) => p,
+            Photometry::L(p) => p,
+            Photometry::M(p) => p,
+            Photometry::Custom(_, p) => p,
         }
     }
 }
 /// Photometric data
-#[derive(Debug, Clone, Copy, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct PhotometryData {
     pub wavelength: f64,
     zeropoint: f64,
-    #[allow(dead_code)]
-    spectral_bandwidth: f64,
+    pub spectral_bandwidth: f64,
+    sky_brightness: f64,
+    transmission: Option<FilterTransmission>,
 }
 impl Photometry {
+    /// Creates a user defined photometric band
+    ///
+    /// The band is defined by its name, its central wavelength and spectral bandwidth in meters
+    /// and its zeropoint, the number of photons per second and per square meter of a magnitude 0 star.
+    /// The band has no sky background, it can be set with [Photometry::sky_background]
+    pub fn custom<S: Into<String>>(
+        name: S,
+        wavelength: f64,
+        spectral_bandwidth: f64,
+        zeropoint: f64,
+    ) -> Self {
+        Photometry::Custom(
+            name.into(),
+            PhotometryData {
+                wavelength,
+                zeropoint,
+                spectral_bandwidth,
+                sky_brightness: f64::INFINITY,
+                transmission: None,
+            },
+        )
+    }
+    /// Creates a user defined photometric band from a [filter transmission](FilterTransmission) curve
+    ///
+    /// The central wavelength and the spectral bandwidth are the effective wavelength
+    /// and the equivalent width of the filter, respectively
+    pub fn from_transmission<S: Into<String>>(
+        name: S,
+        transmission: FilterTransmission,
+        zeropoint: f64,
+    ) -> Self {
+        Photometry::custom(
+            name,
+            transmission.effective_wavelength(),
+            transmission.equivalent_width(),
+            zeropoint,
+        )
+        .transmission(transmission)
+    }
+    fn data_mut(&mut self) -> &mut PhotometryData {
+        match self {
+            Photometry::V(p) => p,
+            Photometry::R(p) => p,
+            Photometry::I(p) => p,
+            Photometry::J(p) => p,
+            Photometry::H(p) => p,
+            Photometry::K(p) => p,
+            Photometry::SdssU(p) => p,
+            Photometry::SdssG(p) => p,
+            Photometry::SdssR(p) => p,
+            Photometry::SdssI(p) => p,
+            Photometry::SdssZ(p) => p,
+            Photometry::GaiaG(p) => p,
+            Photometry::GaiaBp(p) => p,
+            Photometry::GaiaRp(p) => p,
+            Photometry::Y(p) => p,
+            Photometry::Z(p) => p,
+            Photometry::L(p) => p,
+            Photometry::M(p) => p,
+            Photometry::Custom(_, p) => p,
+        }
+    }
+    /// Sets the default sky surface brightness in mag/arcsec^2
+    pub fn sky_background(mut self, sky_brightness: f64) -> Self {
+        self.data_mut().sky_brightness = sky_brightness;
+        self
+    }
+    /// Sets the [filter transmission](FilterTransmission) curve
+    pub fn transmission(mut self, transmission: FilterTransmission) -> Self {
+        self.data_mut().transmission = Some(transmission);
+        self
+    }
+    /// Returns the [filter transmission](FilterTransmission) curve, if any
+    pub fn filter_transmission(&self) -> Option<&FilterTransmission> {
+        self.transmission.as_ref()
+    }
     /// Returns the number of photon for the given magnitude
     pub fn n_photon(&self, magnitude: f64) -> f64 {
         self.zeropoint * 10f64.powf(-0.4 * magnitude)
     }
+    /// Returns `n` wavelengths sampling the spectral bandwidth and their relative weights
+    ///
+    /// The wavelengths are the centers of `n` contiguous sub-bands of the spectral bandwidth,
+    /// a single sample is the central wavelength.
+    /// The weights are uniform, unless the band has a [filter transmission](FilterTransmission) curve
+    /// that does not vanish at all the wavelengths, and they sum to 1
+    pub fn spectral_samples(&self, n: usize) -> Vec<(f64, f64)> {
+        let wavelengths: Vec<_> = (0..n)
+            .map(|k| {
+                self.wavelength + self.spectral_bandwidth * ((k as f64 + 0.5) / n as f64 - 0.5)
+            })
+            .collect();
+        let weights: Vec<_> = match self.transmission.as_ref() {
+            Some(transmission) if n > 1 => {
+                wavelengths.iter().map(|&w| transmission.at(w)).collect()
+            }
+            _ => vec![1f64; n],
+        };
+        let total: f64 = weights.iter().sum();
+        let (weights, total) = if total > 0f64 {
+            (weights, total)
+        } else {
+            (vec![1f64; n], n as f64)
+        };
+        wavelengths
+            .into_iter()
+            .zip(weights.into_iter().map(|w| w / total))
+            .collect()
+    }
+    /// Returns the default sky surface brightness in mag/arcsec^2
+    pub fn sky_brightness(&self) -> f64 {
+        self.sky_brightness
+    }
+}
+
+/// Sky background
+///
+/// The sky background is either set to the default sky surface brightness of the [Photometry] band
+/// or to a given surface brightness in mag/arcsec^2
+/// ## Example
+/// ```
+/// use eyepiece::SkyBackground;
+/// let band_sky = SkyBackground::default();
+/// let dark_sky: SkyBackground = 22.5.into();
+/// ```
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub enum SkyBackground {
+    /// Photometric band default surface brightness
+    #[default]
+    Band,
+    /// Surface brightness in mag/arcsec^2
+    SurfaceBrightness(f64),
+}
+impl SkyBackground {
+    /// Returns the sky surface brightness in mag/arcsec^2 in the given band
+    pub fn brightness(&self, photometry: &Photometry) -> f64 {
+        match self {
+            SkyBackground::Band => photometry.sky_brightness(),
+            SkyBackground::SurfaceBrightness(mag) => *mag,
+        }
+    }
+}
+impl From<f64> for SkyBackground {
+    fn from(surface_brightness: f64) -> Self {
+        SkyBackground::SurfaceBrightness(surface_brightness)
+    }
+}
+
+/// Filter transmission curve
+///
+/// The transmission curve is tabulated at increasing wavelengths
+/// and it is linearly interpolated in between.
+#[derive(Debug, Clone, Serialize)]
+pub struct FilterTransmission {
+    wavelength: Vec<f64>,
+    transmission: Vec<f64>,
+}
+impl FilterTransmission {
+    /// Creates a new filter transmission curve from the wavelengths in meters and the transmission
+    ///
+    /// Returns an [error](crate::Error) if the curve is empty or if the numbers of wavelengths
+    /// and of transmission values do not match
+    pub fn new(wavelength: Vec<f64>, transmission: Vec<f64>) -> Result<Self> {
+        if wavelength.len() != transmission.len() {
+            return Err(Error::FilterTransmission(format!(
+                "the number of wavelengths ({}) and of transmission values ({}) do not match",
+                wavelength.len(),
+                transmission.len()
+            )));
+        }
+        if wavelength.is_empty() {
+            return Err(Error::FilterTransmission(String::from(
+                "no transmission value",
+            )));
+        }
+        let mut table: Vec<_> = wavelength.into_iter().zip(transmission).collect();
+        table.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let (wavelength, transmission) = table.into_iter().unzip();
+        Ok(Self {
+            wavelength,
+            transmission,
+        })
+    }
+    /// Loads a filter transmission curve from a text file
+    ///
+    /// The file has two columns, separated by spaces, tabs or commas: the wavelength in nanometers
+    /// and the transmission. Empty lines and lines starting with `#` are skipped.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let mut wavelength = vec![];
+        let mut transmission = vec![];
+        for line in contents
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !(line.is_empty() || line.starts_with('#')))
+        {
+            let values: Vec<f64> = line
+                .split(|c: char| c.is_whitespace() || c == ',')
+                .filter(|value| !value.is_empty())
+                .take(2)
+                .map(|value| value.parse::<f64>())
+                .collect::<std::result::Result<_, _>>()
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            if values.len() < 2 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("expected 2 columns, found {line:?}"),
+                ));
+            }
+            wavelength.push(values[0] * 1e-9);
+            transmission.push(values[1]);
+        }
+        Self::new(wavelength, transmission)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+    }
+    /// Returns the transmission at the given wavelength in meters
+    pub fn at(&self, wavelength: f64) -> f64 {
+        let k = self.wavelength.partition_point(|&w| w <= wavelength);
+        if k == 0 || k == self.wavelength.len() {
+            return 0f64;
+        }
+        let (w0, w1) = (self.wavelength[k - 1], self.wavelength[k]);
+        let (t0, t1) = (self.transmission[k - 1], self.transmission[k]);
+        t0 + (t1 - t0) * (wavelength - w0) / (w1 - w0)
+    }
+    fn integrate<F: Fn(f64, f64) -> f64>(&self, f: F) -> f64 {
+        self.wavelength
+            .windows(2)
+            .zip(self.transmission.windows(2))
+            .map(|(w, t)| 0.5 * (f(w[0], t[0]) + f(w[1], t[1])) * (w[1] - w[0]))
+            .sum()
+    }
+    /// Returns the filter effective wavelength in meters
+    pub fn effective_wavelength(&self) -> f64 {
+        self.integrate(|w, t| w * t) / self.integrate(|_, t| t)
+    }
+    /// Returns the filter equivalent width in meters
+    ///
+    /// The equivalent width is the width of a rectangular filter with the same peak transmission
+    pub fn equivalent_width(&self) -> f64 {
+        let peak = self
+            .transmission
+            .iter()
+            .fold(f64::NEG_INFINITY, |m, &t| m.max(t));
+        self.integrate(|_, t| t) / peak
+    }
 }
 
 /// Astronomical photometric bands
-pub struct PhotometricBands<'a>([&'a str; 6]);
+pub struct PhotometricBands<'a>(Vec<&'a str>);
 impl<'a> Default for PhotometricBands<'a> {
-    /// Returns the array `["V", "R", "I", "J", "H", "K"]`
+    /// Returns the bands `["V", "R", "I", "J", "H", "K"]`
     fn default() -> Self {
-        Self(["V", "R", "I", "J", "H", "K"])
+        Self(vec!["V", "R", "I", "J", "H", "K"])
+    }
+}
+impl<'a> PhotometricBands<'a> {
+    /// Returns the SDSS bands `["u", "g", "r", "i", "z"]`
+    pub fn sdss() -> Self {
+        Self(vec!["u", "g", "r", "i", "z"])
+    }
+    /// Returns the Gaia bands `["G", "BP", "RP"]`
+    pub fn gaia() -> Self {
+        Self(vec!["G", "BP", "RP"])
     }
 }
 impl<'a> IntoIterator for PhotometricBands<'a> {
     type Item = &'a str;
 
-    type IntoIter = std::array::IntoIter<&'a str, 6>;
+    type IntoIter = std::vec::IntoIter<&'a str>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.0.into_iter()
     }
 }
 
-impl From<&str> for Photometry {
-    /// Astronomical photometric bands
+impl Photometry {
+    /// Converts the bands V, R, I, J, H, K, u, g, r, i, z, G, BP, RP, Y, Z, L and M into star [Photometry]
     ///
-    /// Converts the bands V, R, I, J, H and K into star [Photometry]
-    fn from(band: &str) -> Self {
-        match band {
+    /// Returns an [error](crate::Error) for any other band
+    pub fn try_from(band: &str) -> Result<Self> {
+        Ok(match band {
             "V" => Photometry::V(PhotometryData {
                 wavelength: 0.55e-6,
                 zeropoint: 8.97e9,
                 spectral_bandwidth: 0.09e-6,
+                sky_brightness: 21.8,
+                transmission: None,
             }),
             "R" => Photometry::R(PhotometryData {
                 wavelength: 0.64e-6,
                 zeropoint: 10.87e9,
                 spectral_bandwidth: 0.15e-6,
+                sky_brightness: 20.9,
+                transmission: None,
             }),
             "I" => Photometry::I(PhotometryData {
                 wavelength: 0.79e-6,
                 zeropoint: 7.34e9,
                 spectral_bandwidth: 0.15e-6,
+                sky_brightness: 19.9,
+                transmission: None,
             }),
             "J" => Photometry::J(PhotometryData {
                 wavelength: 1.215e-6,
                 zeropoint: 5.16e9,
                 spectral_bandwidth: 0.26e-6,
+                sky_brightness: 16.5,
+                transmission: None,
             }),
             "H" => Photometry::H(PhotometryData {
                 wavelength: 1.654e-6,
                 zeropoint: 2.99e9,
                 spectral_bandwidth: 0.29e-6,
+                sky_brightness: 14.4,
+                transmission: None,
             }),
             "K" => Photometry::K(PhotometryData {
                 wavelength: 2.179e-6,
                 zeropoint: 1.90e9,
                 spectral_bandwidth: 0.41e-6,
+                sky_brightness: 13.5,
+                transmission: None,
             }),
-            _ => panic!("expected the photometric band: V, R, I, J, H or K, found {band}"),
-        }
+            "u" => Photometry::SdssU(PhotometryData {
+                wavelength: 0.354e-6,
+                zeropoint: 8.82e9,
+                spectral_bandwidth: 0.057e-6,
+                sky_brightness: 22.0,
+                transmission: None,
+            }),
+            "g" => Photometry::SdssG(PhotometryData {
+                wavelength: 0.477e-6,
+                zeropoint: 15.74e9,
+                spectral_bandwidth: 0.137e-6,
+                sky_brightness: 22.2,
+                transmission: None,
+            }),
+            "r" => Photometry::SdssR(PhotometryData {
+                wavelength: 0.623e-6,
+                zeropoint: 12.05e9,
+                spectral_bandwidth: 0.137e-6,
+                sky_brightness: 21.2,
+                transmission: None,
+            }),
+            "i" => Photometry::SdssI(PhotometryData {
+                wavelength: 0.762e-6,
+                zeropoint: 11.00e9,
+                spectral_bandwidth: 0.153e-6,
+                sky_brightness: 20.5,
+                transmission: None,
+            }),
+            "z" => Photometry::SdssZ(PhotometryData {
+                wavelength: 0.913e-6,
+                zeropoint: 5.70e9,
+                spectral_bandwidth: 0.095e-6,
+                sky_brightness: 19.6,
+                transmission: None,
+            }),
+            "G" => Photometry::GaiaG(PhotometryData {
+                wavelength: 0.622e-6,
+                zeropoint: 34.90e9,
+                spectral_bandwidth: 0.440e-6,
+                sky_brightness: 21.2,
+                transmission: None,
+            }),
+            "BP" => Photometry::GaiaBp(PhotometryData {
+                wavelength: 0.511e-6,
+                zeropoint: 28.00e9,
+                spectral_bandwidth: 0.265e-6,
+                sky_brightness: 21.9,
+                transmission: None,
+            }),
+            "RP" => Photometry::GaiaRp(PhotometryData {
+                wavelength: 0.777e-6,
+                zeropoint: 15.04e9,
+                spectral_bandwidth: 0.296e-6,
+                sky_brightness: 20.4,
+                transmission: None,
+            }),
+            "Y" => Photometry::Y(PhotometryData {
+                wavelength: 1.02e-6,
+                zeropoint: 3.01e9,
+                spectral_bandwidth: 0.10e-6,
+                sky_brightness: 17.9,
+                transmission: None,
+            }),
+            "Z" => Photometry::Z(PhotometryData {
+                wavelength: 0.88e-6,
+                zeropoint: 3.82e9,
+                spectral_bandwidth: 0.10e-6,
+                sky_brightness: 18.8,
+                transmission: None,
+            }),
+            "L" => Photometry::L(PhotometryData {
+                wavelength: 3.45e-6,
+                zeropoint: 0.577e9,
+                spectral_bandwidth: 0.47e-6,
+                sky_brightness: 4.5,
+                transmission: None,
+            }),
+            "M" => Photometry::M(PhotometryData {
+                wavelength: 4.75e-6,
+                zeropoint: 0.256e9,
+                spectral_bandwidth: 0.46e-6,
+                sky_brightness: 1.0,
+                transmission: None,
+            }),
+            _ => return Err(Error::PhotometricBand(band.to_string())),
+        })
+    }
+}
+impl From<&str> for Photometry {
+    /// Astronomical photometric bands
+    ///
+    /// Converts the bands V, R, I, J, H, K, u, g, r, i, z, G, BP, RP, Y, Z, L and M into star [Photometry]
+    ///
+    /// # Panics
+    /// Panics on any other band, see [Photometry::try_from] for the fallible conversion
+    fn from(band: &str) -> Self {
+        Photometry::try_from(band).unwrap_or_else(|e| panic!("{e}"))
     }
 }
 impl From<&String> for Photometry {
@@ -116,6 +514,11 @@ impl From<String> for Photometry {
         band.as_str().into()
     }
 }
+impl From<&Photometry> for Photometry {
+    fn from(photometry: &Photometry) -> Self {
+        photometry.clone()
+    }
+}
 impl Display for Photometry {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -128,7 +531,91 @@ impl Display for Photometry {
                 Photometry::J(_) => "J",
                 Photometry::H(_) => "H",
                 Photometry::K(_) => "K",
+                Photometry::SdssU(_) => "u",
+                Photometry::SdssG(_) => "g",
+                Photometry::SdssR(_) => "r",
+                Photometry::SdssI(_) => "i",
+                Photometry::SdssZ(_) => "z",
+                Photometry::GaiaG(_) => "G",
+                Photometry::GaiaBp(_) => "BP",
+                Photometry::GaiaRp(_) => "RP",
+                Photometry::Y(_) => "Y",
+                Photometry::Z(_) => "Z",
+                Photometry::L(_) => "L",
+                Photometry::M(_) => "M",
+                Photometry::Custom(name, _) => name,
             }
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{field::tests::hst_field, Builder, DiffractionLimited, Field, Hst};
+
+    #[test]
+    fn try_from() {
+        assert!(Photometry::try_from("BP").is_ok());
+        assert!(matches!(
+            Photometry::try_from("W"),
+            Err(Error::PhotometricBand(band)) if band == "W"
+        ));
+    }
+
+    #[test]
+    fn transmission() {
+        let path = std::env::temp_dir().join("filter.txt");
+        fs::write(&path, "# nm T\n500 0\n510 1\n\n530, 1\n540 0\n").unwrap();
+        let filter = FilterTransmission::from_file(&path).unwrap();
+        assert!((filter.at(505e-9) - 0.5).abs() < 1e-9);
+        assert_eq!(filter.at(600e-9), 0.);
+        assert!((filter.effective_wavelength() - 520e-9).abs() < 1e-12);
+        assert!((filter.equivalent_width() - 30e-9).abs() < 1e-12);
+        let photometry = Photometry::from_transmission("custom", filter, 1e10);
+        assert_eq!(photometry.to_string(), "custom");
+        let samples = photometry.spectral_samples(4);
+        assert!((samples.iter().map(|(_, w)| w).sum::<f64>() - 1.).abs() < 1e-12);
+        // uniform weights if the filter vanishes within the band
+        let photometry = Photometry::custom("custom", 700e-9, 50e-9, 1e10)
+            .transmission(photometry.filter_transmission().unwrap().clone());
+        assert!(photometry
+            .spectral_samples(4)
+            .iter()
+            .all(|(_, w)| (w - 0.25).abs() < 1e-12));
+        assert!(FilterTransmission::new(vec![500e-9], vec![]).is_err());
+    }
+
+    #[test]
+    fn sky_background_field() {
+        let mut field: Field<Hst, DiffractionLimited> = hst_field()
+            .field_of_view(21)
+            .photometry("J")
+            .sky_background(SkyBackground::default())
+            .build();
+        assert_eq!(field.sky_brightness(), Some(16.5));
+        let n_sky = field.sky_photons().unwrap();
+        assert!(n_sky > 0.);
+        let intensity = field.intensity(None);
+        assert!(intensity.iter().all(|&i| i >= n_sky));
+        // custom bands have no default sky brightness
+        let band = Photometry::custom("Halpha", 656.3e-9, 3e-9, 1.2e8);
+        let field: crate::Result<Field<Hst, DiffractionLimited>> = hst_field()
+            .photometry(band.clone())
+            .sky_background(SkyBackground::default())
+            .try_build();
+        assert!(matches!(field, Err(Error::SkyBackground(_))));
+        let field: Field<Hst, DiffractionLimited> = hst_field()
+            .photometry(band.clone())
+            .sky_background(SkyBackground::default())
+            .build();
+        assert_eq!(field.sky_brightness(), None);
+        assert!(field.header().get("SKYBRIGH").is_none());
+        let field: Field<Hst, DiffractionLimited> = hst_field()
+            .photometry(band.sky_background(21.))
+            .sky_background(SkyBackground::default())
+            .try_build()
+            .unwrap();
+        assert_eq!(field.sky_brightness(), Some(21.));
+    }
+}
@@ -0,0 +1,206 @@
+use std::fmt::Display;
+
+use serde::Serialize;
+use skyangle::SkyAngle;
+
+/// Atmospheric refraction model
+///
+/// The refractivity of air is derived from the site temperature, pressure and relative humidity
+/// following Filippenko, PASP, 94, 1982.
+/// The refraction moves the stars toward the zenith, along the parallactic angle,
+/// by `(n-1) tan(z)` where `z` is the zenith angle.
+/// The parallactic angle is the angle of the direction to the zenith from north toward east.
+///
+/// # Example
+/// ```
+/// use eyepiece::{AtmosphericRefraction, SeeingBuilder};
+/// use skyangle::SkyAngle;
+///
+/// let refraction = AtmosphericRefraction::new()
+///     .temperature(12.)
+///     .humidity(0.3)
+///     .altitude(2400.)
+///     .parallactic_angle(SkyAngle::Degree(30.));
+/// let seeing = SeeingBuilder::new(16e-2)
+///     .zenith_angle(SkyAngle::Degree(45.))
+///     .atmospheric_refraction(refraction);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct AtmosphericRefraction {
+    temperature: f64,
+    pressure: Option<f64>,
+    humidity: f64,
+    altitude: f64,
+    parallactic_angle: f64,
+    reference_wavelength: Option<f64>,
+}
+impl Default for AtmosphericRefraction {
+    fn default() -> Self {
+        Self {
+            temperature: 10f64,
+            pressure: None,
+            humidity: 0.2,
+            altitude: 0f64,
+            parallactic_angle: 0f64,
+            reference_wavelength: None,
+        }
+    }
+}
+impl AtmosphericRefraction {
+    /// Creates a new atmospheric refraction model
+    ///
+    /// The default site is at sea level with a temperature of 10C, a relative humidity of 20%
+    /// and a parallactic angle of 0
+    pub fn new() -> Self {
+        Default::default()
+    }
+    /// Sets the site temperature in Celsius
+    pub fn temperature(self, temperature: f64) -> Self {
+        Self {
+            temperature,
+            ..self
+        }
+    }
+    /// Sets the site pressure in hPa
+    ///
+    /// If the pressure is not set, it is derived from the site altitude
+    pub fn pressure(self, pressure: f64) -> Self {
+        Self {
+            pressure: Some(pressure),
+            ..self
+        }
+    }
+    /// Sets the site relative humidity (between 0 and 1)
+    pub fn humidity(self, humidity: f64) -> Self {
+        Self { humidity, ..self }
+    }
+    /// Sets the site altitude in meters
+    pub fn altitude(self, altitude: f64) -> Self {
+        Self { altitude, ..self }
+    }
+    /// Sets the parallactic angle
+    pub fn parallactic_angle(self, parallactic_angle: SkyAngle<f64>) -> Self {
+        Self {
+            parallactic_angle: parallactic_angle.to_radians(),
+            ..self
+        }
+    }
+    /// Sets the wavelength in meters of the star images that are not moved by the refraction
+    ///
+    /// The default is the central wavelength of the field [photometric band](crate::Photometry),
+    /// setting the same reference wavelength for all the bands of a [polychromatic field](crate::PolychromaticField)
+    /// disperses the images from one band to the next
+    pub fn reference_wavelength(self, reference_wavelength: f64) -> Self {
+        Self {
+            reference_wavelength: Some(reference_wavelength),
+            ..self
+        }
+    }
+    /// Returns the site pressure in hPa
+    ///
+    /// Without a given pressure, the pressure is the standard atmosphere pressure at the site altitude
+    pub fn site_pressure(&self) -> f64 {
+        self.pressure
+            .unwrap_or_else(|| 1013.25 * (1. - 2.25577e-5 * self.altitude).powf(5.25588))
+    }
+    /// Returns the refractivity `n-1` of air at the wavelength in meters
+    pub fn refractivity(&self, wavelength: f64) -> f64 {
+        let sigma2 = (wavelength * 1e6).recip().powi(2);
+        let t = self.temperature;
+        // dry air at 15C and 760mmHg
+        let n_1 = 64.328 + 29498.1 / (146. - sigma2) + 255.4 / (41. - sigma2);
+        // pressure [mmHg]
+        let p = self.site_pressure() * 0.750062;
+        let n_1 =
+            n_1 * p * (1. + (1.049 - 0.0157 * t) * 1e-6 * p) / (720.883 * (1. + 0.003661 * t));
+        // water vapour pressure [mmHg]
+        let f = self.humidity * 6.1094 * (17.625 * t / (t + 243.04)).exp() * 0.750062;
+        (n_1 - f * (0.0624 - 0.000680 * sigma2) / (1. + 0.003661 * t)) * 1e-6
+    }
+    /// Returns the atmospheric refraction in radians at the wavelength in meters and at the zenith angle in radians
+    pub fn refraction(&self, wavelength: f64, zenith_angle: f64) -> f64 {
+        self.refractivity(wavelength) * zenith_angle.tan()
+    }
+    /// Returns the sky coordinates offsets in radians of a star at the wavelength with respect to
+    /// the reference wavelength, both in meters, and at the zenith angle in radians
+    ///
+    /// The reference wavelength is used only if it has not been [set](AtmosphericRefraction::reference_wavelength)
+    pub fn dispersion(
+        &self,
+        wavelength: f64,
+        reference_wavelength: f64,
+        zenith_angle: f64,
+    ) -> (f64, f64) {
+        let reference_wavelength = self.reference_wavelength.unwrap_or(reference_wavelength);
+        let dr = self.refraction(wavelength, zenith_angle)
+            - self.refraction(reference_wavelength, zenith_angle);
+        let (s, c) = self.parallactic_angle.sin_cos();
+        // north is +y and east is -x
+        (-dr * s, dr * c)
+    }
+}
+impl Display for AtmosphericRefraction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "atmospheric refraction: {:.1}C, {:.1}hPa, {:.0}% humidity, parallactic angle: {:.3}degree",
+            self.temperature,
+            self.site_pressure(),
+            self.humidity * 1e2,
+            self.parallactic_angle.to_degrees()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{field::tests::hst_field, Builder, Field, Hst, SeeingBuilder, SeeingLimited};
+    use skyangle::SkyAngle;
+
+    #[test]
+    fn refraction() {
+        let adr = AtmosphericRefraction::new()
+            .temperature(15.)
+            .pressure(1013.25)
+            .humidity(0.);
+        let z = 45f64.to_radians();
+        // ~1 arcmin at 45 degree
+        let r = adr.refraction(500e-9, z).to_degrees() * 3600.;
+        assert!(r > 55. && r < 60., "{r}");
+        // blue light is more refracted than red light
+        assert!(adr.refraction(400e-9, z) > adr.refraction(700e-9, z));
+        // higher sites refract less
+        let sea_level = AtmosphericRefraction::new().temperature(15.).humidity(0.);
+        assert!(sea_level.altitude(2400.).refraction(500e-9, z) < sea_level.refraction(500e-9, z));
+        // the dispersion is along the parallactic angle
+        let (dx, dy) = adr
+            .parallactic_angle(SkyAngle::Degree(90.))
+            .dispersion(400e-9, 500e-9, z);
+        assert!(dx < 0. && dy.abs() < 1e-15);
+    }
+
+    #[test]
+    fn field() {
+        let n = 51;
+        let centroid = |refraction: crate::AtmosphericRefraction| {
+            let seeing = SeeingBuilder::new(10.)
+                .zenith_angle(SkyAngle::Degree(60.))
+                .atmospheric_refraction(refraction);
+            let mut field: Field<Hst, SeeingLimited> = hst_field().seeing_limited(seeing).build();
+            let intensity = field.intensity(None);
+            intensity
+                .iter()
+                .enumerate()
+                .map(|(k, i)| (k / n) as f64 * i)
+                .sum::<f64>()
+                / intensity.iter().sum::<f64>()
+                - (n / 2) as f64
+        };
+        // the images at the band center are not moved
+        assert!(centroid(AtmosphericRefraction::new()).abs() < 0.1);
+        // the longer wavelengths are less refracted, moving the images away from the zenith
+        let shift = centroid(AtmosphericRefraction::new().reference_wavelength(500e-9));
+        assert!(shift > 1., "{shift}");
+    }
+}
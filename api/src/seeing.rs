@@ -3,7 +3,11 @@ use std::fmt::Display;
 use serde::Serialize;
 use skyangle::SkyAngle;
 
-use crate::{AdaptiveOpticsCorrection, Photometry, Star};
+use crate::{
+    optust, Actuators, AdaptiveOpticsCorrection, AtmosphericRefraction, Error,
+    GroundLayerAdaptiveOptics, LaserTomographyAdaptiveOptics, MultiConjugateAdaptiveOptics,
+    Photometry, Result, Star, TurbulenceProfile,
+};
 
 /// Atmospheric seeing builder
 ///
@@ -21,12 +25,49 @@ pub struct SeeingBuilder {
     pub(crate) fried_parameter: f64,
     pub(crate) outer_scale: f64,
     pub(crate) adaptive_optics: Option<AdaptiveOpticsCorrection>,
+    pub(crate) turbulence: TurbulenceProfile,
+    pub(crate) airmass: f64,
+    pub(crate) refraction: Option<AtmosphericRefraction>,
+    pub(crate) wavelength: f64,
+    /// adaptive optics control loop frame rate [Hz] and delay [s]
+    control_loop: Option<(f64, f64)>,
+    /// adaptive optics wavefront sensor photometric band and throughput
+    wavefront_sensor: Option<(Photometry, f64)>,
 }
 impl Display for SeeingBuilder {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "seeing limited:")?;
         writeln!(f, " . Fried parameter: {:.3}cm", self.fried_parameter * 1e2)?;
         writeln!(f, " . outer scale: {:.3}m", self.outer_scale)?;
+        writeln!(f, " . {}", self.turbulence)?;
+        let band: Photometry = "V".into();
+        writeln!(
+            f,
+            " . V band seeing FWHM: {:.3}arcsec (Kolmogorov), {:.3}arcsec (von Karman)",
+            self.kolmogorov_fwhm(&band).to_degrees() * 3600.,
+            self.von_karman_fwhm(&band).to_degrees() * 3600.
+        )?;
+        writeln!(
+            f,
+            " . V band isoplanatic angle: {:.3}arcsec",
+            self.isoplanatic_angle(&band).to_degrees() * 3600.
+        )?;
+        if let Some(tau0) = self.coherence_time(&band) {
+            writeln!(f, " . V band coherence time: {:.3}ms", tau0 * 1e3)?;
+        }
+        writeln!(
+            f,
+            " . V band phase variance: {:.3}rd^2",
+            self.phase_variance(&band)
+        )?;
+        if let Some(refraction) = &self.refraction {
+            writeln!(
+                f,
+                " . zenith angle: {:.3}degree",
+                self.airmass.recip().acos().to_degrees()
+            )?;
+            writeln!(f, " . {}", refraction)?;
+        }
         if let Some(ao) = &self.adaptive_optics {
             write!(f, r" . with {}", ao)?;
         }
@@ -36,12 +77,27 @@ impl Display for SeeingBuilder {
 impl SeeingBuilder {
     /// Creates a new atmospheric seeing builder by setting the Fried parameter in meters @ 500nm
     ///
-    /// The outer scale is set to 25m.
+    /// The outer scale is set to 25m and the turbulence profile to the [default](TurbulenceProfile::default) profile.
     pub fn new(fried_parameter: f64) -> Self {
         Self {
             fried_parameter,
             outer_scale: 25.,
             adaptive_optics: None,
+            turbulence: Default::default(),
+            airmass: 1f64,
+            refraction: None,
+            wavelength: 500e-9,
+            control_loop: None,
+            wavefront_sensor: None,
+        }
+    }
+    /// Sets the [turbulence profile](TurbulenceProfile)
+    ///
+    /// The layer altitudes are scaled according to the [zenith angle](SeeingBuilder::zenith_angle)
+    pub fn turbulence_profile(self, turbulence: TurbulenceProfile) -> Self {
+        Self {
+            turbulence: turbulence.scale_height(self.airmass),
+            ..self
         }
     }
     /// Sets the atmosphere outer scale on meters
@@ -51,63 +107,338 @@ impl SeeingBuilder {
             ..self
         }
     }
-    /// Scales the Fried parameter according to the zenith angle
+    /// Scales the Fried parameter and the altitudes of the turbulence layers according to the zenith angle
     pub fn zenith_angle(self, zenith_angle: SkyAngle<f64>) -> Self {
+        let cos_z = zenith_angle.to_radians().cos();
         Self {
-            fried_parameter: self.fried_parameter
-                * zenith_angle.to_radians().cos().powf(3_f64 / 5_f64),
+            fried_parameter: self.fried_parameter * cos_z.powf(3_f64 / 5_f64),
+            turbulence: self.turbulence.scale_height(cos_z.recip()),
+            airmass: self.airmass / cos_z,
             ..self
         }
     }
+    /// Sets the [atmospheric refraction](AtmosphericRefraction) model
+    ///
+    /// The stars are dispersed according to the [zenith angle](SeeingBuilder::zenith_angle) along the parallactic angle,
+    /// the images at the central wavelength of the photometric band being unmoved
+    pub fn atmospheric_refraction(self, refraction: AtmosphericRefraction) -> Self {
+        Self {
+            refraction: Some(refraction),
+            ..self
+        }
+    }
+    /// Returns the sky coordinates offsets in radians of the stars at the wavelength
+    /// with respect to the reference wavelength due to the [atmospheric refraction](AtmosphericRefraction)
+    pub(crate) fn dispersion(
+        &self,
+        wavelength: f64,
+        reference_wavelength: f64,
+    ) -> Option<(f64, f64)> {
+        self.refraction.as_ref().map(|refraction| {
+            refraction.dispersion(
+                wavelength,
+                reference_wavelength,
+                self.airmass.recip().acos(),
+            )
+        })
+    }
     /// Reduces the seeing FWHM by the given fraction
+    ///
+    /// The whole PSF is scaled uniformly, see [SeeingBuilder::ground_layer_adaptive_optics]
+    /// for a model of the correction of the turbulence layers across the field
+    ///
+    /// # Panics
+    /// Panics if the fraction is not less than 1, see [SeeingBuilder::try_glao]
     pub fn glao(self, corrected_fraction: f64) -> Self {
-        assert!(
-            corrected_fraction < 1f64,
-            "GLAO fraction of correction should be less that 1"
-        );
-        Self {
+        self.try_glao(corrected_fraction)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+    /// Reduces the seeing FWHM by the given fraction
+    ///
+    /// Returns an [error](crate::Error) if the fraction is not less than 1
+    pub fn try_glao(self, corrected_fraction: f64) -> Result<Self> {
+        if corrected_fraction >= 1f64 {
+            return Err(Error::AdaptiveOptics(format!(
+                "the GLAO fraction of correction must be less than 1, found {corrected_fraction}"
+            )));
+        }
+        Ok(Self {
             fried_parameter: self.fried_parameter / (1. - corrected_fraction),
             ..self
-        }
+        })
     }
     /// Scales the Fried parameter according to the wavelength of the [photometric bands](crate::Photometry)
     pub(crate) fn wavelength<P: Into<Photometry>>(self, band: P) -> Self {
         let photometry: Photometry = band.into();
+        self.scale_wavelength(photometry.wavelength, 500e-9)
+    }
+    /// Scales the Fried parameter from the reference wavelength to the given wavelength
+    pub(crate) fn scale_wavelength(self, wavelength: f64, reference_wavelength: f64) -> Self {
         Self {
             fried_parameter: self.fried_parameter
-                * (photometry.wavelength / 500e-9).powf(1.2_f64),
+                * (wavelength / reference_wavelength).powf(1.2_f64),
+            wavelength: self.wavelength * wavelength / reference_wavelength,
             ..self
         }
     }
-    /// Corrects the seeing with a Natural Guide Star Adaptive Optics system
+    /// Returns the Fried parameter in meters at the wavelength of the [photometric band](crate::Photometry)
+    pub fn fried_parameter<P: Into<Photometry>>(&self, band: P) -> f64 {
+        let photometry: Photometry = band.into();
+        self.fried_parameter * (photometry.wavelength / self.wavelength).powf(1.2_f64)
+    }
+    /// Returns the Kolmogorov seeing FWHM in radians at the wavelength of the [photometric band](crate::Photometry)
+    pub fn kolmogorov_fwhm<P: Into<Photometry>>(&self, band: P) -> f64 {
+        let photometry: Photometry = band.into();
+        0.976 * photometry.wavelength / self.fried_parameter(&photometry)
+    }
+    /// Returns the von Kármán seeing FWHM in radians at the wavelength of the [photometric band](crate::Photometry)
     ///
-    /// Only the fitting and anisoplanatism errors of the NGAO system are modeled.
-    /// The fitting error is set according to the Strehl ratio (≥ 0.5).
-    /// The anisoplanatism error is set only if a guide star is given.
-    pub fn ngao(self, strehl_ratio: f64, guide_star: Option<Star>) -> Self {
-        if strehl_ratio < 0.5 {
-            panic!("Strel ratio must be at least 0.5 or higher");
+    /// The Kolmogorov FWHM is corrected for the outer scale according to
+    /// Tokovinin, JOSA A, 19(7), 2002: `sqrt(1-2.183(r0/L0)^0.356)`
+    pub fn von_karman_fwhm<P: Into<Photometry>>(&self, band: P) -> f64 {
+        let photometry: Photometry = band.into();
+        let r0 = self.fried_parameter(&photometry);
+        let correction = 1. - 2.183 * (r0 / self.outer_scale).powf(0.356);
+        self.kolmogorov_fwhm(&photometry) * correction.max(0f64).sqrt()
+    }
+    /// Returns the isoplanatic angle in radians at the wavelength of the [photometric band](crate::Photometry)
+    ///
+    /// The isoplanatic angle is derived from the altitudes of the layers of the [turbulence profile](TurbulenceProfile)
+    pub fn isoplanatic_angle<P: Into<Photometry>>(&self, band: P) -> f64 {
+        let mean_height = self
+            .turbulence
+            .iter()
+            .map(|(h, w)| w * h.powf(5. / 3.))
+            .sum::<f64>()
+            .powf(3. / 5.);
+        0.314 * self.fried_parameter(band) / mean_height
+    }
+    /// Returns the coherence time in seconds at the wavelength of the [photometric band](crate::Photometry)
+    ///
+    /// The coherence time is derived from the wind speeds of the layers of the [turbulence profile](TurbulenceProfile),
+    /// it is `None` if the wind is not set for all the layers
+    pub fn coherence_time<P: Into<Photometry>>(&self, band: P) -> Option<f64> {
+        let mean_speed = self
+            .turbulence
+            .layers()
+            .iter()
+            .map(|layer| layer.wind.map(|(v, _)| layer.weight * v.powf(5. / 3.)))
+            .sum::<Option<f64>>()?
+            .powf(3. / 5.);
+        Some(0.314 * self.fried_parameter(band) / mean_speed)
+    }
+    /// Returns the von Kármán phase variance in radians squared at the wavelength of the [photometric band](crate::Photometry)
+    pub fn phase_variance<P: Into<Photometry>>(&self, band: P) -> f64 {
+        optust::phase::variance(self.fried_parameter(band), self.outer_scale)
+    }
+    /// Sets the frame rate in Hz and the delay in seconds of the adaptive optics control loop
+    ///
+    /// The servo-lag error is derived from the wind of the layers of the [turbulence profile](TurbulenceProfile).
+    /// The control loop applies to the adaptive optics correction whether it is set before or after,
+    /// see [SeeingBuilder::ngao], [SeeingBuilder::ltao], [SeeingBuilder::ground_layer_adaptive_optics],
+    /// [SeeingBuilder::laser_tomography_adaptive_optics] and [SeeingBuilder::multi_conjugate_adaptive_optics]
+    pub fn control_loop(self, frame_rate: f64, delay: f64) -> Self {
+        Self {
+            adaptive_optics: self
+                .adaptive_optics
+                .map(|ao| ao.control_loop(frame_rate, delay)),
+            control_loop: Some((frame_rate, delay)),
+            ..self
+        }
+    }
+    /// Sets the [photometric band](crate::Photometry) and the throughput of the wavefront sensor
+    ///
+    /// The wavefront sensor noise error is derived from the number of photons from the natural guide star,
+    /// the observed star being its own guide star if none is given.
+    /// The noise of laser guide stars is not modeled but the noise of the tip-tilt star of
+    /// a [LTAO](SeeingBuilder::laser_tomography_adaptive_optics) system is.
+    /// The wavefront sensor applies to the adaptive optics correction whether it is set before or after,
+    /// see [SeeingBuilder::ngao], [SeeingBuilder::ltao], [SeeingBuilder::ground_layer_adaptive_optics],
+    /// [SeeingBuilder::laser_tomography_adaptive_optics] and [SeeingBuilder::multi_conjugate_adaptive_optics]
+    pub fn wavefront_sensor<P: Into<Photometry>>(self, band: P, throughput: f64) -> Self {
+        let band: Photometry = band.into();
+        Self {
+            adaptive_optics: self
+                .adaptive_optics
+                .map(|ao| ao.wavefront_sensor(band.clone(), throughput)),
+            wavefront_sensor: Some((band, throughput)),
+            ..self
+        }
+    }
+    /// Sets the adaptive optics correction with the [control loop](SeeingBuilder::control_loop)
+    /// and the [wavefront sensor](SeeingBuilder::wavefront_sensor) if any
+    fn with_adaptive_optics(self, mut ao: AdaptiveOpticsCorrection) -> Self {
+        if let Some((frame_rate, delay)) = self.control_loop {
+            ao = ao.control_loop(frame_rate, delay);
+        }
+        if let Some((band, throughput)) = self.wavefront_sensor.clone() {
+            ao = ao.wavefront_sensor(band, throughput);
         }
         Self {
-            adaptive_optics: Some(AdaptiveOpticsCorrection::ngao(strehl_ratio, guide_star)),
+            adaptive_optics: Some(ao),
             ..self
         }
     }
+    /// Corrects the seeing with a [Ground Layer Adaptive Optics](GroundLayerAdaptiveOptics) system
+    ///
+    /// The residual of each layer of the [turbulence profile](TurbulenceProfile) depends on the layer altitude
+    /// and on the position of the star with respect to the guide stars,
+    /// the fitting error is set by the actuator pitch.
+    /// The servo-lag and noise errors are modeled if the [control loop](SeeingBuilder::control_loop)
+    /// and the [wavefront sensor](SeeingBuilder::wavefront_sensor) are set.
+    pub fn ground_layer_adaptive_optics(self, glao: GroundLayerAdaptiveOptics) -> Self {
+        self.with_adaptive_optics(AdaptiveOpticsCorrection::glao(glao))
+    }
+    /// Corrects the seeing with a [Laser Tomography Adaptive Optics](LaserTomographyAdaptiveOptics) system
+    ///
+    /// The residual of each layer of the [turbulence profile](TurbulenceProfile) depends on the coverage of the star
+    /// pupil footprint by the laser guide star footprints, the tip-tilt anisoplanatism depends on the offset
+    /// of the star from the tip-tilt star and the fitting error is set by the actuator pitch.
+    /// The servo-lag error and the tip-tilt noise error are modeled if the [control loop](SeeingBuilder::control_loop)
+    /// and the [wavefront sensor](SeeingBuilder::wavefront_sensor) are set.
+    pub fn laser_tomography_adaptive_optics(self, ltao: LaserTomographyAdaptiveOptics) -> Self {
+        self.with_adaptive_optics(AdaptiveOpticsCorrection::tomography(ltao))
+    }
+    /// Corrects the seeing with a [Multi-Conjugate Adaptive Optics](MultiConjugateAdaptiveOptics) system
+    ///
+    /// The fields with MCAO correction are built with the [Mcao](crate::Mcao) observing mode.
+    /// The servo-lag and noise errors are modeled if the [control loop](SeeingBuilder::control_loop)
+    /// and the [wavefront sensor](SeeingBuilder::wavefront_sensor) are set.
+    pub fn multi_conjugate_adaptive_optics(self, mcao: MultiConjugateAdaptiveOptics) -> Self {
+        self.with_adaptive_optics(AdaptiveOpticsCorrection::mcao(mcao))
+    }
+    /// Corrects the seeing with a Natural Guide Star Adaptive Optics system
+    ///
+    /// The fitting and anisoplanatism errors of the NGAO system are modeled,
+    /// the servo-lag and noise errors are modeled if the [control loop](SeeingBuilder::control_loop)
+    /// and the [wavefront sensor](SeeingBuilder::wavefront_sensor) are set.
+    /// The fitting error is set according to the Strehl ratio, see [SeeingBuilder::ngao_actuators]
+    /// to set it according to the deformable mirror actuators instead.
+    /// The anisoplanatism error is set only if a guide star is given.
+    ///
+    /// # Panics
+    /// Panics if the Strehl ratio is not in ]0,1[, see [SeeingBuilder::try_ngao]
+    pub fn ngao(self, strehl_ratio: f64, guide_star: Option<Star>) -> Self {
+        self.try_ngao(strehl_ratio, guide_star)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+    /// Corrects the seeing with a Natural Guide Star Adaptive Optics system
+    ///
+    /// Returns an [error](crate::Error) if the Strehl ratio is not in ]0,1[
+    pub fn try_ngao(self, strehl_ratio: f64, guide_star: Option<Star>) -> Result<Self> {
+        if strehl_ratio <= 0f64 || strehl_ratio >= 1f64 {
+            return Err(Error::StrehlRatio(strehl_ratio));
+        }
+        Ok(self.with_adaptive_optics(AdaptiveOpticsCorrection::ngao(strehl_ratio, guide_star)))
+    }
     /// Corrects the seeing with a Laser Guide Star Adaptive Optics system
     ///
-    /// Only the fitting and anisoplanatism errors of the LTAO system are modeled.
-    /// The fitting error is set according to the Strehl ratio (≥ 0.5).
+    /// The fitting and anisoplanatism errors of the LTAO system are modeled,
+    /// the servo-lag error is modeled if the [control loop](SeeingBuilder::control_loop) is set.
+    /// The fitting error is set according to the Strehl ratio, see [SeeingBuilder::ltao_actuators]
+    /// to set it according to the deformable mirror actuators instead.
     /// The anisoplanatism error is set only outside the Laser guide stars radius.
+    ///
+    /// # Panics
+    /// Panics if the Strehl ratio is not in ]0,1[, see [SeeingBuilder::try_ltao]
     pub fn ltao(self, strehl_ratio: f64, laser_guide_star_radius: SkyAngle<f64>) -> Self {
-        if strehl_ratio < 0.5 {
-            panic!("Strel ratio must be at least 0.5 or higher");
+        self.try_ltao(strehl_ratio, laser_guide_star_radius)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+    /// Corrects the seeing with a Laser Guide Star Adaptive Optics system
+    ///
+    /// Returns an [error](crate::Error) if the Strehl ratio is not in ]0,1[
+    pub fn try_ltao(
+        self,
+        strehl_ratio: f64,
+        laser_guide_star_radius: SkyAngle<f64>,
+    ) -> Result<Self> {
+        if strehl_ratio <= 0f64 || strehl_ratio >= 1f64 {
+            return Err(Error::StrehlRatio(strehl_ratio));
         }
-        Self {
-            adaptive_optics: Some(AdaptiveOpticsCorrection::ltao(
-                strehl_ratio,
+        Ok(self.with_adaptive_optics(AdaptiveOpticsCorrection::ltao(
+            strehl_ratio,
+            laser_guide_star_radius,
+        )))
+    }
+    /// Corrects the seeing with a Natural Guide Star Adaptive Optics system
+    /// with the fitting error set by the deformable mirror [actuators](Actuators)
+    ///
+    /// The spatial frequencies of the turbulence are corrected up to the cutoff frequency `1/(2p)`,
+    /// `p` being the actuator pitch, so the Strehl ratio follows from the seeing and the wavelength.
+    /// The other errors are modeled as with [SeeingBuilder::ngao].
+    ///
+    /// # Panics
+    /// Panics if the actuator pitch or count is not positive, see [SeeingBuilder::try_ngao_actuators]
+    pub fn ngao_actuators(self, actuators: Actuators, guide_star: Option<Star>) -> Self {
+        self.try_ngao_actuators(actuators, guide_star)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+    /// Corrects the seeing with a Natural Guide Star Adaptive Optics system
+    /// with the fitting error set by the deformable mirror [actuators](Actuators)
+    ///
+    /// Returns an [error](crate::Error) if the actuator pitch or count is not positive
+    pub fn try_ngao_actuators(
+        self,
+        actuators: Actuators,
+        guide_star: Option<Star>,
+    ) -> Result<Self> {
+        Ok(
+            self.with_adaptive_optics(AdaptiveOpticsCorrection::ngao_actuators(
+                actuators.check()?,
+                guide_star,
+            )),
+        )
+    }
+    /// Corrects the seeing with a Laser Guide Star Adaptive Optics system
+    /// with the fitting error set by the deformable mirror [actuators](Actuators)
+    ///
+    /// See [SeeingBuilder::ngao_actuators] and [SeeingBuilder::ltao]
+    ///
+    /// # Panics
+    /// Panics if the actuator pitch or count is not positive, see [SeeingBuilder::try_ltao_actuators]
+    pub fn ltao_actuators(
+        self,
+        actuators: Actuators,
+        laser_guide_star_radius: SkyAngle<f64>,
+    ) -> Self {
+        self.try_ltao_actuators(actuators, laser_guide_star_radius)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+    /// Corrects the seeing with a Laser Guide Star Adaptive Optics system
+    /// with the fitting error set by the deformable mirror [actuators](Actuators)
+    ///
+    /// Returns an [error](crate::Error) if the actuator pitch or count is not positive
+    pub fn try_ltao_actuators(
+        self,
+        actuators: Actuators,
+        laser_guide_star_radius: SkyAngle<f64>,
+    ) -> Result<Self> {
+        Ok(
+            self.with_adaptive_optics(AdaptiveOpticsCorrection::ltao_actuators(
+                actuators.check()?,
                 laser_guide_star_radius,
             )),
-            ..self
-        }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parameters() {
+        let seeing = SeeingBuilder::new(16e-2);
+        let band = Photometry::custom("500nm", 500e-9, 100e-9, 1e10);
+        assert!((seeing.kolmogorov_fwhm(&band).to_degrees() * 3600. - 0.629).abs() < 1e-3);
+        assert!(seeing.von_karman_fwhm(&band) < seeing.kolmogorov_fwhm(&band));
+        let seeing = seeing.turbulence_profile(TurbulenceProfile::single_layer(10e3));
+        assert!((seeing.isoplanatic_angle(&band) - 0.314 * 16e-2 / 10e3).abs() < 1e-12);
+        assert!(seeing.coherence_time(&band).is_none());
+        let seeing =
+            seeing.turbulence_profile(TurbulenceProfile::single_layer(10e3).bufton_wind(0.));
+        assert!(seeing.coherence_time(&band).unwrap() > 0.);
+        assert!(seeing.try_glao(1.).is_err());
     }
 }
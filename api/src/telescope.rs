@@ -1,11 +1,22 @@
 use std::{fmt::Display, ops::Deref};
 
 mod generic;
-pub use generic::{Telescope, TelescopeBuilder};
+pub use generic::{Spider, Telescope, TelescopeBuilder};
+mod elt;
+pub use elt::Elt;
 mod gmt;
-pub use gmt::Gmt;
+pub use gmt::{Gmt, GmtBuilder, GmtSegment};
+mod hexagonal;
 mod jwst;
 pub use jwst::{Hexagon, Jwst};
+mod keck;
+pub use keck::Keck;
+mod mask;
+pub use mask::PupilMask;
+mod rotated;
+pub use rotated::Rotated;
+mod tmt;
+pub use tmt::Tmt;
 
 use crate::Observer;
 
@@ -23,11 +34,22 @@ impl Deref for Hst {
 }
 impl Hst {
     /// Creates a Hubble Space Telescope object
+    ///
+    /// The secondary mirror is held by 4 spider vanes, 2.64cm wide, at 45 degrees
     pub fn new() -> Self {
-        Self(Telescope::new(2.4).obscuration(0.3).build())
+        Self(
+            Telescope::new(2.4)
+                .obscuration(0.6)
+                .spider(Spider::new(4, 2.64e-2).angle(45f64.to_radians()))
+                .build(),
+        )
     }
 }
 impl Observer for Hst {
+    fn name(&self) -> String {
+        String::from("HST")
+    }
+
     fn diameter(&self) -> f64 {
         self.0.diameter()
     }
@@ -40,9 +62,10 @@ impl Display for Hst {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "HST: {}m diameter ({}m diameter obscuration), {:.3}m^2 collection area",
+            "HST: {}m diameter ({}m diameter obscuration, {} spider vanes), {:.3}m^2 collection area",
             self.diameter,
             self.obscuration.unwrap(),
+            self.spider.map_or(0, |spider| spider.n_vane()),
             self.area()
         )
     }
@@ -56,3 +79,155 @@ impl Serialize for Hst {
         "HST".serialize(serializer)
     }
 }
+
+#[derive(Debug, Clone, Copy)]
+/// ESO Very Large Telescope unit telescope
+///
+/// The secondary mirror is held by 4 spider vanes, 5cm wide
+pub struct Vlt(Telescope);
+impl Deref for Vlt {
+    type Target = Telescope;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+impl Vlt {
+    /// Creates a Very Large Telescope unit telescope object
+    pub fn new() -> Self {
+        Self(
+            Telescope::new(8.2)
+                .obscuration(1.116)
+                .spider(Spider::new(4, 5e-2))
+                .build(),
+        )
+    }
+}
+impl Default for Vlt {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Observer for Vlt {
+    fn name(&self) -> String {
+        String::from("VLT")
+    }
+
+    fn diameter(&self) -> f64 {
+        self.0.diameter()
+    }
+
+    fn inside_pupil(&self, x: f64, y: f64) -> bool {
+        self.0.inside_pupil(x, y)
+    }
+}
+impl Display for Vlt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "VLT: {}m diameter ({} spider vanes), {:.3}m^2 collection area",
+            self.diameter,
+            self.spider.map_or(0, |spider| spider.n_vane()),
+            self.area()
+        )
+    }
+}
+impl Serialize for Vlt {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        "VLT".serialize(serializer)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Subaru telescope
+///
+/// The secondary mirror is held by 4 spider vanes, 22cm wide, at 45 degrees
+pub struct Subaru(Telescope);
+impl Deref for Subaru {
+    type Target = Telescope;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+impl Subaru {
+    /// Creates a Subaru telescope object
+    pub fn new() -> Self {
+        Self(
+            Telescope::new(8.2)
+                .obscuration(2.3)
+                .spider(Spider::new(4, 0.22).angle(45f64.to_radians()))
+                .build(),
+        )
+    }
+}
+impl Default for Subaru {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Observer for Subaru {
+    fn name(&self) -> String {
+        String::from("Subaru")
+    }
+
+    fn diameter(&self) -> f64 {
+        self.0.diameter()
+    }
+
+    fn inside_pupil(&self, x: f64, y: f64) -> bool {
+        self.0.inside_pupil(x, y)
+    }
+}
+impl Display for Subaru {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Subaru: {}m diameter ({} spider vanes), {:.3}m^2 collection area",
+            self.diameter,
+            self.spider.map_or(0, |spider| spider.n_vane()),
+            self.area()
+        )
+    }
+}
+impl Serialize for Subaru {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        "Subaru".serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Builder, DiffractionLimited, Field, FieldBuilder};
+
+    fn intensity<T: Observer + Sync + Send>(tel: T) -> Vec<f64> {
+        let mut field: Field<T, DiffractionLimited> = FieldBuilder::new(tel)
+            .field_of_view(11)
+            .photometry("K")
+            .build();
+        field.intensity(None)
+    }
+
+    #[test]
+    fn telescopes() {
+        for psf in [
+            intensity(Elt::new()),
+            intensity(Tmt::new()),
+            intensity(Keck::new()),
+            intensity(Vlt::new()),
+            intensity(Subaru::new()),
+        ] {
+            assert_eq!(psf.len(), 11 * 11);
+            // the star is at the center of the field
+            let peak = psf.iter().fold(0f64, |a, &i| a.max(i));
+            assert_eq!(psf[psf.len() / 2], peak);
+        }
+    }
+}
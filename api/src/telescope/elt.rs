@@ -0,0 +1,67 @@
+use std::fmt::Display;
+
+use super::{hexagonal::HexagonalSegments, Spider};
+use crate::Observer;
+
+#[derive(Debug, Clone)]
+/// Extremely Large Telescope
+///
+/// The primary mirror is made of 798 hexagonal segments, 1.4m corner-to-corner,
+/// and the secondary mirror is held by 6 spider vanes, 50cm wide
+pub struct Elt {
+    segments: HexagonalSegments,
+    spider: Spider,
+}
+impl Elt {
+    /// Creates an Extremely Large Telescope object
+    pub fn new() -> Self {
+        Self {
+            segments: HexagonalSegments::new(1.4 * 3f64.sqrt() * 0.5, 6.2, 19.),
+            spider: Spider::new(6, 0.5),
+        }
+    }
+    /// Returns the number of segments
+    pub fn n_segment(&self) -> usize {
+        self.segments.len()
+    }
+}
+impl Default for Elt {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Display for Elt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "ELT: {:.2}m diameter ({} segments, {} spider vanes), {:.3}m^2 collection area",
+            self.diameter(),
+            self.segments.len(),
+            self.spider.n_vane(),
+            self.area()
+        )
+    }
+}
+impl Observer for Elt {
+    fn name(&self) -> String {
+        String::from("ELT")
+    }
+
+    fn diameter(&self) -> f64 {
+        self.segments.diameter()
+    }
+
+    fn inside_pupil(&self, x: f64, y: f64) -> bool {
+        self.segments.inside_pupil(x, y) && !self.spider.inside_vane(x, y)
+    }
+}
+
+use serde::ser::{Serialize, Serializer};
+impl Serialize for Elt {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        "ELT".serialize(serializer)
+    }
+}
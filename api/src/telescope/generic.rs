@@ -4,18 +4,80 @@ use serde::Serialize;
 
 use crate::Observer;
 
+/// Spider vanes
+///
+/// The vanes are evenly distributed around the pupil center,
+/// starting from the angle offset (counter-clockwise from the x-axis).
+/// Each vane is a straight bar of a given width that may be offset
+/// perpendicularly from the pupil center.
+///
+/// # Example
+/// ```
+/// use eyepiece::Spider;
+/// let spider = Spider::new(4, 0.05).angle(45f64.to_radians());
+/// ```
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Spider {
+    n_vane: usize,
+    width: f64,
+    angle: f64,
+    offset: f64,
+}
+impl Spider {
+    /// Creates `n_vane` vanes of the given `width` in meters
+    pub fn new(n_vane: usize, width: f64) -> Self {
+        Self {
+            n_vane,
+            width,
+            angle: 0f64,
+            offset: 0f64,
+        }
+    }
+    /// Sets the angle of the first vane in radians
+    pub fn angle(self, angle: f64) -> Self {
+        Self { angle, ..self }
+    }
+    /// Sets the offset of the vanes from the pupil center in meters
+    pub fn offset(self, offset: f64) -> Self {
+        Self { offset, ..self }
+    }
+    /// Returns the number of vanes
+    pub fn n_vane(&self) -> usize {
+        self.n_vane
+    }
+    /// Returns the width of the vanes in meters
+    pub fn width(&self) -> f64 {
+        self.width
+    }
+    /// Checks if a point is hidden by one of the vanes
+    pub fn inside_vane(&self, x: f64, y: f64) -> bool {
+        let half_width = 0.5 * self.width;
+        (0..self.n_vane).any(|k| {
+            let o = self.angle + 2. * std::f64::consts::PI * k as f64 / self.n_vane as f64;
+            let (so, co) = o.sin_cos();
+            let u = x * co + y * so;
+            let v = -x * so + y * co;
+            u >= 0f64 && (v - self.offset).abs() <= half_width
+        })
+    }
+}
+
 /// Generic circular telescope
 ///
 /// # Example
 /// ```
-/// use eyepiece::Telescope;
-/// let tel = Telescope::new(8.).build();
+/// use eyepiece::{Spider, Telescope};
+/// let tel = Telescope::new(8.)
+///     .obscuration(0.6)
+///     .spider(Spider::new(4, 5e-2))
+///     .build();
 /// ```
 #[derive(Debug, Clone, Copy, Serialize)]
 pub struct Telescope {
     /// Primary mirror diameter D (Nyquist sampling criteria: λ/2D)
     pub diameter: f64,
     pub obscuration: Option<f64>,
+    pub spider: Option<Spider>,
 }
 
 impl Default for Telescope {
@@ -23,6 +85,7 @@ impl Default for Telescope {
         Self {
             diameter: 1f64,
             obscuration: Default::default(),
+            spider: Default::default(),
         }
     }
 }
@@ -35,15 +98,24 @@ impl Display for Telescope {
                 self.diameter,
                 obscuration,
                 self.area()
-            )
+            )?;
         } else {
             write!(
                 f,
                 "Telescope: {}m diameter, {:.3}m^2 collection area",
                 self.diameter,
                 self.area()
-            )
+            )?;
+        }
+        if let Some(spider) = self.spider {
+            write!(
+                f,
+                ", {} spider vanes of {:.1}cm",
+                spider.n_vane,
+                spider.width * 1e2
+            )?;
         }
+        Ok(())
     }
 }
 
@@ -51,6 +123,7 @@ impl Display for Telescope {
 pub struct TelescopeBuilder {
     diameter: f64,
     obscuration: Option<f64>,
+    spider: Option<Spider>,
 }
 
 impl Telescope {
@@ -59,6 +132,7 @@ impl Telescope {
         TelescopeBuilder {
             diameter,
             obscuration: None,
+            spider: None,
         }
     }
 }
@@ -69,12 +143,17 @@ impl TelescopeBuilder {
         self.obscuration = Some(obscuration);
         self
     }
+    /// Sets the [spider](Spider) vanes
+    pub fn spider(mut self, spider: Spider) -> Self {
+        self.spider = Some(spider);
+        self
+    }
     /// Build the telescope
     pub fn build(self) -> Telescope {
         Telescope {
             diameter: self.diameter,
             obscuration: self.obscuration,
-            ..Default::default()
+            spider: self.spider,
         }
     }
 }
@@ -86,8 +165,52 @@ impl Observer for Telescope {
 
     fn inside_pupil(&self, x: f64, y: f64) -> bool {
         let r_outer = self.diameter * 0.5;
-        let r_inner = self.obscuration.unwrap_or_default();
+        let r_inner = self.obscuration.unwrap_or_default() * 0.5;
         let r = x.hypot(y);
-        r >= r_inner && r <= r_outer
+        r >= r_inner
+            && r <= r_outer
+            && !self
+                .spider
+                .as_ref()
+                .is_some_and(|spider| spider.inside_vane(x, y))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spider() {
+        let spider = Spider::new(4, 0.1);
+        assert!(spider.inside_vane(1., 0.));
+        assert!(spider.inside_vane(0., -1.));
+        assert!(!spider.inside_vane(1., 1.));
+        assert!(!Spider::new(3, 0.1).inside_vane(-1., 0.));
+        assert!(spider.offset(0.2).inside_vane(1., 0.2));
+        let tel = Telescope::new(8.).build();
+        let tel_spider = Telescope::new(8.).spider(Spider::new(4, 0.12)).build();
+        let vanes_area = tel.area() - tel_spider.area();
+        assert!((vanes_area / (4. * 4. * 0.12) - 1.).abs() < 0.1);
+    }
+
+    #[test]
+    fn supersampling() {
+        let tel = Telescope::new(8.).build();
+        let area = std::f64::consts::PI * 16.;
+        let error = |n| (tel.supersampled_area(n) / area - 1.).abs();
+        assert!(error(4) < 1e-4);
+        assert!(error(4) < error(1));
+        let pupil = tel.supersampled_pupil(None, 4);
+        assert!(pupil.iter().any(|p| p.re > 0. && p.re < 1.));
+    }
+
+    #[test]
+    fn obscuration() {
+        let tel = Telescope::new(8.).obscuration(2.).build();
+        assert!(!tel.inside_pupil(0.9, 0.));
+        assert!(tel.inside_pupil(1.1, 0.));
+        let area = std::f64::consts::PI * (16. - 1.);
+        assert!((tel.supersampled_area(4) / area - 1.).abs() < 1e-3);
     }
 }
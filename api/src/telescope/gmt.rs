@@ -1,17 +1,123 @@
-use std::fmt::Display;
+use std::{fmt::Display, sync::OnceLock};
 
-use crate::Observer;
+use crate::{Error, Observer, Result};
 use geotrans::{Conic, Segment, SegmentTrait, Transform, M1};
+use skyangle::Conversion;
+
+/// GMT M1 segment
+///
+/// Segment wavefront piston and tip-tilt, reflectivity and whether the segment is missing
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct GmtSegment {
+    /// wavefront piston [nm]
+    pub piston: f64,
+    /// wavefront tip and tilt [mas]
+    pub tip_tilt: (f64, f64),
+    /// intensity reflectivity
+    pub reflectivity: f64,
+    /// missing segment flag
+    pub missing: bool,
+}
+impl Default for GmtSegment {
+    fn default() -> Self {
+        Self {
+            piston: 0f64,
+            tip_tilt: (0f64, 0f64),
+            reflectivity: 1f64,
+            missing: false,
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 /// Giant Magellan Telescope
 ///
+/// The segments are numbered from 1 to 7, the center segment being #7.
+/// The segment piston and tip-tilt are applied as a pupil phase in every observing mode.
+///
 /// <img src="https://raw.githubusercontent.com/rconan/eyepiece/main/examples/gmt/telescope_pupil.png" width="20%" alt="GMT pupil">
-pub struct Gmt;
+///
+/// # Example
+/// ```
+/// use eyepiece::{Gmt, Observer};
+///
+/// let gmt = Gmt::builder()
+///     .piston(1, 50.)
+///     .tip_tilt(3, (2., -1.))
+///     .reflectivity(5, 0.8)
+///     .drop_segment(7)
+///     .build();
+/// assert!(!gmt.inside_pupil(0., 0.));
+/// ```
+pub struct Gmt {
+    segments: [GmtSegment; 7],
+    /// segment centers in the pupil [m]
+    centers: [(f64, f64); 7],
+    /// optical path difference map, computed on first use
+    opd_map: OnceLock<Option<Vec<f64>>>,
+}
+impl Default for Gmt {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
 impl Gmt {
     /// Creates a Giant Magellan Telescope object
     pub fn new() -> Self {
-        Self
+        Default::default()
+    }
+    /// Creates a Giant Magellan Telescope builder
+    pub fn builder() -> GmtBuilder {
+        GmtBuilder {
+            segments: [GmtSegment::default(); 7],
+            error: None,
+        }
+    }
+    /// Returns the [segments](GmtSegment)
+    pub fn segments(&self) -> &[GmtSegment; 7] {
+        &self.segments
+    }
+    /// Returns the segment number (1 to 7) at a given point of the pupil, missing segments included
+    pub fn segment(&self, x: f64, y: f64) -> Option<usize> {
+        let m1 = Conic::m1();
+        let h = m1.height(x.hypot(y));
+        let pt = [x, y, 3.9 + h];
+        let r_outer = 8.365 * 0.5;
+        let r_inner = 3.6 * 0.5;
+        for i in 1..=7 {
+            let xyz = pt.fro(Segment::<M1>::new(i)).unwrap();
+            let r = xyz[0].hypot(xyz[1]);
+            if i == 7 && r < r_inner {
+                return None;
+            }
+            if r <= r_outer {
+                return Some(i as usize);
+            }
+        }
+        None
+    }
+    /// Returns the segment piston RMS in nm, missing segments excluded
+    pub fn piston_rms(&self) -> f64 {
+        let pistons: Vec<_> = self
+            .segments
+            .iter()
+            .filter(|s| !s.missing)
+            .map(|s| s.piston)
+            .collect();
+        let n = pistons.len().max(1) as f64;
+        let mean = pistons.iter().sum::<f64>() / n;
+        (pistons.iter().map(|p| (p - mean).powi(2)).sum::<f64>() / n).sqrt()
+    }
+    /// Checks if all the segments are free of piston and tip-tilt
+    fn is_phased(&self) -> bool {
+        self.segments
+            .iter()
+            .all(|segment| segment.piston == 0f64 && segment.tip_tilt == (0f64, 0f64))
+    }
+    fn is_perfect(&self) -> bool {
+        self.segments
+            .iter()
+            .all(|segment| *segment == GmtSegment::default())
     }
 }
 impl Display for Gmt {
@@ -21,40 +127,225 @@ impl Display for Gmt {
             "GMT: {}m diameter, {:.3}m^2 collection area",
             self.diameter(),
             self.area()
-        )
+        )?;
+        if !self.is_perfect() {
+            write!(
+                f,
+                " ({} segments, {:.1}nm RMS segment piston)",
+                self.segments.iter().filter(|s| !s.missing).count(),
+                self.piston_rms()
+            )?;
+        }
+        Ok(())
     }
 }
 impl Observer for Gmt {
+    fn name(&self) -> String {
+        String::from("GMT")
+    }
+
     fn diameter(&self) -> f64 {
         25.5
     }
 
     fn inside_pupil(&self, x: f64, y: f64) -> bool {
-        let m1 = Conic::m1();
-        let h = m1.height(x.hypot(y));
-        let pt = [x, y, 3.9 + h];
-        let r_outer = 8.365 * 0.5;
-        let r_inner = 3.6 * 0.5;
-        for i in 1..=7 {
-            let xyz = pt.fro(Segment::<M1>::new(i)).unwrap();
-            let r = xyz[0].hypot(xyz[1]);
-            if i == 7 && r < r_inner {
-                return false;
-            }
-            if r <= r_outer {
-                return true;
+        self.segment(x, y)
+            .is_some_and(|i| !self.segments[i - 1].missing)
+    }
+
+    fn transmission(&self, x: f64, y: f64) -> f64 {
+        match self.segment(x, y).map(|i| self.segments[i - 1]) {
+            Some(segment) if !segment.missing => segment.reflectivity.sqrt(),
+            _ => 0f64,
+        }
+    }
+
+    fn optical_path_difference(&self, x: f64, y: f64) -> f64 {
+        self.segment(x, y).map_or(0f64, |i| {
+            let GmtSegment {
+                piston,
+                tip_tilt: (tip, tilt),
+                ..
+            } = self.segments[i - 1];
+            let (xc, yc) = self.centers[i - 1];
+            piston * 1e-9 + tip.from_mas() * (x - xc) + tilt.from_mas() * (y - yc)
+        })
+    }
+
+    fn optical_path_difference_map(&self) -> Option<Vec<f64>> {
+        if self.is_phased() {
+            return None;
+        }
+        self.opd_map
+            .get_or_init(|| crate::optical_path_difference_map(self))
+            .clone()
+    }
+}
+
+/// [Gmt] builder
+///
+/// The segments are numbered from 1 to 7, the settings of a segment with any other number
+/// result in an [error](crate::Error) when the telescope is [built](GmtBuilder::try_build)
+pub struct GmtBuilder {
+    segments: [GmtSegment; 7],
+    error: Option<Error>,
+}
+impl GmtBuilder {
+    fn segment_mut(&mut self, sid: usize) -> Result<&mut GmtSegment> {
+        if (1..=7).contains(&sid) {
+            Ok(&mut self.segments[sid - 1])
+        } else {
+            Err(Error::GmtSegment(sid))
+        }
+    }
+    /// Applies `f` to segment `sid`, keeping the first invalid segment number error
+    fn with_segment<F: FnOnce(&mut GmtSegment)>(mut self, sid: usize, f: F) -> Self {
+        match self.segment_mut(sid) {
+            Ok(segment) => f(segment),
+            Err(e) => {
+                self.error.get_or_insert(e);
             }
         }
-        false
+        self
+    }
+    /// Sets the wavefront piston in nm of segment `sid`
+    pub fn piston(self, sid: usize, piston: f64) -> Self {
+        self.with_segment(sid, |segment| segment.piston = piston)
+    }
+    /// Sets the wavefront piston in nm of all the segments
+    pub fn pistons(mut self, pistons: [f64; 7]) -> Self {
+        self.segments
+            .iter_mut()
+            .zip(pistons)
+            .for_each(|(segment, piston)| segment.piston = piston);
+        self
+    }
+    /// Sets the wavefront tip and tilt in mas of segment `sid`
+    pub fn tip_tilt(self, sid: usize, tip_tilt: (f64, f64)) -> Self {
+        self.with_segment(sid, |segment| segment.tip_tilt = tip_tilt)
+    }
+    /// Sets the intensity reflectivity of segment `sid`
+    pub fn reflectivity(self, sid: usize, reflectivity: f64) -> Self {
+        self.with_segment(sid, |segment| segment.reflectivity = reflectivity)
+    }
+    /// Removes segment `sid` from the pupil
+    pub fn drop_segment(self, sid: usize) -> Self {
+        self.with_segment(sid, |segment| segment.missing = true)
+    }
+    /// Builds the telescope
+    ///
+    /// # Panics
+    /// Panics if a segment number is not in the range [1,7], see [GmtBuilder::try_build]
+    pub fn build(self) -> Gmt {
+        self.try_build().unwrap_or_else(|e| panic!("{e}"))
+    }
+    /// Builds the telescope
+    ///
+    /// Returns an [error](crate::Error) if a segment number is not in the range [1,7]
+    pub fn try_build(self) -> Result<Gmt> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        let mut centers = [(0f64, 0f64); 7];
+        for (i, center) in centers.iter_mut().enumerate() {
+            let xyz = [0f64; 3].to(Segment::<M1>::new(i as i32 + 1)).unwrap();
+            *center = (xyz[0], xyz[1]);
+        }
+        Ok(Gmt {
+            segments: self.segments,
+            centers,
+            opd_map: OnceLock::new(),
+        })
     }
 }
 
-use serde::ser::{Serialize, Serializer};
+use serde::ser::{Serialize, SerializeStruct, Serializer};
 impl Serialize for Gmt {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        "GMT".serialize(serializer)
+        if self.is_perfect() {
+            "GMT".serialize(serializer)
+        } else {
+            let mut s = serializer.serialize_struct("GMT", 1)?;
+            s.serialize_field("segments", &self.segments)?;
+            s.end()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        AdaptiveOptics, Builder, DiffractionLimited, Field, FieldBuilder, SeeingBuilder,
+        SeeingLimited,
+    };
+
+    #[test]
+    fn segments() {
+        let gmt = Gmt::new();
+        assert_eq!(gmt.segment(0., 0.), None);
+        assert_eq!(gmt.segment(0., 3.), Some(7));
+        assert_eq!(gmt.segment(0., 8.5), Some(1));
+        let (xc, yc) = gmt.centers[0];
+        assert!(xc.abs() < 1e-9 && yc > 8.);
+        let gmt = Gmt::builder()
+            .piston(1, 100.)
+            .reflectivity(2, 0.25)
+            .drop_segment(7)
+            .build();
+        assert!(!gmt.inside_pupil(0., 3.));
+        assert!((gmt.optical_path_difference(0., 8.5) - 100e-9).abs() < 1e-15);
+        let (x, y) = gmt.centers[1];
+        assert!((gmt.transmission(x, y) - 0.5).abs() < 1e-12);
+        assert!(gmt.optical_path_difference_map().is_some());
+        assert!(Gmt::builder()
+            .drop_segment(7)
+            .build()
+            .optical_path_difference_map()
+            .is_none());
+        assert!(Gmt::builder().piston(8, 100.).try_build().is_err());
+    }
+
+    #[test]
+    fn phasing() {
+        let unphased = || {
+            Gmt::builder()
+                .piston(1, 400.)
+                .tip_tilt(2, (20., -10.))
+                .build()
+        };
+        assert!(unphased().optical_path_difference_map().is_some());
+        let peak = |intensity: Vec<f64>| intensity.into_iter().fold(0f64, f64::max);
+        let gmt = |gmt: Gmt| FieldBuilder::new(gmt).field_of_view(21).photometry("K");
+        // diffraction limited
+        let intensity = |tel: Gmt| {
+            let mut field: Field<Gmt, DiffractionLimited> = gmt(tel).build();
+            field.intensity(None)
+        };
+        let (phased, unphased_psf) = (intensity(Gmt::new()), intensity(unphased()));
+        assert!(peak(unphased_psf) < 0.9 * peak(phased));
+        // seeing limited
+        let intensity = |tel: Gmt| {
+            let mut field: Field<Gmt, SeeingLimited> =
+                gmt(tel).seeing_limited(SeeingBuilder::new(16e-2)).build();
+            field.intensity(None)
+        };
+        let (phased, unphased_psf) = (intensity(Gmt::new()), intensity(unphased()));
+        assert!(phased
+            .iter()
+            .zip(&unphased_psf)
+            .any(|(p, u)| (p - u).abs() > 1e-3 * p));
+        // adaptive optics
+        let intensity = |tel: Gmt| {
+            let mut field: Field<Gmt, AdaptiveOptics> = gmt(tel)
+                .seeing_limited(SeeingBuilder::new(16e-2).ngao(0.5, None))
+                .build();
+            field.intensity(None)
+        };
+        let (phased, unphased_psf) = (intensity(Gmt::new()), intensity(unphased()));
+        assert!(peak(unphased_psf) < 0.9 * peak(phased));
     }
 }
@@ -0,0 +1,107 @@
+use std::collections::HashSet;
+
+/// Hexagonal segments of a segmented primary mirror
+///
+/// The segments are laid out on a regular hexagonal lattice with the segment flats
+/// parallel to the x axis, like the [Hexagon](super::Hexagon).
+/// The segments are stored by their axial lattice coordinates.
+#[derive(Debug, Clone)]
+pub(crate) struct HexagonalSegments {
+    flat_to_flat: f64,
+    segments: HashSet<(i32, i32)>,
+}
+impl HexagonalSegments {
+    /// Creates the segments with their centers at a distance to the origin within `[r_inner,r_outer]`
+    pub fn new(flat_to_flat: f64, r_inner: f64, r_outer: f64) -> Self {
+        let mut this = Self {
+            flat_to_flat,
+            segments: HashSet::new(),
+        };
+        let n = 2 * (r_outer / flat_to_flat).ceil() as i32;
+        this.segments = (-n..=n)
+            .flat_map(|q| (-n..=n).map(move |r| (q, r)))
+            .filter(|&(q, r)| {
+                let (x, y) = this.center(q, r);
+                let d = x.hypot(y);
+                d >= r_inner && d <= r_outer
+            })
+            .collect();
+        this
+    }
+    /// Returns the number of segments
+    pub fn len(&self) -> usize {
+        self.segments.len()
+    }
+    /// Returns the segment centers
+    pub fn centers(&self) -> impl Iterator<Item = (f64, f64)> + '_ {
+        self.segments.iter().map(|&(q, r)| self.center(q, r))
+    }
+    /// Returns the diameter of the circle enclosing all the segments
+    pub fn diameter(&self) -> f64 {
+        let vertex = self.flat_to_flat / 3f64.sqrt();
+        2. * self
+            .centers()
+            .map(|(x, y)| x.hypot(y) + vertex)
+            .fold(0f64, f64::max)
+    }
+    /// Checks if a point is inside one of the segments
+    pub fn inside_pupil(&self, x: f64, y: f64) -> bool {
+        self.segments.contains(&self.cell(x, y))
+    }
+    /// Returns the center of the lattice cell with axial coordinates `(q,r)`
+    fn center(&self, q: i32, r: i32) -> (f64, f64) {
+        let s = self.flat_to_flat / 3f64.sqrt();
+        (
+            1.5 * s * q as f64,
+            self.flat_to_flat * (r as f64 + 0.5 * q as f64),
+        )
+    }
+    /// Returns the axial coordinates of the lattice cell that contains the point `(x,y)`
+    fn cell(&self, x: f64, y: f64) -> (i32, i32) {
+        let s = self.flat_to_flat / 3f64.sqrt();
+        // fractional cube coordinates
+        let q = x / (1.5 * s);
+        let r = y / self.flat_to_flat - 0.5 * q;
+        let z = -q - r;
+        let (mut rq, mut rr, rz) = (q.round(), r.round(), z.round());
+        let (dq, dr, dz) = ((rq - q).abs(), (rr - r).abs(), (rz - z).abs());
+        if dq > dr && dq > dz {
+            rq = -rr - rz;
+        } else if dr > dz {
+            rr = -rq - rz;
+        }
+        (rq as i32, rr as i32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Elt, Hexagon, Keck, Observer, Tmt};
+
+    #[test]
+    fn lattice() {
+        let f2f = 1.;
+        let segments = HexagonalSegments::new(f2f, 0.5, 3.1 * f2f);
+        assert_eq!(segments.len(), 36);
+        assert!(!segments.inside_pupil(0., 0.));
+        // the lattice cells match the hexagons
+        let hexagons: Vec<_> = segments
+            .centers()
+            .map(|center| Hexagon::new(center, f2f))
+            .collect();
+        for i in 0..50 {
+            for j in 0..50 {
+                let (x, y) = (-3.513 + 0.141 * j as f64, -3.487 + 0.139 * i as f64);
+                assert_eq!(
+                    segments.inside_pupil(x, y),
+                    hexagons.iter().any(|hex| hex.inside_pupil(x, y)),
+                    "({x},{y})"
+                );
+            }
+        }
+        assert_eq!(Elt::new().n_segment(), 798);
+        assert_eq!(Tmt::new().n_segment(), 492);
+        assert_eq!(Keck::new().len(), 36);
+    }
+}
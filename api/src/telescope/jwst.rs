@@ -23,6 +23,10 @@ impl Hexagon {
 }
 
 impl Observer for Hexagon {
+    fn name(&self) -> String {
+        String::from("Hexagon")
+    }
+
     fn diameter(&self) -> f64 {
         let (cx, cy) = self.origin;
         2. * (cx.hypot(cy) + 0.5 * self.flat_to_flat / 30f64.to_radians().cos())
@@ -94,6 +98,10 @@ impl Jwst {
 }
 
 impl Observer for Jwst {
+    fn name(&self) -> String {
+        String::from("JWST")
+    }
+
     fn diameter(&self) -> f64 {
         6.6
     }
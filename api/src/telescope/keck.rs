@@ -0,0 +1,68 @@
+use std::{fmt::Display, ops::Deref};
+
+use super::{hexagonal::HexagonalSegments, Hexagon};
+use crate::Observer;
+
+/// W. M. Keck Observatory telescope
+///
+/// The primary mirror is made of 36 [hexagonal](Hexagon) segments, 1.8m corner-to-corner
+#[derive(Debug, Clone)]
+pub struct Keck(Vec<Hexagon>);
+impl Deref for Keck {
+    type Target = Vec<Hexagon>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+impl Keck {
+    /// Creates a Keck telescope object
+    pub fn new() -> Self {
+        let f2f = 1.8 * 3f64.sqrt() * 0.5;
+        Self(
+            HexagonalSegments::new(f2f, 0.5 * f2f, 3.1 * f2f)
+                .centers()
+                .map(|center| Hexagon::new(center, f2f))
+                .collect(),
+        )
+    }
+}
+impl Default for Keck {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Display for Keck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Keck: {:.2}m diameter ({} segments), {:.3}m^2 collection area",
+            self.diameter(),
+            self.len(),
+            self.area()
+        )
+    }
+}
+impl Observer for Keck {
+    fn name(&self) -> String {
+        String::from("Keck")
+    }
+
+    fn diameter(&self) -> f64 {
+        self.iter().map(|hex| hex.diameter()).fold(0f64, f64::max)
+    }
+
+    fn inside_pupil(&self, x: f64, y: f64) -> bool {
+        self.iter().any(|hex| hex.inside_pupil(x, y))
+    }
+}
+
+use serde::ser::{Serialize, Serializer};
+impl Serialize for Keck {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        "Keck".serialize(serializer)
+    }
+}
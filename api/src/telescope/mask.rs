@@ -0,0 +1,231 @@
+use std::{fmt::Display, path::Path};
+
+use serde::Serialize;
+
+use crate::{Error, FitsImage, Observer, Result};
+
+/// Pupil mask
+///
+/// A pupil defined by a map of the amplitude transmission sampled on a regular grid
+/// of square pixels.
+/// The map is given in row-major order with the first row at the top of the pupil (+y)
+/// and the first column on the left (-x).
+/// The map is resampled on the [Observer::resolution] grid by averaging the
+/// bilinear interpolation of the map over each pupil sample.
+///
+/// # Example
+/// ```
+/// use eyepiece::{Builder, Field, FieldBuilder, Observer, PupilMask};
+///
+/// let n = 101;
+/// let transmission: Vec<f64> = (0..n * n)
+///     .map(|k| {
+///         let (i, j) = (k / n, k % n);
+///         let r = (i as f64 - 50.).hypot(j as f64 - 50.);
+///         if r <= 50. { 1. } else { 0. }
+///     })
+///     .collect();
+/// let mask = PupilMask::new(transmission, (n, n), 8e-2).unwrap();
+/// assert_eq!(mask.diameter(), 8.08);
+/// let field: Field<PupilMask> = FieldBuilder::new(mask).build();
+/// ```
+#[derive(Debug, Clone, Serialize)]
+pub struct PupilMask {
+    name: String,
+    diameter: f64,
+    pixel_size: f64,
+    resolution: f64,
+    shape: (usize, usize),
+    #[serde(skip)]
+    transmission: Vec<f64>,
+}
+impl PupilMask {
+    /// Creates a new pupil mask from the amplitude transmission map
+    ///
+    /// The map `shape` is given as `(# of rows, # of columns)` and the physical size
+    /// of the map pixels in meters.
+    /// The pupil diameter is set to the largest size of the map.
+    /// Returns an error if the map is empty, if its length does not match the shape
+    /// or if the pixel size is not a positive finite number
+    pub fn new(transmission: Vec<f64>, shape: (usize, usize), pixel_size: f64) -> Result<Self> {
+        let (n_row, n_col) = shape;
+        check_length("pixel size", pixel_size)?;
+        if n_row * n_col != transmission.len() || transmission.is_empty() {
+            return Err(Error::PupilMask(format!(
+                "the transmission map length ({}) does not match the shape {:?}",
+                transmission.len(),
+                shape
+            )));
+        }
+        Ok(Self {
+            name: String::from("PupilMask"),
+            diameter: n_row.max(n_col) as f64 * pixel_size,
+            pixel_size,
+            resolution: 2.5e-2,
+            shape,
+            transmission,
+        })
+    }
+    /// Loads a pupil mask from a grayscale image
+    ///
+    /// The transmission is the pixel luminance normalized to 1
+    pub fn from_image<P: AsRef<Path>>(path: P, pixel_size: f64) -> Result<Self> {
+        let image = image::open(path)?.into_luma16();
+        let shape = (image.height() as usize, image.width() as usize);
+        let transmission = image
+            .into_raw()
+            .into_iter()
+            .map(|l| l as f64 / u16::MAX as f64)
+            .collect();
+        Self::new(transmission, shape, pixel_size)
+    }
+    /// Loads a pupil mask from the primary HDU of a FITS file
+    pub fn from_fits<P: AsRef<Path>>(path: P, pixel_size: f64) -> Result<Self> {
+        let FitsImage { shape, data, .. } = FitsImage::open(path)?;
+        match shape[..] {
+            [n_col, n_row] => Self::new(data, (n_row, n_col), pixel_size),
+            _ => Err(Error::PupilMask(format!(
+                "expected a 2D FITS image, found the shape {shape:?}"
+            ))),
+        }
+    }
+    /// Sets the pupil name
+    pub fn named<S: Into<String>>(self, name: S) -> Self {
+        Self {
+            name: name.into(),
+            ..self
+        }
+    }
+    /// Sets the pupil diameter in meters
+    pub fn pupil_diameter(self, diameter: f64) -> Self {
+        Self { diameter, ..self }
+    }
+    /// Sets the pupil sampling in meters
+    ///
+    /// Returns an error if the sampling is not a positive finite number
+    pub fn pupil_sampling(self, resolution: f64) -> Result<Self> {
+        check_length("pupil sampling", resolution)?;
+        Ok(Self { resolution, ..self })
+    }
+    /// Returns the bilinear interpolation of the transmission map
+    fn interpolate(&self, x: f64, y: f64) -> f64 {
+        let (n_row, n_col) = self.shape;
+        // fractional column and row indices
+        let u = x / self.pixel_size + 0.5 * (n_col - 1) as f64;
+        let v = 0.5 * (n_row - 1) as f64 - y / self.pixel_size;
+        // each map pixel covers its full area
+        if u < -0.5 || v < -0.5 || u >= n_col as f64 - 0.5 || v >= n_row as f64 - 0.5 {
+            return 0f64;
+        }
+        let (u, v) = (
+            u.clamp(0f64, (n_col - 1) as f64),
+            v.clamp(0f64, (n_row - 1) as f64),
+        );
+        let (j0, i0) = (u.floor() as usize, v.floor() as usize);
+        let (j1, i1) = ((j0 + 1).min(n_col - 1), (i0 + 1).min(n_row - 1));
+        let (du, dv) = (u - j0 as f64, v - i0 as f64);
+        let t = |i: usize, j: usize| self.transmission[i * n_col + j];
+        (1. - dv) * ((1. - du) * t(i0, j0) + du * t(i0, j1))
+            + dv * ((1. - du) * t(i1, j0) + du * t(i1, j1))
+    }
+}
+/// Checks that a length is a positive finite number
+fn check_length(name: &str, length: f64) -> Result<()> {
+    if length.is_finite() && length > 0f64 {
+        Ok(())
+    } else {
+        Err(Error::PupilMask(format!(
+            "the {name} must be a positive finite number of meters, found {length}"
+        )))
+    }
+}
+impl Display for PupilMask {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {}m diameter, {:.3}m^2 collection area",
+            self.name,
+            self.diameter,
+            self.area()
+        )
+    }
+}
+impl Observer for PupilMask {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn diameter(&self) -> f64 {
+        self.diameter
+    }
+
+    fn resolution(&self) -> f64 {
+        self.resolution
+    }
+
+    fn inside_pupil(&self, x: f64, y: f64) -> bool {
+        self.transmission(x, y) > 0f64
+    }
+
+    fn transmission(&self, x: f64, y: f64) -> f64 {
+        // # of map samples across a pupil sample
+        let n = (self.resolution / self.pixel_size).ceil().max(1f64) as usize;
+        if n == 1 {
+            return self.interpolate(x, y);
+        }
+        let d = self.resolution / n as f64;
+        let o = 0.5 * (self.resolution - d);
+        (0..n)
+            .flat_map(|i| (0..n).map(move |j| (i, j)))
+            .map(|(i, j)| self.interpolate(x - o + j as f64 * d, y - o + i as f64 * d))
+            .sum::<f64>()
+            / (n * n) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resampling() {
+        // 1m x 2m rectangle with a 50% transmission lower half
+        let (n_row, n_col) = (200, 100);
+        let transmission: Vec<f64> = (0..n_row * n_col)
+            .map(|k| if k / n_col < n_row / 2 { 1. } else { 0.5 })
+            .collect();
+        let mask = PupilMask::new(transmission, (n_row, n_col), 1e-2)
+            .unwrap()
+            .pupil_sampling(5e-2)
+            .unwrap();
+        assert_eq!(mask.diameter(), 2.);
+        assert!((mask.transmission(0., 0.5) - 1.).abs() < 1e-12);
+        assert!((mask.transmission(0., -0.5) - 0.5).abs() < 1e-12);
+        assert_eq!(mask.transmission(0.75, 0.), 0.);
+        assert!(mask.transmission(0., 0.) > 0.5 && mask.transmission(0., 0.) < 1.);
+        let area = 1. + 0.25;
+        assert!((mask.area() / area - 1.).abs() < 0.05);
+    }
+
+    #[test]
+    fn lengths() {
+        let new = |pixel_size: f64| PupilMask::new(vec![1f64; 4], (2, 2), pixel_size);
+        for pixel_size in [0., -1e-2, f64::NAN, f64::INFINITY] {
+            assert!(matches!(new(pixel_size), Err(Error::PupilMask(_))));
+        }
+        for resolution in [0., -1e-2, f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                new(1e-2).unwrap().pupil_sampling(resolution),
+                Err(Error::PupilMask(_))
+            ));
+        }
+        assert_eq!(
+            new(1e-2)
+                .unwrap()
+                .pupil_sampling(5e-3)
+                .unwrap()
+                .resolution(),
+            5e-3
+        );
+    }
+}
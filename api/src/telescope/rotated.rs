@@ -0,0 +1,122 @@
+use std::{fmt::Display, ops::Deref};
+
+use serde::Serialize;
+use skyangle::SkyAngle;
+
+use crate::Observer;
+
+/// Rotated pupil
+///
+/// An [observer](Observer) with its pupil rotated counterclockwise
+///
+/// # Example
+/// ```
+/// use eyepiece::{Gmt, Observer};
+/// use skyangle::SkyAngle;
+///
+/// let gmt = Gmt::new().rotate(SkyAngle::Degree(30.));
+/// assert_eq!(gmt.pupil_rotation(), 30f64.to_radians());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Rotated<T: Observer> {
+    observer: T,
+    /// rotation angle [rd]
+    angle: f64,
+}
+impl<T: Observer> Deref for Rotated<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.observer
+    }
+}
+impl<T: Observer> Rotated<T> {
+    /// Creates a new rotated pupil
+    pub fn new(observer: T, angle: SkyAngle<f64>) -> Self {
+        Self {
+            observer,
+            angle: angle.to_radians(),
+        }
+    }
+    /// Returns the coordinates of a point in the frame of the observer pupil
+    fn derotate(&self, x: f64, y: f64) -> (f64, f64) {
+        let (s, c) = self.angle.sin_cos();
+        (x * c + y * s, -x * s + y * c)
+    }
+}
+impl<T: Observer + Display> Display for Rotated<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} rotated by {:.3}degree",
+            self.observer,
+            self.angle.to_degrees()
+        )
+    }
+}
+impl<T: Observer> Observer for Rotated<T> {
+    fn name(&self) -> String {
+        self.observer.name()
+    }
+
+    fn diameter(&self) -> f64 {
+        self.observer.diameter()
+    }
+
+    fn resolution(&self) -> f64 {
+        self.observer.resolution()
+    }
+
+    fn supersampling(&self) -> usize {
+        self.observer.supersampling()
+    }
+
+    fn pupil_rotation(&self) -> f64 {
+        self.observer.pupil_rotation() + self.angle
+    }
+
+    fn inside_pupil(&self, x: f64, y: f64) -> bool {
+        let (x, y) = self.derotate(x, y);
+        self.observer.inside_pupil(x, y)
+    }
+
+    fn transmission(&self, x: f64, y: f64) -> f64 {
+        let (x, y) = self.derotate(x, y);
+        self.observer.transmission(x, y)
+    }
+
+    fn optical_path_difference(&self, x: f64, y: f64) -> f64 {
+        let (x, y) = self.derotate(x, y);
+        self.observer.optical_path_difference(x, y)
+    }
+
+    fn optical_path_difference_map(&self) -> Option<Vec<f64>> {
+        // the rotated map is null if the map of the observer is
+        self.observer.optical_path_difference_map()?;
+        crate::optical_path_difference_map(self)
+    }
+}
+impl<T: Observer + Serialize> Serialize for Rotated<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.observer.serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Spider, Telescope};
+
+    #[test]
+    fn rotation() {
+        let tel = Telescope::new(8.).spider(Spider::new(1, 0.2)).build();
+        assert!(!tel.inside_pupil(2., 0.));
+        let rotated = tel.rotate(SkyAngle::Degree(90.));
+        assert!(rotated.inside_pupil(2., 0.));
+        assert!(!rotated.inside_pupil(0., 2.));
+        assert!((rotated.area() - tel.area()).abs() / tel.area() < 1e-3);
+    }
+}
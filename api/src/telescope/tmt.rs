@@ -0,0 +1,59 @@
+use std::fmt::Display;
+
+use super::hexagonal::HexagonalSegments;
+use crate::Observer;
+
+#[derive(Debug, Clone)]
+/// Thirty Meter Telescope
+///
+/// The primary mirror is made of 492 hexagonal segments, 1.44m corner-to-corner
+pub struct Tmt(HexagonalSegments);
+impl Tmt {
+    /// Creates a Thirty Meter Telescope object
+    pub fn new() -> Self {
+        Self(HexagonalSegments::new(1.44 * 3f64.sqrt() * 0.5, 1.5, 14.5))
+    }
+    /// Returns the number of segments
+    pub fn n_segment(&self) -> usize {
+        self.0.len()
+    }
+}
+impl Default for Tmt {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Display for Tmt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "TMT: {:.2}m diameter ({} segments), {:.3}m^2 collection area",
+            self.diameter(),
+            self.0.len(),
+            self.area()
+        )
+    }
+}
+impl Observer for Tmt {
+    fn name(&self) -> String {
+        String::from("TMT")
+    }
+
+    fn diameter(&self) -> f64 {
+        self.0.diameter()
+    }
+
+    fn inside_pupil(&self, x: f64, y: f64) -> bool {
+        self.0.inside_pupil(x, y)
+    }
+}
+
+use serde::ser::{Serialize, Serializer};
+impl Serialize for Tmt {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        "TMT".serialize(serializer)
+    }
+}
@@ -0,0 +1,253 @@
+use std::fmt::Display;
+
+use serde::Serialize;
+
+use crate::{Error, Result};
+
+/// Turbulence layer
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct TurbulenceLayer {
+    /// altitude [m]
+    pub height: f64,
+    /// fraction of the turbulence strength
+    pub weight: f64,
+    /// wind speed [m/s] and direction [rad], counterclockwise from the x axis
+    pub wind: Option<(f64, f64)>,
+}
+impl TurbulenceLayer {
+    /// Creates a new layer at the altitude `height` in meters with the fractional weight `weight`
+    pub fn new(height: f64, weight: f64) -> Self {
+        Self {
+            height,
+            weight,
+            wind: None,
+        }
+    }
+    /// Sets the wind speed in m/s and the wind direction in radians
+    pub fn wind(self, speed: f64, direction: f64) -> Self {
+        Self {
+            wind: Some((speed, direction)),
+            ..self
+        }
+    }
+}
+
+/// Multi-layer turbulence profile
+///
+/// The profile is a set of thin turbulence [layers](TurbulenceLayer), each with a fraction
+/// of the turbulence strength (Cn² integral), the fractions summing to 1.
+/// The default profile is the [Las Campanas](TurbulenceProfile::las_campanas) profile.
+///
+/// # Example
+/// ```
+/// use eyepiece::{SeeingBuilder, TurbulenceLayer, TurbulenceProfile};
+///
+/// let profile = TurbulenceProfile::new(vec![
+///     TurbulenceLayer::new(0., 0.6).wind(5., 0.),
+///     TurbulenceLayer::new(10e3, 0.4).wind(25., 90f64.to_radians()),
+/// ])
+/// .unwrap();
+/// let seeing = SeeingBuilder::new(16e-2).turbulence_profile(profile);
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TurbulenceProfile {
+    name: String,
+    layers: Vec<TurbulenceLayer>,
+}
+impl Default for TurbulenceProfile {
+    fn default() -> Self {
+        Self::las_campanas()
+    }
+}
+impl TurbulenceProfile {
+    /// Creates a new turbulence profile from the turbulence layers
+    ///
+    /// The layer weights are normalized to sum to 1.
+    /// Returns an [error](crate::Error) if there is no layer, or if any of the weights is negative or
+    /// if they sum to 0
+    pub fn new(layers: Vec<TurbulenceLayer>) -> Result<Self> {
+        if layers.is_empty() {
+            return Err(Error::TurbulenceProfile(String::from(
+                "no turbulence layer",
+            )));
+        }
+        if layers.iter().any(|layer| layer.weight < 0f64) {
+            return Err(Error::TurbulenceProfile(String::from(
+                "the layer weights must be positive",
+            )));
+        }
+        let sum: f64 = layers.iter().map(|layer| layer.weight).sum();
+        if sum <= 0f64 {
+            return Err(Error::TurbulenceProfile(String::from(
+                "the layer weights sum to 0",
+            )));
+        }
+        Ok(Self {
+            name: String::from("custom"),
+            layers: layers
+                .into_iter()
+                .map(|layer| TurbulenceLayer {
+                    weight: layer.weight / sum,
+                    ..layer
+                })
+                .collect(),
+        })
+    }
+    /// Creates a new turbulence profile from the layer altitudes in meters and the fractional weights
+    pub fn from_layers(heights: &[f64], weights: &[f64]) -> Result<Self> {
+        if heights.len() != weights.len() {
+            return Err(Error::TurbulenceProfile(format!(
+                "the number of heights ({}) and of weights ({}) do not match",
+                heights.len(),
+                weights.len()
+            )));
+        }
+        Self::new(
+            heights
+                .iter()
+                .zip(weights)
+                .map(|(&h, &w)| TurbulenceLayer::new(h, w))
+                .collect(),
+        )
+    }
+    fn named(self, name: &str) -> Self {
+        Self {
+            name: name.into(),
+            ..self
+        }
+    }
+    /// Las Campanas median 7 layers profile (GMT site)
+    pub fn las_campanas() -> Self {
+        Self::from_layers(
+            &[25., 275., 425., 1250., 4000., 8000., 13000.],
+            &[0.1257, 0.0874, 0.0666, 0.3498, 0.2273, 0.0681, 0.0751],
+        )
+        .unwrap()
+        .named("Las Campanas")
+    }
+    /// Hufnagel-Valley 5/7 profile sampled with `n_layer` layers evenly spaced from 0 to 20km
+    pub fn hufnagel_valley(n_layer: usize) -> Self {
+        let n_layer = n_layer.max(1);
+        let cn2 = |h: f64| {
+            0.00594 * (21f64 / 27.).powi(2) * (1e-5 * h).powi(10) * (-h / 1000.).exp()
+                + 2.7e-16 * (-h / 1500.).exp()
+                + 1.7e-14 * (-h / 100.).exp()
+        };
+        // each layer integrates the Cn² over a slab centered on the layer
+        let dh = 20e3 / n_layer as f64;
+        let n_sub = 100;
+        let layers: Vec<_> = (0..n_layer)
+            .map(|i| {
+                let h0 = i as f64 * dh;
+                let weight = (0..n_sub)
+                    .map(|k| cn2(h0 + (k as f64 + 0.5) * dh / n_sub as f64))
+                    .sum::<f64>();
+                TurbulenceLayer::new(h0 + 0.5 * dh, weight)
+            })
+            .collect();
+        Self::new(layers).unwrap().named("Hufnagel-Valley 5/7")
+    }
+    /// Single layer profile at the altitude `height` in meters
+    pub fn single_layer(height: f64) -> Self {
+        Self::new(vec![TurbulenceLayer::new(height, 1f64)])
+            .unwrap()
+            .named("single layer")
+    }
+    /// Ground layer profile
+    pub fn ground_layer() -> Self {
+        Self::single_layer(0f64).named("ground layer")
+    }
+    /// Sets the wind speeds in m/s and directions in radians of all the layers
+    ///
+    /// Returns an [error](crate::Error) if the number of winds and of layers do not match
+    pub fn wind(self, wind: &[(f64, f64)]) -> Result<Self> {
+        if wind.len() != self.layers.len() {
+            return Err(Error::TurbulenceProfile(format!(
+                "the number of winds ({}) and of layers ({}) do not match",
+                wind.len(),
+                self.layers.len()
+            )));
+        }
+        Ok(Self {
+            layers: self
+                .layers
+                .into_iter()
+                .zip(wind)
+                .map(|(layer, &(speed, direction))| layer.wind(speed, direction))
+                .collect(),
+            ..self
+        })
+    }
+    /// Sets the wind speeds of all the layers according to the Bufton wind model
+    /// with a ground wind speed of 5m/s and the same direction in radians for all the layers
+    pub fn bufton_wind(self, direction: f64) -> Self {
+        Self {
+            layers: self
+                .layers
+                .into_iter()
+                .map(|layer| {
+                    let speed = 5. + 30. * (-((layer.height - 9400.) / 4800.).powi(2)).exp();
+                    layer.wind(speed, direction)
+                })
+                .collect(),
+            ..self
+        }
+    }
+    /// Scales the layer altitudes by the airmass `1/cos(zenith angle)`
+    pub(crate) fn scale_height(self, airmass: f64) -> Self {
+        Self {
+            layers: self
+                .layers
+                .into_iter()
+                .map(|layer| TurbulenceLayer {
+                    height: layer.height * airmass,
+                    ..layer
+                })
+                .collect(),
+            ..self
+        }
+    }
+    /// Returns the profile name
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+    /// Returns the turbulence layers
+    pub fn layers(&self) -> &[TurbulenceLayer] {
+        self.layers.as_slice()
+    }
+    /// Returns an iterator over the (altitude, weight) pairs of the layers
+    pub fn iter(&self) -> impl Iterator<Item = (f64, f64)> + '_ {
+        self.layers.iter().map(|layer| (layer.height, layer.weight))
+    }
+}
+impl Display for TurbulenceProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} turbulence profile with {} layers",
+            self.name,
+            self.layers.len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profile() {
+        let profile = TurbulenceProfile::from_layers(&[0., 1e3], &[3., 1.]).unwrap();
+        assert_eq!(profile.layers()[0].weight, 0.75);
+        assert!(TurbulenceProfile::from_layers(&[0., 1e3], &[1.]).is_err());
+        let weights: f64 = TurbulenceProfile::hufnagel_valley(10)
+            .iter()
+            .map(|(_, w)| w)
+            .sum();
+        assert!((weights - 1.).abs() < 1e-12);
+        let profile = TurbulenceProfile::las_campanas().bufton_wind(0.);
+        assert!(profile.layers().iter().all(|layer| layer.wind.is_some()));
+        let heights: Vec<_> = profile.scale_height(2.).iter().map(|(h, _)| h).collect();
+        assert_eq!(heights[0], 50.);
+    }
+}
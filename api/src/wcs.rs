@@ -0,0 +1,152 @@
+use serde::Serialize;
+use skyangle::SkyAngle;
+
+use crate::FitsHeader;
+
+/// World Coordinate System
+///
+/// Gnomonic projection (`RA---TAN`, `DEC--TAN`) of the sky onto the field, centered on
+/// the pointing coordinates (right ascension and declination) in degrees.
+///
+/// The field coordinates `(x,y)` of a [Star](crate::Star) are along the first and second image axis, respectively.
+/// With a null position angle, north is up (+y) and east is left (-x).
+/// The position angle is the angle from north toward east of the +y axis.
+///
+/// # Example
+/// ```
+/// use eyepiece::Wcs;
+/// use skyangle::SkyAngle;
+///
+/// let wcs = Wcs::new((273.991, -32.217))
+///     .position_angle(SkyAngle::Degree(30.))
+///     .image(SkyAngle::MilliArcsec(5f64).to_radians(), 201);
+/// ```
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Wcs {
+    /// (right ascension, declination) of the reference pixel [degree]
+    crval: (f64, f64),
+    /// position angle [degree]
+    position_angle: f64,
+    /// reference pixel (1-based)
+    crpix: (f64, f64),
+    /// pixel scale [degree]
+    cdelt: f64,
+    /// linear transformation from pixels to intermediate world coordinates [degree]
+    cd: [[f64; 2]; 2],
+}
+impl Wcs {
+    /// Creates a new WCS from the pointing right ascension and declination in degrees
+    pub fn new(crval: (f64, f64)) -> Self {
+        Self {
+            crval,
+            position_angle: 0f64,
+            crpix: (1f64, 1f64),
+            cdelt: 0f64,
+            cd: [[0f64; 2]; 2],
+        }
+    }
+    /// Sets the position angle
+    pub fn position_angle(mut self, position_angle: SkyAngle<f64>) -> Self {
+        self.position_angle = position_angle.to_radians().to_degrees();
+        self.update_cd();
+        self
+    }
+    /// Rotates the image by adding the angle to the position angle
+    pub fn rotate(self, angle: SkyAngle<f64>) -> Self {
+        let position_angle = self.position_angle + angle.to_radians().to_degrees();
+        self.position_angle(SkyAngle::Degree(position_angle))
+    }
+    /// Sets the image pixel scale in radians and the image size in pixels
+    ///
+    /// The reference pixel is set at the center of the image
+    pub fn image(mut self, pixel_scale: f64, n_px: usize) -> Self {
+        let c = 0.5 * (n_px + 1) as f64;
+        self.crpix = (c, c);
+        self.cdelt = pixel_scale.to_degrees();
+        self.update_cd();
+        self
+    }
+    fn update_cd(&mut self) {
+        let (s, c) = self.position_angle.to_radians().sin_cos();
+        let d = self.cdelt;
+        self.cd = [[-d * c, d * s], [d * s, d * c]];
+    }
+    /// Returns the reference (right ascension, declination) in degrees
+    pub fn crval(&self) -> (f64, f64) {
+        self.crval
+    }
+    /// Returns the reference pixel
+    pub fn crpix(&self) -> (f64, f64) {
+        self.crpix
+    }
+    /// Returns the CD matrix in degrees
+    pub fn cd(&self) -> [[f64; 2]; 2] {
+        self.cd
+    }
+    /// Returns the (right ascension, declination) in degrees of a pixel (1-based)
+    pub fn pixel_to_world(&self, p1: f64, p2: f64) -> (f64, f64) {
+        let (dp1, dp2) = (p1 - self.crpix.0, p2 - self.crpix.1);
+        // intermediate world coordinates
+        let xi = (self.cd[0][0] * dp1 + self.cd[0][1] * dp2).to_radians();
+        let eta = (self.cd[1][0] * dp1 + self.cd[1][1] * dp2).to_radians();
+        // gnomonic deprojection
+        let (ra0, dec0) = (self.crval.0.to_radians(), self.crval.1.to_radians());
+        let (s_dec0, c_dec0) = dec0.sin_cos();
+        let d = c_dec0 - eta * s_dec0;
+        let ra = ra0 + xi.atan2(d);
+        let dec = (s_dec0 + eta * c_dec0).atan2(xi.hypot(d));
+        (ra.to_degrees().rem_euclid(360.), dec.to_degrees())
+    }
+    /// Returns the WCS [FITS](crate::FitsWriter) header keywords
+    pub fn header(&self) -> FitsHeader {
+        FitsHeader::new()
+            .keyword("WCSAXES", 2usize, "number of WCS axes")
+            .keyword("CTYPE1", "RA---TAN", "gnomonic projection")
+            .keyword("CTYPE2", "DEC--TAN", "gnomonic projection")
+            .keyword("CUNIT1", "deg", "")
+            .keyword("CUNIT2", "deg", "")
+            .keyword(
+                "CRVAL1",
+                self.crval.0,
+                "right ascension of reference pixel [deg]",
+            )
+            .keyword(
+                "CRVAL2",
+                self.crval.1,
+                "declination of reference pixel [deg]",
+            )
+            .keyword("CRPIX1", self.crpix.0, "reference pixel along axis 1")
+            .keyword("CRPIX2", self.crpix.1, "reference pixel along axis 2")
+            .keyword("CD1_1", self.cd[0][0], "")
+            .keyword("CD1_2", self.cd[0][1], "")
+            .keyword("CD2_1", self.cd[1][0], "")
+            .keyword("CD2_2", self.cd[1][1], "")
+            .keyword("RADESYS", "ICRS", "")
+            .keyword("PA", self.position_angle, "position angle [deg]")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pixel_to_world() {
+        let alpha = SkyAngle::Arcsecond(1f64);
+        let wcs = Wcs::new((10., 20.)).image(alpha.to_radians(), 101);
+        assert_eq!(wcs.crpix(), (51., 51.));
+        assert_eq!(wcs.pixel_to_world(51., 51.), (10., 20.));
+        // north up
+        let (ra, dec) = wcs.pixel_to_world(51., 61.);
+        assert!((ra - 10.).abs() < 1e-12 && (dec - 20. - 10. / 3600.).abs() < 1e-9);
+        // east left
+        let (ra, _) = wcs.pixel_to_world(41., 51.);
+        assert!(ra > 10.);
+        // position angle of 90 degree: north to the right, east up
+        let wcs = wcs.position_angle(SkyAngle::Degree(90.));
+        let (_, dec) = wcs.pixel_to_world(61., 51.);
+        assert!((dec - 20. - 10. / 3600.).abs() < 1e-9);
+        let (ra, dec) = wcs.pixel_to_world(51., 61.);
+        assert!(ra > 10. && (dec - 20.).abs() < 1e-6);
+    }
+}
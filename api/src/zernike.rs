@@ -0,0 +1,282 @@
+use std::fmt::Display;
+
+use serde::Serialize;
+
+use crate::{Error, FitsHeader, Observer, Result};
+
+/// Zernike static aberrations
+///
+/// Static wavefront aberrations given as the coefficients, in nm RMS, of the Zernike
+/// polynomials following Noll's ordering and normalization (each polynomial has a unit
+/// RMS over the unit disk).
+/// The polynomials are defined over the disk of diameter [Observer::diameter].
+///
+/// # Example
+/// ```
+/// use eyepiece::{Builder, Field, FieldBuilder, Hst, Zernike};
+///
+/// let aberrations = Zernike::new().defocus(50.).astigmatism(20., -10.).spherical(15.);
+/// let field: Field<Hst> = FieldBuilder::new(Hst::new())
+///     .aberrations(aberrations)
+///     .build();
+/// ```
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Zernike {
+    /// (Noll index, coefficient [nm RMS]) pairs sorted by increasing Noll index
+    coefficients: Vec<(usize, f64)>,
+}
+impl Zernike {
+    /// Creates an aberration-free wavefront
+    pub fn new() -> Self {
+        Default::default()
+    }
+    /// Creates the aberrations from the coefficients in nm RMS, starting with piston (`j=1`)
+    pub fn from_coefficients(coefficients: &[f64]) -> Self {
+        coefficients
+            .iter()
+            .enumerate()
+            .fold(Self::new(), |z, (i, &c)| z.mode(i + 1, c))
+    }
+    /// Sets the coefficient in nm RMS of the mode with Noll index `j` (`j>=1`)
+    ///
+    /// # Panics
+    /// Panics if `j` is 0, see [Zernike::try_mode]
+    pub fn mode(self, j: usize, nm_rms: f64) -> Self {
+        self.try_mode(j, nm_rms).unwrap_or_else(|e| panic!("{e}"))
+    }
+    /// Sets the coefficient in nm RMS of the mode with Noll index `j` (`j>=1`)
+    ///
+    /// Returns an [error](crate::Error) if `j` is 0
+    pub fn try_mode(mut self, j: usize, nm_rms: f64) -> Result<Self> {
+        if j == 0 {
+            return Err(Error::NollIndex(j));
+        }
+        match self.coefficients.binary_search_by_key(&j, |&(k, _)| k) {
+            Ok(k) => self.coefficients[k].1 = nm_rms,
+            Err(k) => self.coefficients.insert(k, (j, nm_rms)),
+        }
+        Ok(self)
+    }
+    /// Sets the tip (`j=2`) and tilt (`j=3`) in nm RMS
+    pub fn tip_tilt(self, tip: f64, tilt: f64) -> Self {
+        self.mode(2, tip).mode(3, tilt)
+    }
+    /// Sets the defocus (`j=4`) in nm RMS
+    pub fn defocus(self, defocus: f64) -> Self {
+        self.mode(4, defocus)
+    }
+    /// Sets the oblique (`j=5`) and vertical (`j=6`) astigmatism in nm RMS
+    pub fn astigmatism(self, oblique: f64, vertical: f64) -> Self {
+        self.mode(5, oblique).mode(6, vertical)
+    }
+    /// Sets the vertical (`j=7`) and horizontal (`j=8`) coma in nm RMS
+    pub fn coma(self, vertical: f64, horizontal: f64) -> Self {
+        self.mode(7, vertical).mode(8, horizontal)
+    }
+    /// Sets the vertical (`j=9`) and oblique (`j=10`) trefoil in nm RMS
+    pub fn trefoil(self, vertical: f64, oblique: f64) -> Self {
+        self.mode(9, vertical).mode(10, oblique)
+    }
+    /// Sets the primary spherical aberration (`j=11`) in nm RMS
+    pub fn spherical(self, spherical: f64) -> Self {
+        self.mode(11, spherical)
+    }
+    /// Returns the (Noll index, coefficient [nm RMS]) pairs
+    pub fn coefficients(&self) -> &[(usize, f64)] {
+        self.coefficients.as_slice()
+    }
+    /// Returns the wavefront error in nm RMS, piston excluded
+    pub fn wavefront_error(&self) -> f64 {
+        self.coefficients
+            .iter()
+            .filter(|(j, _)| *j > 1)
+            .map(|(_, c)| c * c)
+            .sum::<f64>()
+            .sqrt()
+    }
+    /// Returns the radial order `n` and azimuthal frequency `m` of the mode with Noll index `j`
+    ///
+    /// Negative azimuthal frequencies correspond to the sine modes
+    ///
+    /// # Panics
+    /// Panics if `j` is 0, see [Zernike::try_noll]
+    pub fn noll(j: usize) -> (usize, i32) {
+        Self::try_noll(j).unwrap_or_else(|e| panic!("{e}"))
+    }
+    /// Returns the radial order `n` and azimuthal frequency `m` of the mode with Noll index `j`
+    ///
+    /// Returns an [error](crate::Error) if `j` is 0
+    pub fn try_noll(j: usize) -> Result<(usize, i32)> {
+        if j == 0 {
+            return Err(Error::NollIndex(j));
+        }
+        let mut n = 0;
+        while (n + 1) * (n + 2) / 2 < j {
+            n += 1;
+        }
+        // position of the mode within the radial order
+        let k = j - n * (n + 1) / 2 - 1;
+        let m = if n % 2 == 0 {
+            2 * k.div_ceil(2)
+        } else {
+            2 * (k / 2) + 1
+        } as i32;
+        if m != 0 && j % 2 == 1 {
+            Ok((n, -m))
+        } else {
+            Ok((n, m))
+        }
+    }
+    /// Returns the value of the mode with Noll index `j` at the polar coordinates `(r,o)` of the unit disk
+    pub fn polynomial(j: usize, r: f64, o: f64) -> f64 {
+        let (n, m) = Self::noll(j);
+        let a = m.unsigned_abs() as usize;
+        let factorial = |k: usize| (1..=k).map(|i| i as f64).product::<f64>();
+        let radial = (0..=(n - a) / 2)
+            .map(|s| {
+                let sign = if s % 2 == 0 { 1f64 } else { -1f64 };
+                sign * factorial(n - s)
+                    / (factorial(s) * factorial((n + a) / 2 - s) * factorial((n - a) / 2 - s))
+                    * r.powi((n - 2 * s) as i32)
+            })
+            .sum::<f64>();
+        if m == 0 {
+            ((n + 1) as f64).sqrt() * radial
+        } else if m > 0 {
+            (2. * (n + 1) as f64).sqrt() * radial * (a as f64 * o).cos()
+        } else {
+            (2. * (n + 1) as f64).sqrt() * radial * (a as f64 * o).sin()
+        }
+    }
+    /// Returns the optical path difference in meters at the pupil coordinates `(x,y)`
+    /// of a pupil of the given diameter
+    pub fn opd(&self, x: f64, y: f64, diameter: f64) -> f64 {
+        let r = 2. * x.hypot(y) / diameter;
+        let o = y.atan2(x);
+        self.coefficients
+            .iter()
+            .map(|&(j, c)| c * 1e-9 * Self::polynomial(j, r, o))
+            .sum()
+    }
+    /// Returns the optical path difference in meters sampled like the [observer pupil](Observer::pupil)
+    pub fn opd_map<T: Observer>(&self, observer: &T) -> Vec<f64> {
+        let diameter = observer.diameter();
+        let n_px = (diameter / observer.resolution()).round() as usize + 1;
+        let l = (n_px - 1) as f64;
+        (0..n_px)
+            .flat_map(|i| (0..n_px).map(move |j| (i, j)))
+            .map(|(i, j)| {
+                let x = (j as f64 / l - 0.5) * diameter;
+                let y = (i as f64 / l - 0.5) * diameter;
+                if observer.inside_pupil(x, y) {
+                    self.opd(x, y, diameter)
+                } else {
+                    0f64
+                }
+            })
+            .collect()
+    }
+    /// Returns the [FITS](crate::FitsWriter) header keywords
+    pub fn header(&self) -> FitsHeader {
+        let mut header = FitsHeader::new().keyword(
+            "WFE",
+            self.wavefront_error(),
+            "static wavefront error [nm RMS]",
+        );
+        for (j, c) in self.coefficients.iter() {
+            header.push(
+                &format!("ZERN{j}"),
+                *c,
+                &format!("Zernike coefficient #{j} [nm RMS]"),
+            );
+        }
+        header
+    }
+}
+impl Display for Zernike {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Zernike aberrations: {:.1}nm RMS wavefront error [",
+            self.wavefront_error()
+        )?;
+        let modes: Vec<_> = self
+            .coefficients
+            .iter()
+            .map(|(j, c)| format!("Z{j}={c:.1}"))
+            .collect();
+        write!(f, "{}]", modes.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{field::tests::hst_field, Builder, DiffractionLimited, Field, Hst};
+
+    #[test]
+    fn noll() {
+        assert!(Zernike::try_noll(0).is_err());
+        assert!(Zernike::new().try_mode(0, 10.).is_err());
+        let nm: Vec<_> = (1..=11).map(Zernike::noll).collect();
+        assert_eq!(
+            nm,
+            vec![
+                (0, 0),
+                (1, 1),
+                (1, -1),
+                (2, 0),
+                (2, -2),
+                (2, 2),
+                (3, -1),
+                (3, 1),
+                (3, -3),
+                (3, 3),
+                (4, 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn orthonormality() {
+        // inner product over the unit disk
+        let n = 401;
+        let inner = |a: usize, b: usize| {
+            let (mut s, mut k) = (0f64, 0usize);
+            for i in 0..n {
+                let y = 2. * i as f64 / (n - 1) as f64 - 1.;
+                for j in 0..n {
+                    let x = 2. * j as f64 / (n - 1) as f64 - 1.;
+                    let r = x.hypot(y);
+                    if r <= 1. {
+                        let o = y.atan2(x);
+                        s += Zernike::polynomial(a, r, o) * Zernike::polynomial(b, r, o);
+                        k += 1;
+                    }
+                }
+            }
+            s / k as f64
+        };
+        for j in 1..=11 {
+            assert!((inner(j, j) - 1.).abs() < 1e-2, "Z{j} RMS: {}", inner(j, j));
+        }
+        assert!(inner(4, 11).abs() < 1e-2);
+        assert!(inner(1, 4).abs() < 1e-2);
+        assert!(inner(7, 8).abs() < 1e-2);
+    }
+
+    #[test]
+    fn field() {
+        let peak = |aberrations: Zernike| {
+            let mut field: Field<Hst, DiffractionLimited> = hst_field()
+                .field_of_view(21)
+                .aberrations(aberrations)
+                .build();
+            field.intensity(None).into_iter().fold(0f64, f64::max)
+        };
+        let wfe = 40f64;
+        let strehl = peak(Zernike::new().defocus(wfe)) / peak(Zernike::new());
+        let marechal = (-(2. * std::f64::consts::PI * wfe * 1e-9 / 0.55e-6).powi(2)).exp();
+        assert!((strehl - marechal).abs() < 5e-2);
+    }
+}
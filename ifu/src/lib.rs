@@ -7,11 +7,11 @@ pub use slit::Slit;
 
 /// IFU throughput definition
 pub trait Throughput {
-    fn throughput(&self, field_image: &mut FieldImage) -> image::ImageResult<()>;
+    fn throughput(&self, field_image: &mut FieldImage) -> eyepiece::Result<()>;
 }
 
 impl Throughput for IFU {
-    fn throughput(&self, field_image: &mut FieldImage) -> image::ImageResult<()> {
+    fn throughput(&self, field_image: &mut FieldImage) -> eyepiece::Result<()> {
         let flux0 = field_image.flux();
         // IFU throughput
         field_image.masked(self);
@@ -30,7 +30,7 @@ impl Throughput for IFU {
 }
 
 impl Throughput for Telescope {
-    fn throughput(&self, field_image: &mut FieldImage) -> image::ImageResult<()> {
+    fn throughput(&self, field_image: &mut FieldImage) -> eyepiece::Result<()> {
         let flux0 = field_image.flux();
         // IFU throughput
         field_image.masked(self);
@@ -42,7 +42,7 @@ impl Throughput for Telescope {
 }
 
 impl Throughput for Slit {
-    fn throughput(&self, field_image: &mut FieldImage) -> image::ImageResult<()> {
+    fn throughput(&self, field_image: &mut FieldImage) -> eyepiece::Result<()> {
         let flux0 = field_image.flux();
         // IFU throughput
         field_image.masked(self);
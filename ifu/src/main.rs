@@ -1,6 +1,7 @@
 use clap::{Parser, Subcommand};
 use eyepiece::{
-    Builder, Field, FieldBuilder, FieldImage, Gmt, SeeingBuilder, SeeingLimited, Telescope,
+    AtmosphericRefraction, Builder, Field, FieldBuilder, FieldImage, Gmt, SeeingBuilder,
+    SeeingLimited, Telescope,
 };
 use ifu::{Slit, Throughput, IFU};
 use skyangle::SkyAngle;
@@ -17,6 +18,12 @@ struct Cli {
     /// Photometric band, one of V,R,I,J,H,K
     #[arg(short, long, default_value_t = String::from("V"))]
     band: String,
+    /// Number of wavelengths sampling the photometric band
+    #[arg(long, default_value_t = 1)]
+    bandwidth_sampling: usize,
+    /// Parallactic angle [deg], enables the atmospheric dispersion
+    #[arg(short, long)]
+    parallactic_angle: Option<f64>,
     #[command(subcommand)]
     ifu: Option<IfuKind>,
 }
@@ -52,7 +59,14 @@ fn main() -> anyhow::Result<()> {
     // the telescope
     let tel = Gmt::new();
     // the seeing
-    let seeing = SeeingBuilder::new(cli.r0 * 1e-2).zenith_angle(SkyAngle::Degree(cli.zenith_angle));
+    let mut seeing =
+        SeeingBuilder::new(cli.r0 * 1e-2).zenith_angle(SkyAngle::Degree(cli.zenith_angle));
+    // the atmospheric dispersion
+    if let Some(parallactic_angle) = cli.parallactic_angle {
+        seeing = seeing.atmospheric_refraction(
+            AtmosphericRefraction::new().parallactic_angle(SkyAngle::Degree(parallactic_angle)),
+        );
+    }
     // image pixel scale (1mas)
     let px = SkyAngle::MilliArcsec(1f64);
     // image size (6arsec x 6 arcsec)
@@ -63,6 +77,7 @@ fn main() -> anyhow::Result<()> {
         .pixel_scale(px)
         .field_of_view(n_px)
         .photometry(cli.band)
+        .bandwidth_sampling(cli.bandwidth_sampling)
         .seeing_limited(seeing)
         .build();
 
@@ -31,6 +31,7 @@ async fn main() -> anyhow::Result<()> {
         .unwrap();
     println!("{objects}");
     let n_star = objects.len();
+    let wcs = objects.wcs();
 
     let stars: eyepiece::Objects = objects.into();
     println!("{stars}");
@@ -43,6 +44,7 @@ async fn main() -> anyhow::Result<()> {
         .field_of_view(SkyAngle::Arcminute(fov_arcmin))
         .photometry(band)
         .objects(stars)
+        .wcs(wcs)
         .seeing_limited(
             SeeingBuilder::new(16e-2)
                 .zenith_angle(SkyAngle::Degree(30.))
@@ -99,6 +99,25 @@ impl MastObjects {
     pub fn len(&self) -> usize {
         self.objects.len()
     }
+    /// Returns the (ra,dec) coordinates in degree of the queried object
+    pub fn origin(&self) -> (f64, f64) {
+        self.origin
+    }
+    /// Returns the [world coordinate system](eyepiece::Wcs) of the field centered on the queried object
+    pub fn wcs(&self) -> eyepiece::Wcs {
+        self.into()
+    }
+}
+
+impl From<&MastObjects> for eyepiece::Wcs {
+    /// Converts a [MastObjects] into a [eyepiece::Wcs]
+    ///
+    /// The star coordinates of [eyepiece::Objects] derived from [MastObjects] are the offsets
+    /// from the stars to the origin, i.e. along the south and west directions,
+    /// corresponding to a -90° position angle
+    fn from(mast_objects: &MastObjects) -> Self {
+        eyepiece::Wcs::new(mast_objects.origin).position_angle(SkyAngle::Degree(-90.))
+    }
 }
 
 impl From<MastObjects> for eyepiece::Objects {
@@ -138,6 +157,26 @@ mod tests {
         assert!(pos_angle.to_radians() - 0.0349453518 < 1e-9);
     }
 
+    #[test]
+    fn wcs() {
+        let objects = MastObjects {
+            target: "test".into(),
+            origin: (12., 85.),
+            radius: 1e-3,
+            objects: vec![],
+            photometry: GaiaPhotometry::V,
+        };
+        let o = MastObject::from(objects.origin);
+        let c = MastObject::from((12.001, 85.0002));
+        let (x, y) = c.offsets(&o);
+        let alpha = SkyAngle::MilliArcsec(1f64).to_radians();
+        let wcs = objects.wcs().image(alpha, 1001);
+        let (cx, cy) = wcs.crpix();
+        let (ra, dec) =
+            wcs.pixel_to_world(cx + x.to_radians() / alpha, cy + y.to_radians() / alpha);
+        assert!((ra - c.ra).abs() < 1e-6 && (dec - c.dec).abs() < 1e-6);
+    }
+
     #[test]
     fn offsets() {
         let c = MastObject::from((12.11, 85.26));